@@ -0,0 +1,31 @@
+//! The reference sync server of `libocc`, hosting named projectors over a RESTful HTTP API.
+//!
+//! Usage: `libocc-server [--bind <address>]` (defaults to `127.0.0.1:8080`)
+
+use anyhow::{bail, Result};
+use libocc::server::Server;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Parse the command line arguments
+    let mut address = String::from("127.0.0.1:8080");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bind", Some(value)) => address = value,
+            _ => bail!("Usage: libocc-server [--bind <address>]"),
+        }
+    }
+
+    // Serve the requests until interrupted
+    let listener = TcpListener::bind(&address).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+
+    Arc::new(Server::new())
+        .serve(listener, async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+}
@@ -0,0 +1,58 @@
+use crate::{crdt::Crdt, events::ReplicaId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/**
+A counter supporting increments and decrements (PN-counter).
+
+Every replica counts its own increments and decrements, so concurrent
+modifications add up instead of overwriting each other.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PnCounter {
+    /// The sum of the increments of every replica
+    increments: BTreeMap<ReplicaId, u64>,
+
+    /// The sum of the decrements of every replica
+    decrements: BTreeMap<ReplicaId, u64>,
+}
+
+impl PnCounter {
+    /// Creates a new counter with a value of zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current value of the counter
+    pub fn get(&self) -> i64 {
+        let increments: u64 = self.increments.values().sum();
+        let decrements: u64 = self.decrements.values().sum();
+
+        increments as i64 - decrements as i64
+    }
+
+    /// Increments the counter on behalf of a replica
+    pub fn increment(&mut self, amount: u64, replica: &str) {
+        *self.increments.entry(replica.to_owned()).or_insert(0) += amount;
+    }
+
+    /// Decrements the counter on behalf of a replica
+    pub fn decrement(&mut self, amount: u64, replica: &str) {
+        *self.decrements.entry(replica.to_owned()).or_insert(0) += amount;
+    }
+}
+
+/// Merges two maps of counters using the maximum of every counter
+fn merge_max(own: &mut BTreeMap<ReplicaId, u64>, other: &BTreeMap<ReplicaId, u64>) {
+    for (replica, &count) in other {
+        let own = own.entry(replica.clone()).or_insert(0);
+        *own = (*own).max(count);
+    }
+}
+
+impl Crdt for PnCounter {
+    fn merge(&mut self, other: &Self) {
+        merge_max(&mut self.increments, &other.increments);
+        merge_max(&mut self.decrements, &other.decrements);
+    }
+}
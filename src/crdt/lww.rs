@@ -0,0 +1,63 @@
+use crate::{
+    crdt::Crdt,
+    events::{ReplicaId, Timestamp},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/**
+A last-writer-wins register.
+
+Concurrent assignments are ordered by their timestamps (and by the identifiers
+of their replicas, if their timestamps are equal), keeping the latest value.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LwwRegister<V> {
+    /// The current value
+    value: V,
+
+    /// The moment in time the value was assigned
+    timestamp: Timestamp,
+
+    /// The replica which assigned the value
+    replica: ReplicaId,
+}
+
+impl<V: Clone> LwwRegister<V> {
+    /// Creates a new register with a value assigned by a replica
+    pub fn new(value: V, replica: &str) -> Self {
+        Self {
+            value,
+            timestamp: Utc::now(),
+            replica: replica.to_owned(),
+        }
+    }
+
+    /// Returns a reference to the current value
+    pub fn get(&self) -> &V {
+        &self.value
+    }
+
+    /// Returns a reference to the moment in time the current value was assigned
+    pub fn get_time(&self) -> &Timestamp {
+        &self.timestamp
+    }
+
+    /// Assigns a new value on behalf of a replica
+    pub fn set(&mut self, value: V, replica: &str) {
+        // Never move back in time, so the assignment always wins locally
+        let timestamp = Utc::now().max(self.timestamp + chrono::Duration::nanoseconds(1));
+
+        self.value = value;
+        self.timestamp = timestamp;
+        self.replica = replica.to_owned();
+    }
+}
+
+impl<V: Clone> Crdt for LwwRegister<V> {
+    fn merge(&mut self, other: &Self) {
+        if (&other.timestamp, &other.replica) > (&self.timestamp, &self.replica) {
+            *self = other.clone();
+        }
+    }
+}
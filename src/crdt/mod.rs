@@ -0,0 +1,131 @@
+/*!
+This module contains conflict-free replicated data types (CRDTs).
+
+They're meant to be embedded as fields of entities, so concurrent updates of
+an entity can be merged field by field instead of letting the latest update
+win. Entities implement [`Crdt`] by merging their fields, and are merged
+by [`Projector::merge_crdt`], or automatically whenever events are merged
+after calling [`Projector::resolve_automatically`].
+
+All types are state-based: Merging is commutative, associative and
+idempotent, so every replica converges to the same state regardless of
+the order in which it receives the updates.
+
+[`Crdt`]: trait.Crdt.html
+[`Projector::merge_crdt`]: ../events/struct.Projector.html#method.merge_crdt
+[`Projector::resolve_automatically`]: ../events/struct.Projector.html#method.resolve_automatically
+*/
+
+mod counter;
+mod lww;
+mod mv;
+mod set;
+mod text;
+
+pub use counter::PnCounter;
+pub use lww::LwwRegister;
+pub use mv::MvRegister;
+pub use set::OrSet;
+pub use text::{Text, TextEdit, TextId, TextOperation, TextProjection};
+
+use crate::events::{Change, Conflict, Event, Projector};
+use anyhow::Result;
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// A conflict-free replicated data type
+pub trait Crdt {
+    /// Merges the state of another replica into this one
+    fn merge(&mut self, other: &Self);
+}
+
+impl<'a, T> Projector<'a, T>
+where
+    T: Clone + PartialEq + Serialize + Crdt,
+{
+    /// Resolves conflicts automatically on behalf of a replica whenever events are
+    /// merged (see [`merge`](#method.merge)), e.g. when delivered or synced
+    ///
+    /// Only the conflicts concerning the entities of the merged events are resolved.
+    /// See [`resolve_conflicts`](#method.resolve_conflicts) for details.
+    pub fn resolve_automatically(&mut self, replica: &str) {
+        self.set_resolver(replica, Self::resolve_merged);
+    }
+
+    /// Merges (possibly concurrent) events of other replicas, and resolves all
+    /// conflicts by merging the concurrent versions of their entities
+    ///
+    /// The returned changes contain the changes of the merged events, followed by
+    /// the changes of the resolutions.
+    pub fn merge_crdt(
+        &mut self,
+        events: Vec<Event<'a, T>>,
+        replica: &str,
+    ) -> Result<Vec<Change<'a, T>>> {
+        let mut changes = self.merge(events)?;
+        changes.extend(self.resolve_conflicts(replica)?);
+
+        Ok(changes)
+    }
+
+    /// Resolves all conflicts by merging the concurrent versions of their entities,
+    /// returning the resulting changes
+    ///
+    /// Every conflict (see [`get_conflicts`](#method.get_conflicts)) is resolved by
+    /// pushing an update stamped by the given replica, containing the merged
+    /// entity. Since merging is deterministic, replicas resolving the same conflict
+    /// independently agree on the entity, so such resolutions don't conflict again.
+    pub fn resolve_conflicts(&mut self, replica: &str) -> Result<Vec<Change<'a, T>>> {
+        let conflicts = self.get_conflicts();
+        self.resolve(conflicts, replica)
+    }
+
+    /// Resolves the conflicts concerning the entities of the changes of merged events
+    fn resolve_merged(
+        &mut self,
+        replica: &str,
+        merged: &[Change<'a, T>],
+    ) -> Result<Vec<Change<'a, T>>> {
+        let entities: Vec<&T> = merged.iter().map(|c| &**c.get_event()).collect();
+        let conflicts = self.get_conflicts_of(&entities);
+        self.resolve(conflicts, replica)
+    }
+
+    /// Resolves some conflicts by merging the concurrent versions of their entities
+    fn resolve(
+        &mut self,
+        conflicts: Vec<Conflict<'a, T>>,
+        replica: &str,
+    ) -> Result<Vec<Change<'a, T>>> {
+        let mut changes = vec![];
+
+        for conflict in conflicts {
+            let (first, others) = match conflict.get_events().split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+
+            // Merge the concurrent versions of the entity
+            let mut merged = T::clone(first);
+            for event in others {
+                merged.merge(event);
+            }
+
+            // Concurrent deletions and updates are ordered by their timestamps. If the
+            // deletion came last, there's nothing left to resolve. Otherwise, the entity
+            // is kept (with the state of the deleted version merged into it).
+            let old = match self.get_projection().iter().find(|e| ***e == merged) {
+                Some(old) => old.clone(),
+                None => continue,
+            };
+
+            // Resolve the conflict
+            let resolution = self.stamp(Event::update(Cow::Owned(merged)), replica);
+            self.push(resolution.clone())?;
+            let new = resolution.clone().take();
+            changes.push(Change::new(resolution, Some(old), Some(new)));
+        }
+
+        Ok(changes)
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{crdt::Crdt, events::VersionVector};
+use serde::{Deserialize, Serialize};
+
+/**
+A multi-value register.
+
+Unlike a [`LwwRegister`], concurrent assignments don't overwrite each other:
+The register keeps all of them, until a later assignment replaces them.
+
+[`LwwRegister`]: struct.LwwRegister.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MvRegister<V> {
+    /// The concurrent values along with the versions they were assigned at
+    values: Vec<(VersionVector, V)>,
+}
+
+impl<V: Clone + PartialEq> MvRegister<V> {
+    /// Creates a new register with a value assigned by a replica
+    pub fn new(value: V, replica: &str) -> Self {
+        let mut version = VersionVector::new();
+        version.increment(replica);
+
+        Self {
+            values: vec![(version, value)],
+        }
+    }
+
+    /// Returns references to all concurrent values
+    pub fn get(&self) -> Vec<&V> {
+        self.values.iter().map(|(_, value)| value).collect()
+    }
+
+    /// Checks if there are several concurrent values
+    pub fn is_conflicting(&self) -> bool {
+        self.values.len() > 1
+    }
+
+    /// Assigns a new value on behalf of a replica, replacing all concurrent values
+    pub fn set(&mut self, value: V, replica: &str) {
+        let mut version = VersionVector::new();
+        for (other, _) in &self.values {
+            version.merge(other);
+        }
+        version.increment(replica);
+
+        self.values = vec![(version, value)];
+    }
+}
+
+impl<V: Clone + PartialEq> Crdt for MvRegister<V> {
+    fn merge(&mut self, other: &Self) {
+        let mut values = self.values.clone();
+        for entry in &other.values {
+            if !values.contains(entry) {
+                values.push(entry.clone());
+            }
+        }
+
+        // Only keep values which weren't replaced by others
+        self.values = values
+            .iter()
+            .filter(|(version, _)| !values.iter().any(|(other, _)| version < other))
+            .cloned()
+            .collect();
+
+        // Keep the order deterministic
+        self.values
+            .sort_by(|a, b| a.0.get_counters().iter().cmp(b.0.get_counters().iter()));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{crdt::Crdt, events::ReplicaId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A unique tag of an addition to an [`OrSet`](struct.OrSet.html)
+type Tag = (ReplicaId, u64);
+
+/**
+An observed-remove set (OR-set).
+
+Every addition of an element is tagged uniquely, and removing an element only
+removes the additions observed by the removing replica. So if an element is
+added and removed concurrently, the addition wins.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OrSet<V> {
+    /// The elements along with the tags of their additions (ordered by their tags)
+    elements: Vec<(V, Tag)>,
+
+    /// The tags of all removed additions
+    removed: BTreeSet<Tag>,
+}
+
+impl<V: Clone + PartialEq> OrSet<V> {
+    /// Creates a new, empty set
+    pub fn new() -> Self {
+        Self {
+            elements: vec![],
+            removed: BTreeSet::new(),
+        }
+    }
+
+    /// Checks if the set contains an element
+    pub fn contains(&self, value: &V) -> bool {
+        self.elements.iter().any(|(v, _)| v == value)
+    }
+
+    /// Returns references to all elements (each one only once)
+    pub fn get(&self) -> Vec<&V> {
+        let mut values: Vec<&V> = vec![];
+        for (value, _) in &self.elements {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+
+        values
+    }
+
+    /// Adds an element on behalf of a replica
+    pub fn add(&mut self, value: V, replica: &str) {
+        // Use the next unused tag of the replica
+        let counter = self
+            .elements
+            .iter()
+            .map(|(_, tag)| tag)
+            .chain(self.removed.iter())
+            .filter(|(r, _)| r == replica)
+            .map(|(_, counter)| *counter)
+            .max()
+            .unwrap_or(0);
+
+        // Keep the additions ordered by their tags, like merging does
+        let tag = (replica.to_owned(), counter + 1);
+        let position = self.elements.partition_point(|(_, t)| *t < tag);
+        self.elements.insert(position, (value, tag));
+    }
+
+    /// Removes all observed additions of an element
+    pub fn remove(&mut self, value: &V) {
+        let removed = &mut self.removed;
+        self.elements.retain(|(v, tag)| {
+            if v == value {
+                removed.insert(tag.clone());
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl<V: Clone + PartialEq> Default for OrSet<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone + PartialEq> Crdt for OrSet<V> {
+    fn merge(&mut self, other: &Self) {
+        self.removed.extend(other.removed.iter().cloned());
+
+        for entry in &other.elements {
+            if !self.elements.iter().any(|(_, tag)| *tag == entry.1) {
+                self.elements.push(entry.clone());
+            }
+        }
+
+        // Drop removed additions and keep the order deterministic
+        let removed = &self.removed;
+        self.elements.retain(|(_, tag)| !removed.contains(tag));
+        self.elements.sort_by(|a, b| a.1.cmp(&b.1));
+    }
+}
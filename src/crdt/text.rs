@@ -0,0 +1,530 @@
+use crate::{
+    crdt::Crdt,
+    events::{Event, Projection, ReplicaId, Timestamp},
+};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    time::UNIX_EPOCH,
+};
+
+/// The unique identifier of a character inserted into a [`Text`](struct.Text.html)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextId {
+    /// A Lamport timestamp, greater than the ones of all characters known when inserting
+    counter: u64,
+
+    /// The replica which inserted the character
+    replica: ReplicaId,
+}
+
+impl TextId {
+    /// Returns the Lamport timestamp of the insertion
+    pub fn get_counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Returns a reference to the replica which inserted the character
+    pub fn get_replica(&self) -> &ReplicaId {
+        &self.replica
+    }
+}
+
+/// An operation on a [`Text`](struct.Text.html)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TextOperation {
+    /// Inserts a character
+    Insert {
+        /// The identifier of the new character
+        id: TextId,
+
+        /// The character the new one was inserted after (if any)
+        origin: Option<TextId>,
+
+        /// The new character
+        value: char,
+    },
+
+    /// Deletes a character
+    Delete {
+        /// The identifier of the deleted character
+        id: TextId,
+    },
+}
+
+/// A character of a text, along with its position in the sequence
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Character {
+    id: TextId,
+    origin: Option<TextId>,
+    value: char,
+    deleted: bool,
+}
+
+/**
+A text which can be edited concurrently (a replicated growable array, RGA).
+
+Every character is identified uniquely and remembers the character it was
+inserted after, so concurrent insertions and deletions never overwrite each
+other. Deleted characters are kept as tombstones, as later insertions may
+still refer to them.
+
+Edit a text using [`insert`] and [`delete`], which return the operations
+performed. Push them as the payload of a [`TextEdit`] event, so every edit only
+carries its own operations, and let a [`TextProjection`] apply them to the
+projected texts. Operations can also be applied to other copies of the text
+directly using [`apply`].
+
+Texts can also be embedded as fields of entities implementing [`Crdt`], so
+concurrent updates of the entire entity are merged character by character (see
+[`Projector::merge_crdt`]).
+
+[`insert`]: #method.insert
+[`delete`]: #method.delete
+[`apply`]: #method.apply
+[`TextEdit`]: struct.TextEdit.html
+[`TextProjection`]: struct.TextProjection.html
+[`Crdt`]: trait.Crdt.html
+[`Projector::merge_crdt`]: ../events/struct.Projector.html#method.merge_crdt
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Text {
+    /// All characters (including deleted ones) in their order
+    characters: Vec<Character>,
+}
+
+impl Text {
+    /// Creates a new, empty text
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of (not deleted) characters
+    pub fn len(&self) -> usize {
+        self.visible().count()
+    }
+
+    /// Checks if the text doesn't contain any (not deleted) characters
+    pub fn is_empty(&self) -> bool {
+        self.visible().next().is_none()
+    }
+
+    /// Inserts a string at a character index on behalf of a replica,
+    /// returning the operations performed
+    pub fn insert(
+        &mut self,
+        index: usize,
+        text: &str,
+        replica: &str,
+    ) -> Result<Vec<TextOperation>> {
+        let mut origin = match index {
+            0 => None,
+            _ => Some(
+                self.visible()
+                    .nth(index - 1)
+                    .ok_or_else(|| anyhow!("Cannot insert at index {} of a shorter text", index))?
+                    .id
+                    .clone(),
+            ),
+        };
+
+        // Follow all known characters
+        let latest = self
+            .characters
+            .iter()
+            .map(|c| c.id.counter)
+            .max()
+            .unwrap_or(0);
+
+        let mut operations = vec![];
+        for (counter, value) in (latest + 1..).zip(text.chars()) {
+            let id = TextId {
+                counter,
+                replica: replica.to_owned(),
+            };
+            operations.push(TextOperation::Insert {
+                id: id.clone(),
+                origin,
+                value,
+            });
+            origin = Some(id);
+        }
+
+        // Insert all characters at once
+        if !self.apply_all(&operations).is_empty() {
+            bail!("Cannot insert the characters")
+        }
+
+        Ok(operations)
+    }
+
+    /// Deletes a number of characters starting at a character index,
+    /// returning the operations performed
+    pub fn delete(&mut self, index: usize, count: usize) -> Result<Vec<TextOperation>> {
+        let ids: Vec<TextId> = self
+            .visible()
+            .skip(index)
+            .take(count)
+            .map(|c| c.id.clone())
+            .collect();
+        if ids.len() < count {
+            bail!("Cannot delete beyond the end of the text")
+        }
+
+        let operations: Vec<_> = ids
+            .into_iter()
+            .map(|id| TextOperation::Delete { id })
+            .collect();
+
+        // Delete all characters at once
+        if !self.apply_all(&operations).is_empty() {
+            bail!("Cannot delete the characters")
+        }
+
+        Ok(operations)
+    }
+
+    /// Applies an operation (e.g. received from another replica)
+    ///
+    /// The characters an operation refers to must be known already.
+    /// Applying a known operation again doesn't change anything.
+    ///
+    /// Applying an operation takes time linear in the length of the text (including
+    /// deleted characters), so use [`apply_all`](#method.apply_all) for several ones.
+    pub fn apply(&mut self, operation: &TextOperation) -> Result<()> {
+        match operation {
+            TextOperation::Insert { id, origin, value } => {
+                if self.position(id).is_some() {
+                    return Ok(());
+                }
+
+                let pos = self.insert_position(id, origin.as_ref())?;
+                self.characters.insert(
+                    pos,
+                    Character {
+                        id: id.clone(),
+                        origin: origin.clone(),
+                        value: *value,
+                        deleted: false,
+                    },
+                );
+            }
+            TextOperation::Delete { id } => {
+                let pos = self
+                    .position(id)
+                    .ok_or_else(|| anyhow!("Cannot delete an unknown character"))?;
+                self.characters[pos].deleted = true;
+            }
+        }
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Applies several operations in order (e.g. the ones of an edit), returning
+    /// the ones which can't be applied (yet)
+    ///
+    /// Runs of characters inserted right after each other (e.g. pasted ones) and
+    /// runs of deletions are applied at once, so applying them takes time linear in
+    /// the length of the text and the run (rather than their product).
+    pub fn apply_all(&mut self, operations: &[TextOperation]) -> Vec<TextOperation> {
+        let mut failed = vec![];
+        let mut rest = operations;
+
+        while !rest.is_empty() {
+            let (run, applied) = match &rest[0] {
+                TextOperation::Insert { .. } => {
+                    let run = Self::count_insertions(rest);
+                    (run, self.insert_run(&rest[..run]))
+                }
+                TextOperation::Delete { .. } => {
+                    let run = rest
+                        .iter()
+                        .take_while(|o| matches!(o, TextOperation::Delete { .. }))
+                        .count();
+                    (run, self.delete_run(&rest[..run]))
+                }
+            };
+
+            // Apply the operations of runs which can't be applied at once one by one
+            if !applied {
+                failed.extend(
+                    rest[..run]
+                        .iter()
+                        .filter(|operation| self.apply(operation).is_err())
+                        .cloned(),
+                );
+            }
+            rest = &rest[run..];
+        }
+
+        failed
+    }
+
+    /// Returns the number of leading insertions of characters inserted right after
+    /// the previous one (with a greater identifier)
+    fn count_insertions(operations: &[TextOperation]) -> usize {
+        let mut previous: Option<&TextId> = None;
+
+        operations
+            .iter()
+            .take_while(|operation| match (operation, previous) {
+                (TextOperation::Insert { id, origin, .. }, previous_id)
+                    if previous_id.is_none_or(|p| origin.as_ref() == Some(p) && id > p) =>
+                {
+                    previous = Some(id);
+                    true
+                }
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Inserts a run of characters inserted right after each other at once,
+    /// returning whether it succeeded (it fails if any of them is known already)
+    ///
+    /// As every character is greater than the previous one, the characters end
+    /// up next to each other, right where the first one is inserted.
+    fn insert_run(&mut self, run: &[TextOperation]) -> bool {
+        let known: HashSet<&TextId> = self.characters.iter().map(|c| &c.id).collect();
+        let mut characters = vec![];
+        for operation in run {
+            if let TextOperation::Insert { id, origin, value } = operation {
+                if known.contains(id) {
+                    return false;
+                }
+
+                characters.push(Character {
+                    id: id.clone(),
+                    origin: origin.clone(),
+                    value: *value,
+                    deleted: false,
+                });
+            }
+        }
+
+        let pos = match characters.first() {
+            Some(first) => match self.insert_position(&first.id, first.origin.as_ref()) {
+                Ok(pos) => pos,
+                Err(_) => return false,
+            },
+            None => return true,
+        };
+        self.characters.splice(pos..pos, characters);
+
+        true
+    }
+
+    /// Deletes a run of characters at once, returning whether it succeeded
+    /// (it fails if any of them is unknown)
+    fn delete_run(&mut self, run: &[TextOperation]) -> bool {
+        let ids: HashSet<&TextId> = run
+            .iter()
+            .filter_map(|operation| match operation {
+                TextOperation::Delete { id } => Some(id),
+                _ => None,
+            })
+            .collect();
+        let found = self
+            .characters
+            .iter()
+            .filter(|c| ids.contains(&c.id))
+            .count();
+        if found < ids.len() {
+            return false;
+        }
+
+        for character in &mut self.characters {
+            if ids.contains(&character.id) {
+                character.deleted = true;
+            }
+        }
+
+        true
+    }
+
+    /// Returns the internal position a new character is inserted at
+    fn insert_position(&self, id: &TextId, origin: Option<&TextId>) -> Result<usize> {
+        // Start right after the origin
+        let mut pos = match origin {
+            Some(origin) => {
+                self.position(origin)
+                    .ok_or_else(|| anyhow!("Cannot insert after an unknown character"))?
+                    + 1
+            }
+            None => 0,
+        };
+
+        // Skip the characters inserted concurrently by later (greater) insertions
+        while pos < self.characters.len() && self.characters[pos].id > *id {
+            pos += 1;
+        }
+
+        Ok(pos)
+    }
+
+    /// Returns the internal position of a character (including deleted ones)
+    fn position(&self, id: &TextId) -> Option<usize> {
+        self.characters.iter().position(|c| c.id == *id)
+    }
+
+    /// Returns an iterator over the (not deleted) characters
+    fn visible(&self) -> impl Iterator<Item = &Character> {
+        self.characters.iter().filter(|c| !c.deleted)
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for character in self.visible() {
+            write!(f, "{}", character.value)?;
+        }
+
+        // Return Ok
+        Ok(())
+    }
+}
+
+/**
+The operations of an edit of a [`Text`], carried as the payload of an event.
+
+A `TextEdit` entity stands for a text identified by a name: Create it using
+[`create_event`], then push an [`Event::Update`] containing the operations of
+every edit (e.g. a keystroke) instead of the entire text. As the creation is
+identical on all replicas, several replicas may create the same text offline.
+The events of several replicas are merged as usual (see [`Projector::merge`]),
+and a [`TextProjection`] applies their operations to the texts.
+
+Concurrent edits of a text are reported by [`Projector::get_conflicts`] like
+other concurrent updates, but they never need to be resolved.
+
+[`Text`]: struct.Text.html
+[`create_event`]: #method.create_event
+[`Event::Update`]: ../events/enum.Event.html#variant.Update
+[`Projector::merge`]: ../events/struct.Projector.html#method.merge
+[`Projector::get_conflicts`]: ../events/struct.Projector.html#method.get_conflicts
+[`TextProjection`]: struct.TextProjection.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextEdit {
+    /// The name of the edited text
+    text: String,
+
+    /// The operations performed by the edit
+    operations: Vec<TextOperation>,
+}
+
+impl TextEdit {
+    /// Creates a new edit of a text, consisting of some operations
+    pub fn new(text: &str, operations: Vec<TextOperation>) -> Self {
+        Self {
+            text: text.to_owned(),
+            operations,
+        }
+    }
+
+    /// Returns the event creating a text, which is identical on all replicas
+    ///
+    /// It occurs at the Unix epoch and doesn't contain any operations, so merging
+    /// the creations of several replicas is idempotent. Insert it using
+    /// [`Projector::insert`] rather than pushing it, as it predates all other events.
+    ///
+    /// [`Projector::insert`]: ../events/struct.Projector.html#method.insert
+    pub fn create_event(text: &str) -> Event<'static, Self> {
+        Event::create_at(
+            Cow::Owned(Self::new(text, vec![])),
+            Timestamp::from(UNIX_EPOCH),
+        )
+    }
+
+    /// Returns the name of the edited text
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns a reference to the operations performed by the edit
+    pub fn get_operations(&self) -> &Vec<TextOperation> {
+        &self.operations
+    }
+}
+
+// Implemented manually to identify edits by the text they modify
+impl PartialEq for TextEdit {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+/**
+A projection applying the operations of [`TextEdit`] events to the edited texts.
+
+Add it to a projector using [`Projector::add_projection`]. As custom projections
+are snapshotted per segment, the texts can be projected at any moment in time
+(see [`Projector::project_custom_at`]). Operations referring to characters which
+aren't known yet (e.g. as the clocks of the replicas differ) are held back until
+they are.
+
+[`TextEdit`]: struct.TextEdit.html
+[`Projector::add_projection`]: ../events/struct.Projector.html#method.add_projection
+[`Projector::project_custom_at`]: ../events/struct.Projector.html#method.project_custom_at
+*/
+#[derive(Debug, Clone, Default)]
+pub struct TextProjection {
+    /// The texts by their names
+    texts: BTreeMap<String, Text>,
+
+    /// The operations waiting for the characters they refer to by the names of their texts
+    pending: BTreeMap<String, Vec<TextOperation>>,
+}
+
+impl TextProjection {
+    /// Returns a reference to a text by its name
+    pub fn get(&self, text: &str) -> Option<&Text> {
+        self.texts.get(text)
+    }
+}
+
+impl Projection<TextEdit> for TextProjection {
+    fn apply(&mut self, event: &Event<'_, TextEdit>) {
+        if let Event::Delete(_) = event {
+            self.texts.remove(&event.text);
+            self.pending.remove(&event.text);
+            return;
+        }
+
+        let text = self.texts.entry(event.text.clone()).or_default();
+        let pending = self.pending.entry(event.text.clone()).or_default();
+        pending.extend(event.operations.iter().cloned());
+
+        // Apply the operations until none of the remaining ones can be applied
+        loop {
+            let count = pending.len();
+            *pending = text.apply_all(pending);
+            if pending.len() == count {
+                break;
+            }
+        }
+    }
+}
+
+impl Crdt for Text {
+    fn merge(&mut self, other: &Self) {
+        let insertions = other.characters.iter().map(|c| TextOperation::Insert {
+            id: c.id.clone(),
+            origin: c.origin.clone(),
+            value: c.value,
+        });
+        let deletions = other
+            .characters
+            .iter()
+            .filter(|c| c.deleted)
+            .map(|c| TextOperation::Delete { id: c.id.clone() });
+
+        // Never fails, because origins precede the characters inserted after them
+        let operations: Vec<_> = insertions.chain(deletions).collect();
+        self.apply_all(&operations);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::events::{Event, Projector, Segment, Timestamp};
+use anyhow::{bail, Result};
+use chrono::Duration;
+use std::borrow::Cow;
+
+/// Values over time, each one along with the moment in time (or the start of the window) it belongs to
+pub type Series<A> = Vec<(Timestamp, A)>;
+
+/// The maximum number of steps between the start and the end of a series
+pub const MAX_STEPS: i32 = 1_000_000;
+
+impl<'a, T> Projector<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Samples the projection at regular intervals, returning the values computed
+    /// by a function at `from`, `from + step`, ... up to (and including) `to`
+    ///
+    /// Only the projection at `from` is computed (using the snapshot of the previous
+    /// segment), which the following events are applied to in a single sweep, so
+    /// the function sees the same projection as [`project_at`](#method.project_at)
+    /// at every sample.
+    ///
+    /// Returns `None` if `from` predates the first segment.
+    /// Fails if there are more than [`MAX_STEPS`](constant.MAX_STEPS.html) steps
+    /// between `from` and `to`.
+    pub fn sample_every<A, F>(
+        &self,
+        from: &Timestamp,
+        to: &Timestamp,
+        step: Duration,
+        mut f: F,
+    ) -> Result<Option<Series<A>>>
+    where
+        F: FnMut(&Vec<Cow<'a, T>>) -> A,
+    {
+        if step <= Duration::zero() {
+            bail!("Cannot sample using a step which isn't positive")
+        }
+        Self::check_steps(from, to, step)?;
+
+        let mut projection = match self.project_at(from) {
+            Some(projection) => projection,
+            None => return Ok(None),
+        };
+
+        // The events after `from` (the ones up to `from` are projected already)
+        let mut events = self
+            .get_events_from(from)
+            .into_iter()
+            .skip_while(|e| e.get_time() <= from)
+            .peekable();
+
+        let mut samples = vec![];
+        let mut sample_time = *from;
+        while sample_time <= *to {
+            // Apply all events up to (and including) the moment of the sample
+            while let Some(event) = events.next_if(|e| e.get_time() <= &sample_time) {
+                Segment::apply_event_to(&mut projection, event.clone())?;
+            }
+
+            samples.push((sample_time, f(&projection)));
+            sample_time += step;
+        }
+
+        Ok(Some(samples))
+    }
+
+    /// Counts the entities at regular intervals (see [`sample_every`](#method.sample_every))
+    pub fn count_every(
+        &self,
+        from: &Timestamp,
+        to: &Timestamp,
+        step: Duration,
+    ) -> Result<Option<Series<usize>>> {
+        self.sample_every(from, to, step, |projection| projection.len())
+    }
+
+    /// Aggregates the events of consecutive time windows of equal length, returning
+    /// the aggregate of every window along with its start
+    ///
+    /// The windows start at `from`, `from + step`, ... and end before `to`, each one
+    /// containing the events at or after its start and before the next one. Every
+    /// aggregate starts with its default value, and is updated by a function for
+    /// every event of its window. The events are swept once.
+    ///
+    /// Fails if there are more than [`MAX_STEPS`](constant.MAX_STEPS.html) steps
+    /// between `from` and `to`.
+    pub fn aggregate_every<A, F>(
+        &self,
+        from: &Timestamp,
+        to: &Timestamp,
+        step: Duration,
+        mut f: F,
+    ) -> Result<Series<A>>
+    where
+        A: Default,
+        F: FnMut(&mut A, &Event<'a, T>),
+    {
+        if step <= Duration::zero() {
+            bail!("Cannot aggregate using a step which isn't positive")
+        }
+        Self::check_steps(from, to, step)?;
+
+        // Start all windows empty
+        let mut windows: Series<A> = vec![];
+        let mut window_start = *from;
+        while window_start < *to {
+            windows.push((window_start, A::default()));
+            window_start += step;
+        }
+
+        // Assign the events to their windows
+        let mut window_pos = 0;
+        for event in self
+            .get_events_from(from)
+            .into_iter()
+            .take_while(|e| e.get_time() < to)
+        {
+            while window_pos + 1 < windows.len() && event.get_time() >= &windows[window_pos + 1].0 {
+                window_pos += 1;
+            }
+
+            f(&mut windows[window_pos].1, event);
+        }
+
+        Ok(windows)
+    }
+
+    /// Checks that there are at most `MAX_STEPS` steps between two moments in time
+    fn check_steps(from: &Timestamp, to: &Timestamp, step: Duration) -> Result<()> {
+        // Compare the step to a fraction of the range, as counting the steps may take forever
+        if *to > *from && (*to - *from) / MAX_STEPS > step {
+            bail!(
+                "Cannot use more than {} steps of {} between {} and {}",
+                MAX_STEPS,
+                step,
+                from,
+                to
+            )
+        }
+
+        Ok(())
+    }
+
+    /// Counts the events matching a predicate in consecutive time windows of equal length
+    /// (see [`aggregate_every`](#method.aggregate_every))
+    pub fn count_events_every<P>(
+        &self,
+        from: &Timestamp,
+        to: &Timestamp,
+        step: Duration,
+        mut predicate: P,
+    ) -> Result<Series<usize>>
+    where
+        P: FnMut(&Event<'a, T>) -> bool,
+    {
+        self.aggregate_every(from, to, step, |count, event| {
+            if predicate(event) {
+                *count += 1;
+            }
+        })
+    }
+}
@@ -0,0 +1,158 @@
+use crate::events::Event;
+use std::{
+    borrow::Cow,
+    fmt,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+/**
+A change of the current projection of a [`Projector`].
+
+Changes are emitted to the subscribers of a projector whenever an event
+modifies its current projection, be it by pushing new events, by committing
+a transaction or by merging (possibly out-of-order) events.
+
+[`Projector`]: struct.Projector.html
+*/
+#[derive(Clone, PartialEq, Debug)]
+pub struct Change<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The event causing the change
+    event: Event<'a, T>,
+
+    /// The entity before the change (if it existed)
+    old: Option<Cow<'a, T>>,
+
+    /// The entity after the change (if it still exists)
+    new: Option<Cow<'a, T>>,
+}
+
+impl<'a, T> Change<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Constructs a new change
+    pub(crate) fn new(
+        event: Event<'a, T>,
+        old: Option<Cow<'a, T>>,
+        new: Option<Cow<'a, T>>,
+    ) -> Self {
+        Self { event, old, new }
+    }
+
+    /// Returns a reference to the event causing the change
+    pub fn get_event(&self) -> &Event<'a, T> {
+        &self.event
+    }
+
+    /// Returns a reference to the entity before the change (if it existed)
+    pub fn get_old(&self) -> Option<&T> {
+        self.old.as_deref()
+    }
+
+    /// Returns a reference to the entity after the change (if it still exists)
+    pub fn get_new(&self) -> Option<&T> {
+        self.new.as_deref()
+    }
+
+    /// Consumes the change, returning the event, the old and the new entity
+    pub fn take(self) -> (Event<'a, T>, Option<Cow<'a, T>>, Option<Cow<'a, T>>) {
+        (self.event, self.old, self.new)
+    }
+}
+
+/// The subscribers of a projector
+///
+/// Subscriptions aren't cloned along with their projector,
+/// as the clone evolves independently of the original.
+pub(super) struct Subscribers<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    senders: Vec<Sender<Change<'a, T>>>,
+
+    /// The changes held back until they're released (if any are held back)
+    held: Option<Vec<Change<'a, T>>>,
+}
+
+impl<'a, T> Subscribers<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Adds a new subscriber, returning the receiving end of its channel
+    pub(super) fn subscribe(&mut self) -> Receiver<Change<'a, T>> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Checks if there are any subscribers to notify
+    pub(super) fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// Sends the changes to all subscribers, dropping disconnected ones
+    ///
+    /// The changes are held back instead if [`hold`](#method.hold) was called before.
+    pub(super) fn notify(&mut self, changes: &[Change<'a, T>]) {
+        if let Some(held) = &mut self.held {
+            held.extend_from_slice(changes);
+            return;
+        }
+
+        self.senders.retain(|sender| {
+            changes
+                .iter()
+                .all(|change| sender.send(change.clone()).is_ok())
+        });
+    }
+
+    /// Holds back all changes until they're released
+    #[cfg(feature = "async")]
+    pub(super) fn hold(&mut self) {
+        self.held.get_or_insert_with(Vec::new);
+    }
+
+    /// Sends the changes held back to all subscribers (or drops them, e.g. if
+    /// they were rolled back), and stops holding back changes
+    #[cfg(feature = "async")]
+    pub(super) fn release(&mut self, send: bool) {
+        if let Some(held) = self.held.take() {
+            if send {
+                self.notify(&held);
+            }
+        }
+    }
+}
+
+impl<'a, T> Default for Subscribers<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    fn default() -> Self {
+        Self {
+            senders: vec![],
+            held: None,
+        }
+    }
+}
+
+impl<'a, T> Clone for Subscribers<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<'a, T> fmt::Debug for Subscribers<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Subscribers({})", self.senders.len())
+    }
+}
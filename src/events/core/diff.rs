@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+
+/**
+The differences between the projections of a [`Projector`] at two moments in time.
+
+See [`Projector::diff`] for details.
+
+[`Projector`]: struct.Projector.html
+[`Projector::diff`]: struct.Projector.html#method.diff
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The entities created in between
+    created: Vec<Cow<'a, T>>,
+
+    /// The entities updated in between (the old and the new version)
+    updated: Vec<(Cow<'a, T>, Cow<'a, T>)>,
+
+    /// The entities deleted in between
+    deleted: Vec<Cow<'a, T>>,
+}
+
+impl<'a, T> Diff<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Constructs a new, empty diff
+    pub(super) fn new() -> Self {
+        Self {
+            created: vec![],
+            updated: vec![],
+            deleted: vec![],
+        }
+    }
+
+    /// Adds the versions of an entity at both moments in time (if it existed)
+    pub(super) fn add(&mut self, old: Option<Cow<'a, T>>, new: Option<Cow<'a, T>>) {
+        match (old, new) {
+            (None, Some(new)) => self.created.push(new),
+            (Some(old), Some(new)) => self.updated.push((old, new)),
+            (Some(old), None) => self.deleted.push(old),
+            // Created and deleted in between
+            (None, None) => {}
+        }
+    }
+
+    /// Returns a reference to the entities created in between
+    pub fn get_created(&self) -> &Vec<Cow<'a, T>> {
+        &self.created
+    }
+
+    /// Returns a reference to the entities updated in between (the old and the new version)
+    pub fn get_updated(&self) -> &Vec<(Cow<'a, T>, Cow<'a, T>)> {
+        &self.updated
+    }
+
+    /// Returns a reference to the entities deleted in between
+    pub fn get_deleted(&self) -> &Vec<Cow<'a, T>> {
+        &self.deleted
+    }
+
+    /// Checks if nothing changed in between
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+}
@@ -1,4 +1,5 @@
-use crate::events::Timestamp;
+use crate::events::{ContentId, ReplicaId, Timestamp, TransactionId, VersionVector};
+use anyhow::Result;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, cmp::Ordering, ops::Deref};
@@ -25,6 +26,9 @@ The `Event` struct usually is part of a list of events called an "event log".
 Each event represents an atomic change in an entity (including its creation or destruction).
 Every event log only contains one entity type:
 Two entity types should have two separate event logs.
+Use a [`Store`] to order the events of several entity types consistently.
+
+[`Store`]: struct.Store.html
 */
 #[derive(Clone, PartialEq, Serialize, Debug, Deserialize)]
 pub struct EventContent<'a, T>
@@ -39,6 +43,29 @@ where
     /// This can be any type of data, as long as the traits
     /// `Clone` and `PartialEq` are implemented.
     data: Cow<'a, T>,
+
+    /// The transaction this event is part of (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transaction: Option<TransactionId>,
+
+    /// The position of this event within its transaction (if any)
+    ///
+    /// Events of a transaction share the timestamp and the transaction id,
+    /// so this tells apart several events concerning the same entity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transaction_position: Option<usize>,
+
+    /// The replica this event originated from (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replica: Option<ReplicaId>,
+
+    /// The version of the originating replica including this event (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<VersionVector>,
+
+    /// The identifiers of the parent events in a [`History`](struct.History.html)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    parents: Vec<ContentId>,
 }
 
 impl<'a, T> Event<'a, T>
@@ -47,26 +74,116 @@ where
 {
     /// Constructs a new create event
     pub fn create(data: Cow<'a, T>) -> Self {
-        Self::Create(EventContent {
-            timestamp: Utc::now(),
-            data,
-        })
+        Self::create_at(data, Utc::now())
     }
 
     /// Constructs a new update event
     pub fn update(data: Cow<'a, T>) -> Self {
-        Self::Update(EventContent {
-            timestamp: Utc::now(),
-            data,
-        })
+        Self::update_at(data, Utc::now())
     }
 
     /// Constructs a new delete event
     pub fn delete(data: Cow<'a, T>) -> Self {
-        Self::Delete(EventContent {
-            timestamp: Utc::now(),
-            data,
-        })
+        Self::delete_at(data, Utc::now())
+    }
+
+    /// Constructs a new create event occurring at a given moment in time
+    pub fn create_at(data: Cow<'a, T>, timestamp: Timestamp) -> Self {
+        Self::Create(EventContent::new(timestamp, data))
+    }
+
+    /// Constructs a new update event occurring at a given moment in time
+    pub fn update_at(data: Cow<'a, T>, timestamp: Timestamp) -> Self {
+        Self::Update(EventContent::new(timestamp, data))
+    }
+
+    /// Constructs a new delete event occurring at a given moment in time
+    pub fn delete_at(data: Cow<'a, T>, timestamp: Timestamp) -> Self {
+        Self::Delete(EventContent::new(timestamp, data))
+    }
+
+    /// Returns the transaction this event is part of (if any)
+    pub fn get_transaction(&self) -> Option<TransactionId> {
+        self.content().transaction
+    }
+
+    /// Returns the position of this event within its transaction (if any)
+    pub fn get_transaction_position(&self) -> Option<usize> {
+        self.content().transaction_position
+    }
+
+    /// Marks the event as part of a transaction at a given position
+    pub(super) fn set_transaction(&mut self, transaction: TransactionId, position: usize) {
+        let content = self.content_mut();
+        content.transaction = Some(transaction);
+        content.transaction_position = Some(position);
+    }
+
+    /// Returns the replica this event originated from (if known)
+    pub fn get_replica(&self) -> Option<&ReplicaId> {
+        self.content().replica.as_ref()
+    }
+
+    /// Returns the version of the originating replica including this event (if known)
+    pub fn get_version(&self) -> Option<&VersionVector> {
+        self.content().version.as_ref()
+    }
+
+    /// Marks the event as originating from a replica at a given version
+    pub fn with_version(mut self, replica: &str, version: VersionVector) -> Self {
+        let content = self.content_mut();
+        content.replica = Some(replica.to_owned());
+        content.version = Some(version);
+        self
+    }
+
+    /// Checks if this event causally precedes another one
+    ///
+    /// Returns `false` if either event lacks a version.
+    pub fn happened_before(&self, other: &Self) -> bool {
+        match (self.get_version(), other.get_version()) {
+            (Some(own), Some(other)) => own < other,
+            _ => false,
+        }
+    }
+
+    /// Checks if neither event causally precedes the other one
+    ///
+    /// Returns `false` if either event lacks a version.
+    pub fn is_concurrent_with(&self, other: &Self) -> bool {
+        match (self.get_version(), other.get_version()) {
+            (Some(own), Some(other)) => own.is_concurrent_with(other),
+            _ => false,
+        }
+    }
+
+    /// Returns the identifiers of the parent events in a history
+    pub fn get_parents(&self) -> &Vec<ContentId> {
+        &self.content().parents
+    }
+
+    /// Makes the event a child of some other events (e.g. the heads of a history)
+    pub fn with_parents(mut self, parents: Vec<ContentId>) -> Self {
+        self.content_mut().parents = parents;
+        self
+    }
+
+    /// Borrow the content of the event
+    fn content(&self) -> &EventContent<'a, T> {
+        match self {
+            Self::Create(ref content) | Self::Update(ref content) | Self::Delete(ref content) => {
+                content
+            }
+        }
+    }
+
+    /// Borrow the content of the event (mutable)
+    fn content_mut(&mut self) -> &mut EventContent<'a, T> {
+        match self {
+            Self::Create(ref mut content)
+            | Self::Update(ref mut content)
+            | Self::Delete(ref mut content) => content,
+        }
     }
 
     /// Borrow the contained data
@@ -85,6 +202,11 @@ where
         .timestamp
     }
 
+    /// Moves the event to another moment in time
+    pub(super) fn set_time(&mut self, timestamp: Timestamp) {
+        self.content_mut().timestamp = timestamp;
+    }
+
     /// Compare two events based on their timestamps
     fn compare_timestamps(&self, other: &Self) -> Ordering {
         self.get_time().cmp(other.get_time())
@@ -97,6 +219,64 @@ where
         }
         .data
     }
+
+    /// Consumes the event, returning an equivalent event owning its data
+    pub fn into_owned(self) -> Event<'static, T>
+    where
+        T: 'static,
+    {
+        match self {
+            Self::Create(content) => Event::Create(content.into_owned()),
+            Self::Update(content) => Event::Update(content.into_owned()),
+            Self::Delete(content) => Event::Delete(content.into_owned()),
+        }
+    }
+}
+
+impl<'a, T> Event<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    /// Computes the content identifier of this event
+    ///
+    /// Identical events (including their timestamps) always have the same identifier.
+    pub fn id(&self) -> Result<ContentId> {
+        ContentId::of(self)
+    }
+}
+
+impl<'a, T> EventContent<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Constructs the content of a new event
+    fn new(timestamp: Timestamp, data: Cow<'a, T>) -> Self {
+        Self {
+            timestamp,
+            data,
+            transaction: None,
+            transaction_position: None,
+            replica: None,
+            version: None,
+            parents: vec![],
+        }
+    }
+
+    /// Consumes the content, returning an equivalent content owning its data
+    fn into_owned(self) -> EventContent<'static, T>
+    where
+        T: 'static,
+    {
+        EventContent {
+            timestamp: self.timestamp,
+            data: Cow::Owned(self.data.into_owned()),
+            transaction: self.transaction,
+            transaction_position: self.transaction_position,
+            replica: self.replica,
+            version: self.version,
+            parents: self.parents,
+        }
+    }
 }
 
 impl<'a, T> Deref for Event<'a, T>
@@ -0,0 +1,404 @@
+use crate::events::{ContentId, EntityId, Event, Projector, Segment, Timestamp};
+use anyhow::{anyhow, bail, Result};
+use git2::{Commit, ObjectType, Oid, Repository, Signature, Time, Tree};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{borrow::Cow, marker::PhantomData, path::Path};
+
+/// The metadata of a segment stored in a commit
+#[derive(Serialize, Deserialize)]
+struct SegmentInfo {
+    /// The earliest data captured by the segment
+    timestamp: Timestamp,
+
+    /// The content identifier of the segment
+    id: ContentId,
+
+    /// The identifiers of the entities of the snapshot (in the same order)
+    ///
+    /// Segments saved before identifiers were persisted identify them in order.
+    #[serde(default)]
+    ids: Option<Vec<EntityId>>,
+
+    /// The identifier of the next entity created
+    #[serde(default)]
+    next_id: Option<EntityId>,
+}
+
+/**
+A persistence backend storing sealed segments in a (bare) git repository.
+
+Every sealed segment (all but the latest one of a projector) is stored as a
+single commit on a branch, in order. The tree of each commit contains:
+
+- `segment.json`, the metadata of the segment (its timestamp, content identifier
+  and the identifiers of its entities)
+- `events/00000000000000000000.json`, `events/00000000000000000001.json`, ...
+  one file per event, named by its (zero-padded) index
+- `snapshot/00000000000000000000.json`, ... one file per entity of the snapshot
+
+This makes the history inspectable using plain git (e.g. `git log` or
+`git show main~2:snapshot`), and allows replicas to exchange segments using
+[`fetch`] between repositories.
+
+Since the latest segment of a projector is still mutable, it's never saved;
+it's reconstructed from the latest snapshot by [`load_projector`].
+
+[`fetch`]: #method.fetch
+[`load_projector`]: #method.load_projector
+*/
+pub struct GitStorage<T>
+where
+    T: Clone + PartialEq,
+{
+    /// The underlying repository
+    repository: Repository,
+
+    /// The name of the branch containing the segments
+    branch: String,
+
+    _type: PhantomData<T>,
+}
+
+impl<T> GitStorage<T>
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    /// The default branch containing the segments
+    const DEFAULT_BRANCH: &'static str = "main";
+
+    /// The name of the file containing the segment metadata
+    const SEGMENT: &'static str = "segment.json";
+
+    /// The name of the directory containing the events
+    const EVENTS: &'static str = "events";
+
+    /// The name of the directory containing the snapshot
+    const SNAPSHOT: &'static str = "snapshot";
+
+    /// Opens the bare repository at a given path, initializing it if missing
+    pub fn init<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let repository = match Repository::open_bare(path.as_ref()) {
+            Ok(repository) => repository,
+            Err(_) => Repository::init_bare(path.as_ref())?,
+        };
+
+        Ok(Self {
+            repository,
+            branch: String::from(Self::DEFAULT_BRANCH),
+            _type: PhantomData,
+        })
+    }
+
+    /// Uses a different branch to store the segments
+    pub fn with_branch(mut self, branch: &str) -> Self {
+        self.branch = String::from(branch);
+        self
+    }
+
+    /// Returns a shared reference to the underlying repository
+    pub fn get_repository(&self) -> &Repository {
+        &self.repository
+    }
+
+    /// Commits all sealed segments of a projector which haven't been saved yet,
+    /// returning the number of new commits
+    ///
+    /// Fails if the saved segments aren't a prefix of the projectors segments,
+    /// which is checked by comparing their content identifiers.
+    pub fn save(&self, projector: &Projector<'_, T>) -> Result<usize> {
+        // All segments but the latest one are sealed
+        let sealed = &projector[..projector.len() - 1];
+
+        // Check the segments which were saved before
+        let commits = self.commits()?;
+        if commits.len() > sealed.len() {
+            bail!(
+                "The repository contains {} segments, but the projector only {} sealed ones",
+                commits.len(),
+                sealed.len()
+            )
+        }
+        for (commit, segment) in commits.iter().zip(sealed) {
+            if self.read_info(commit)?.id != segment.id()? {
+                bail!("The saved segments diverged from the projector")
+            }
+        }
+
+        // Commit the new segments one by one
+        let mut parent = commits.last().map(|c| c.id());
+        for segment in &sealed[commits.len()..] {
+            parent = Some(self.commit_segment(segment, parent)?);
+        }
+
+        Ok(sealed.len() - commits.len())
+    }
+
+    /// Loads all saved segments in order
+    pub fn load(&self) -> Result<Vec<Segment<'static, T>>> {
+        let commits = self.commits()?;
+        let mut segments = Vec::with_capacity(commits.len());
+
+        for commit in &commits {
+            segments.push(self.read_segment(commit)?);
+        }
+
+        Ok(segments)
+    }
+
+    /// Loads a projector from the saved segments, continuing with a new segment
+    pub fn load_projector(&self) -> Result<Projector<'static, T>> {
+        let mut segments = self.load()?;
+
+        // Continue from the latest snapshot
+        let next = segments.last().map(Segment::next).unwrap_or_default();
+        segments.push(next);
+
+        Projector::from_segments(segments)
+    }
+
+    /// Performs a projection at a given timestamp using the commit history
+    ///
+    /// Only the commit containing the timestamp and its parent are read.
+    /// Returns `None` if the timestamp predates all saved segments.
+    pub fn project_at(&self, timestamp: &Timestamp) -> Result<Option<Vec<Cow<'static, T>>>> {
+        let commits = self.commits()?;
+
+        // Find the latest segment starting before the timestamp
+        let mut position = None;
+        for (index, commit) in commits.iter().enumerate().rev() {
+            if &self.read_info(commit)?.timestamp <= timestamp {
+                position = Some(index);
+                break;
+            }
+        }
+        let position = match position {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        // Use the snapshot of the previous segment (if any)
+        let snapshot = match position {
+            0 => vec![],
+            _ => self.read_entries(&commits[position - 1].tree()?, Self::SNAPSHOT)?,
+        };
+
+        Ok(self
+            .read_segment(&commits[position])?
+            .project_at_onto(timestamp, snapshot))
+    }
+
+    /// Fetches the segments of another repository (e.g. at a local path),
+    /// fast-forwarding the branch if they extend the local ones
+    ///
+    /// Fails if both repositories saved different segments.
+    pub fn fetch(&self, url: &str) -> Result<()> {
+        let remote_ref = format!("refs/remotes/libocc/{}", self.branch);
+
+        // Fetch the branch of the other repository
+        let mut remote = self.repository.remote_anonymous(url)?;
+        remote.fetch(
+            &[format!("+{}:{}", self.branch_ref(), remote_ref)],
+            None,
+            None,
+        )?;
+
+        let theirs = match self.repository.refname_to_id(&remote_ref) {
+            Ok(oid) => oid,
+            Err(_) => return Ok(()),
+        };
+        let ours = self.head()?;
+
+        match ours {
+            // Nothing new
+            Some(ours) if ours == theirs => Ok(()),
+
+            // We're ahead
+            Some(ours) if self.repository.graph_descendant_of(ours, theirs)? => Ok(()),
+
+            // They're ahead, so fast-forward
+            Some(ours) if self.repository.graph_descendant_of(theirs, ours)? => {
+                self.update_branch(theirs)
+            }
+
+            // Both sides saved different segments
+            Some(_) => bail!("Cannot fetch from {}, as the segments diverged", url),
+
+            // We don't have any segments yet
+            None => self.update_branch(theirs),
+        }
+    }
+
+    /// Returns the full name of the branch reference
+    fn branch_ref(&self) -> String {
+        format!("refs/heads/{}", self.branch)
+    }
+
+    /// Returns the latest commit of the branch (if any)
+    fn head(&self) -> Result<Option<Oid>> {
+        match self.repository.refname_to_id(&self.branch_ref()) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Points the branch to a given commit
+    fn update_branch(&self, oid: Oid) -> Result<()> {
+        self.repository
+            .reference(&self.branch_ref(), oid, true, "libocc: fetch")?;
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Returns the commits of the branch from the oldest to the latest one
+    fn commits(&self) -> Result<Vec<Commit<'_>>> {
+        let mut commits = vec![];
+        let mut next = match self.head()? {
+            Some(oid) => Some(self.repository.find_commit(oid)?),
+            None => None,
+        };
+
+        // Walk the first parents back to the root commit
+        while let Some(commit) = next {
+            next = match commit.parent_count() {
+                0 => None,
+                _ => Some(commit.parent(0)?),
+            };
+            commits.push(commit);
+        }
+
+        commits.reverse();
+        Ok(commits)
+    }
+
+    /// Commits a segment on top of a given parent commit
+    fn commit_segment(&self, segment: &Segment<'_, T>, parent: Option<Oid>) -> Result<Oid> {
+        let mut root = self.repository.treebuilder(None)?;
+
+        // Write the metadata
+        let info = serde_json::to_vec_pretty(&SegmentInfo {
+            timestamp: *segment.get_time(),
+            id: segment.id()?,
+            ids: Some(segment.get_ids().clone()),
+            next_id: Some(segment.get_next_id()),
+        })?;
+        root.insert(Self::SEGMENT, self.repository.blob(&info)?, 0o100644)?;
+
+        // Write the events and the snapshot as directories of numbered files
+        let events = self.write_entries(segment.get_events())?;
+        root.insert(Self::EVENTS, events, 0o040000)?;
+        let snapshot = self.write_entries(segment.get_projection())?;
+        root.insert(Self::SNAPSHOT, snapshot, 0o040000)?;
+
+        let tree = self.repository.find_tree(root.write()?)?;
+
+        // Use the segments timestamp as the commit time, making the history reproducible
+        let time = segment.get_time();
+        let signature = Signature::new(
+            "libocc",
+            "libocc@localhost",
+            &Time::new(time.timestamp(), 0),
+        )?;
+        let message = format!(
+            "Segment starting at {}\n\n{} events, {} entities\n",
+            time.to_rfc3339(),
+            segment.get_events().len(),
+            segment.get_projection().len()
+        );
+
+        let parents = match parent {
+            Some(oid) => vec![self.repository.find_commit(oid)?],
+            None => vec![],
+        };
+        let parents: Vec<_> = parents.iter().collect();
+
+        Ok(self.repository.commit(
+            Some(&self.branch_ref()),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parents,
+        )?)
+    }
+
+    /// Writes a list of values as a tree of numbered JSON files
+    fn write_entries<V: Serialize>(&self, values: &[V]) -> Result<Oid> {
+        let mut builder = self.repository.treebuilder(None)?;
+
+        for (index, value) in values.iter().enumerate() {
+            let blob = self.repository.blob(&serde_json::to_vec_pretty(value)?)?;
+
+            // Pad to a fixed width of 20 digits (enough for any u64), so the names sort like the indexes
+            builder.insert(format!("{:020}.json", index as u64), blob, 0o100644)?;
+        }
+
+        Ok(builder.write()?)
+    }
+
+    /// Reads the metadata of the segment stored in a commit
+    fn read_info(&self, commit: &Commit<'_>) -> Result<SegmentInfo> {
+        let entry = commit.tree()?.get_path(Path::new(Self::SEGMENT))?;
+        let blob = self.repository.find_blob(entry.id())?;
+
+        Ok(serde_json::from_slice(blob.content())?)
+    }
+
+    /// Reads the segment stored in a commit
+    fn read_segment(&self, commit: &Commit<'_>) -> Result<Segment<'static, T>> {
+        let tree = commit.tree()?;
+        let events: Vec<Event<'static, T>> = self.read_entries(&tree, Self::EVENTS)?;
+        let snapshot = self.read_entries(&tree, Self::SNAPSHOT)?;
+
+        let info = self.read_info(commit)?;
+
+        let mut segment = Segment::from_parts(info.timestamp, snapshot, events);
+        if let (Some(ids), Some(next_id)) = (info.ids, info.next_id) {
+            segment.set_ids(ids, next_id)?;
+        }
+
+        Ok(segment)
+    }
+
+    /// Reads the numbered JSON files of a directory in order
+    fn read_entries<V: DeserializeOwned>(
+        &self,
+        tree: &Tree<'_>,
+        directory: &str,
+    ) -> Result<Vec<V>> {
+        // Empty directories aren't stored by git
+        let entry = match tree.get_path(Path::new(directory)) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(vec![]),
+        };
+        let directory = self.repository.find_tree(entry.id())?;
+
+        // The entries of a tree are sorted by name, which is the index
+        let mut values = vec![];
+        for entry in directory.iter() {
+            if entry.kind() != Some(ObjectType::Blob) {
+                bail!("Unexpected entry {:?} in a segment", entry.name())
+            }
+            let blob = entry
+                .to_object(&self.repository)?
+                .into_blob()
+                .map_err(|_| anyhow!("Cannot read entry {:?}", entry.name()))?;
+            values.push(serde_json::from_slice(blob.content())?);
+        }
+
+        Ok(values)
+    }
+}
+
+impl<T> std::fmt::Debug for GitStorage<T>
+where
+    T: Clone + PartialEq,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitStorage")
+            .field("path", &self.repository.path())
+            .field("branch", &self.branch)
+            .finish()
+    }
+}
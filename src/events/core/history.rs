@@ -0,0 +1,359 @@
+use crate::events::{ContentId, Event, Projector, Segment, Timestamp};
+use anyhow::{anyhow, bail, Result};
+use petgraph::{
+    algo::has_path_connecting,
+    graph::{DiGraph, NodeIndex},
+    visit::{Dfs, Reversed},
+    Direction,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+/**
+A history of events forming a directed acyclic graph (DAG).
+
+Unlike the linear event log of a [`Segment`], every event of a history
+references its parent events by their [`ContentId`]s. Replicas editing
+offline simply append to their own heads, so several heads coexist after
+exchanging their events. Appending an event while there are several heads
+makes it a merge event, joining all of them.
+
+The projection is computed by replaying the events in a deterministic
+topological order: Every event is replayed after its parents, and events
+which could be replayed at the same time are ordered by their timestamps
+(and their identifiers, if their timestamps are equal).
+
+Concurrent branches may contain conflicting events, e.g. an update of an entity
+deleted on another branch. Replaying skips the events which can't be applied
+(in the example, the update is skipped if the deletion is replayed first), so
+every replica skips the same ones. Use [`get_skipped`] to find them.
+
+A history is separate from the segments of a [`Projector`]: It keeps its own
+events, and doesn't follow the events pushed to (or merged into) a projector.
+Use [`from_projector`] to create a history of the events of a projector.
+
+A history is serialized as the list of its events in replay order.
+
+[`Segment`]: struct.Segment.html
+[`Projector`]: struct.Projector.html
+[`from_projector`]: #method.from_projector
+[`ContentId`]: struct.ContentId.html
+[`get_skipped`]: #method.get_skipped
+*/
+#[derive(Debug, Clone)]
+pub struct History<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The events (along with their identifiers), with edges from parents to children
+    graph: DiGraph<(ContentId, Event<'a, T>), ()>,
+
+    /// The nodes of the graph by the identifiers of their events
+    nodes: HashMap<ContentId, NodeIndex>,
+
+    /// The nodes without children
+    heads: HashSet<NodeIndex>,
+
+    /// All nodes in replay order (updated whenever an event is inserted)
+    order: Vec<NodeIndex>,
+}
+
+impl<'a, T> History<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    /// Creates a new, empty history
+    pub fn new() -> Self {
+        Self {
+            graph: DiGraph::new(),
+            nodes: HashMap::new(),
+            heads: HashSet::new(),
+            order: vec![],
+        }
+    }
+
+    /// Creates a history from events in any order, as long as none of their parents is missing
+    pub fn from_events(events: Vec<Event<'a, T>>) -> Result<Self> {
+        let mut history = Self::new();
+        let mut remaining = events;
+
+        // Insert the events whose parents are known until none are left
+        // (events following their parents are inserted in a single pass)
+        while !remaining.is_empty() {
+            let count = remaining.len();
+            let mut waiting = vec![];
+            for event in remaining {
+                if event.get_parents().iter().all(|p| history.contains(p)) {
+                    history.insert(event)?;
+                } else {
+                    waiting.push(event);
+                }
+            }
+
+            if waiting.len() == count {
+                bail!("Cannot create a history of events with missing parents")
+            }
+            remaining = waiting;
+        }
+
+        Ok(history)
+    }
+
+    /// Creates a linear history from the events of a projector, in the order of their timestamps
+    ///
+    /// Every event becomes the parent of the following one, replacing any parents it had.
+    pub fn from_projector(projector: &Projector<'a, T>) -> Result<Self> {
+        let mut history = Self::new();
+        for event in projector.iter().flat_map(|s| s.get_events()) {
+            history.append(event.clone())?;
+        }
+
+        Ok(history)
+    }
+
+    /// Returns the number of events in this history
+    pub fn len(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// Checks if this history doesn't contain any events
+    pub fn is_empty(&self) -> bool {
+        self.graph.node_count() == 0
+    }
+
+    /// Checks if this history contains an event
+    pub fn contains(&self, id: &ContentId) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    /// Returns a reference to an event by its identifier
+    pub fn get_event(&self, id: &ContentId) -> Option<&Event<'a, T>> {
+        self.nodes.get(id).map(|&node| &self.graph[node].1)
+    }
+
+    /// Inserts an event (e.g. received from another replica), returning its identifier
+    ///
+    /// All of its parents must be part of this history already.
+    /// Inserting a known event again doesn't change anything.
+    pub fn insert(&mut self, event: Event<'a, T>) -> Result<ContentId> {
+        let id = event.id()?;
+        if self.contains(&id) {
+            return Ok(id);
+        }
+
+        // Find the parents
+        let parents = event
+            .get_parents()
+            .iter()
+            .map(|p| {
+                self.nodes
+                    .get(p)
+                    .copied()
+                    .ok_or_else(|| anyhow!("Cannot insert an event with an unknown parent {}", p))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Add the event, which replaces its parents as a head
+        let node = self.graph.add_node((id.clone(), event));
+        for &parent in &parents {
+            self.graph.add_edge(parent, node, ());
+            self.heads.remove(&parent);
+        }
+        self.nodes.insert(id.clone(), node);
+        self.heads.insert(node);
+
+        // The event is replayed as soon as it's ready (after all of its parents) and
+        // precedes the next event in replay order, which doesn't affect the order of
+        // other events (as it has no children)
+        let ready = self
+            .order
+            .iter()
+            .rposition(|n| parents.contains(n))
+            .map_or(0, |position| position + 1);
+        let key = self.key(node);
+        let position = self.order[ready..]
+            .iter()
+            .position(|&n| key < self.key(n))
+            .map_or(self.order.len(), |position| ready + position);
+        self.order.insert(position, node);
+
+        Ok(id)
+    }
+
+    /// Appends a new local event as a child of all current heads, returning its identifier
+    ///
+    /// If there are several heads, the event becomes a merge event joining them.
+    pub fn append(&mut self, event: Event<'a, T>) -> Result<ContentId> {
+        let event = event.with_parents(self.get_heads());
+        self.insert(event)
+    }
+
+    /// Returns the identifiers of all events without children,
+    /// ordered by their timestamps (and their identifiers, if their timestamps are equal)
+    pub fn get_heads(&self) -> Vec<ContentId> {
+        let mut heads: Vec<_> = self.heads.iter().map(|&node| self.key(node)).collect();
+        heads.sort();
+
+        heads
+            .into_iter()
+            .map(|(_, _, node)| self.graph[node].0.clone())
+            .collect()
+    }
+
+    /// Returns the identifiers of all ancestors of an event (excluding itself), in replay order
+    pub fn get_ancestors(&self, id: &ContentId) -> Result<Vec<ContentId>> {
+        let node = self.node(id)?;
+        let ancestors = self.ancestor_nodes(node);
+
+        Ok(self
+            .order
+            .iter()
+            .filter(|n| **n != node && ancestors.contains(n))
+            .map(|&n| self.graph[n].0.clone())
+            .collect())
+    }
+
+    /// Checks if an event is an ancestor of another one
+    pub fn is_ancestor(&self, ancestor: &ContentId, descendant: &ContentId) -> Result<bool> {
+        let ancestor = self.node(ancestor)?;
+        let descendant = self.node(descendant)?;
+
+        Ok(ancestor != descendant && has_path_connecting(&self.graph, ancestor, descendant, None))
+    }
+
+    /// Finds the lowest common ancestor of two events (which may be one of the events itself)
+    ///
+    /// If there are several lowest common ancestors (e.g. after criss-cross merges),
+    /// the latest one in replay order is returned. Returns `None` if the events
+    /// don't share any ancestors.
+    pub fn lowest_common_ancestor(
+        &self,
+        first: &ContentId,
+        second: &ContentId,
+    ) -> Result<Option<ContentId>> {
+        let first = self.ancestor_nodes(self.node(first)?);
+        let second = self.ancestor_nodes(self.node(second)?);
+        let common: HashSet<NodeIndex> = first.intersection(&second).copied().collect();
+
+        // The lowest common ancestors aren't ancestors of other common ancestors
+        // As the ancestors of common ancestors are common, it's enough to check their children
+        let lowest = self.order.iter().rev().find(|&&node| {
+            common.contains(&node)
+                && !self
+                    .graph
+                    .neighbors_directed(node, Direction::Outgoing)
+                    .any(|child| common.contains(&child))
+        });
+
+        Ok(lowest.map(|&node| self.graph[node].0.clone()))
+    }
+
+    /// Returns references to all events in replay order
+    pub fn get_events(&self) -> Vec<&Event<'a, T>> {
+        self.order.iter().map(|&node| &self.graph[node].1).collect()
+    }
+
+    /// Replays all events in replay order, returning the resulting projection
+    ///
+    /// Events which can't be applied (e.g. conflicting with a concurrent branch) are skipped.
+    pub fn replay(&self) -> Vec<Cow<'a, T>> {
+        self.replay_nodes(self.order.iter().copied()).0
+    }
+
+    /// Returns the identifiers of the events skipped when replaying all events, in replay order
+    pub fn get_skipped(&self) -> Vec<ContentId> {
+        self.replay_nodes(self.order.iter().copied()).1
+    }
+
+    /// Replays an event and its ancestors, returning the projection as seen by the event
+    ///
+    /// Events which can't be applied (e.g. conflicting with a concurrent branch) are skipped.
+    pub fn project(&self, id: &ContentId) -> Result<Vec<Cow<'a, T>>> {
+        let ancestors = self.ancestor_nodes(self.node(id)?);
+
+        Ok(self
+            .replay_nodes(self.order.iter().copied().filter(|n| ancestors.contains(n)))
+            .0)
+    }
+
+    /// Returns the node of an event
+    fn node(&self, id: &ContentId) -> Result<NodeIndex> {
+        self.nodes
+            .get(id)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown event {}", id))
+    }
+
+    /// Returns the nodes of an event and all its ancestors
+    fn ancestor_nodes(&self, node: NodeIndex) -> HashSet<NodeIndex> {
+        let mut ancestors = HashSet::new();
+        let mut dfs = Dfs::new(Reversed(&self.graph), node);
+        while let Some(ancestor) = dfs.next(Reversed(&self.graph)) {
+            ancestors.insert(ancestor);
+        }
+
+        ancestors
+    }
+
+    /// Returns the key ordering nodes by their timestamps and identifiers
+    fn key(&self, node: NodeIndex) -> (Timestamp, Vec<u8>, NodeIndex) {
+        let (id, event) = &self.graph[node];
+        (*event.get_time(), id.to_bytes(), node)
+    }
+
+    /// Replays some nodes in a given order, returning the resulting projection
+    /// along with the identifiers of the events which were skipped
+    fn replay_nodes<I>(&self, nodes: I) -> (Vec<Cow<'a, T>>, Vec<ContentId>)
+    where
+        I: IntoIterator<Item = NodeIndex>,
+    {
+        let mut projection = vec![];
+        let mut skipped = vec![];
+        for node in nodes {
+            // Skip events conflicting with the events replayed before
+            let (id, event) = &self.graph[node];
+            if Segment::apply_event_to(&mut projection, event.clone()).is_err() {
+                skipped.push(id.clone());
+            }
+        }
+
+        (projection, skipped)
+    }
+}
+
+impl<'a, T> Default for History<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> Serialize for History<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.get_events())
+    }
+}
+
+impl<'de, 'a, T> Deserialize<'de> for History<'a, T>
+where
+    T: Clone + PartialEq + Serialize + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let events = Vec::<Event<'a, T>>::deserialize(deserializer)?;
+        Self::from_events(events).map_err(de::Error::custom)
+    }
+}
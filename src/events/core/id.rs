@@ -0,0 +1,129 @@
+use crate::format;
+use anyhow::{anyhow, bail, Result};
+use multihash::{Code, Multihash, MultihashDigest};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// The version of the content identifiers
+const VERSION: u64 = 1;
+
+/// The prefix of the (hexadecimal) multibase string representation
+const MULTIBASE_HEX: char = 'f';
+
+/**
+A self-describing content identifier (modeled after CIDv1).
+
+It consists of the multicodec of the encoding used for hashing and a multihash
+(SHA2-256) of the encoded content. It's written as a multibase (hexadecimal)
+string, e.g. `f018080c0011220...`.
+
+Identical content always yields identical identifiers, as it's hashed using
+the canonical encoding of [`libocc::format`].
+
+[`libocc::format`]: ../format/index.html
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentId {
+    /// The multicodec of the encoding of the content
+    codec: u64,
+
+    /// The multihash of the encoded content
+    hash: Multihash,
+}
+
+impl ContentId {
+    /// The multicodec of the canonical encoding of `libocc::format`
+    /// (from the private use range of the multicodec table)
+    pub const LIBOCC_FORMAT: u64 = 0x30_0000;
+
+    /// Computes the identifier of a value using its canonical encoding
+    pub fn of<V: Serialize + ?Sized>(value: &V) -> Result<Self> {
+        Ok(Self {
+            codec: Self::LIBOCC_FORMAT,
+            hash: Code::Sha2_256.digest(&format::to_vec(value)?),
+        })
+    }
+
+    /// Returns the multicodec of the encoding of the content
+    pub fn get_codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// Returns a shared reference to the multihash of the encoded content
+    pub fn get_hash(&self) -> &Multihash {
+        &self.hash
+    }
+
+    /// Returns the binary representation of the identifier
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        format::write_varint(&mut bytes, VERSION);
+        format::write_varint(&mut bytes, self.codec);
+        bytes.extend(self.hash.to_bytes());
+        bytes
+    }
+
+    /// Parses the binary representation of an identifier
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let version = read_varint(&mut bytes)?;
+        if version != VERSION {
+            bail!("Unsupported content identifier version {}", version)
+        }
+
+        Ok(Self {
+            codec: read_varint(&mut bytes)?,
+            hash: Multihash::from_bytes(bytes)?,
+        })
+    }
+}
+
+/// Reads an unsigned varint of the binary representation, advancing the slice
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    format::read_varint(bytes).map_err(|e| anyhow!("Invalid varint in content identifier: {}", e))
+}
+
+impl fmt::Display for ContentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", MULTIBASE_HEX)?;
+        for byte in self.to_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        // Return Ok
+        Ok(())
+    }
+}
+
+impl FromStr for ContentId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s
+            .strip_prefix(MULTIBASE_HEX)
+            .ok_or_else(|| anyhow!("Unsupported multibase of content identifier {:?}", s))?;
+
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            bail!("Invalid content identifier {:?}", s)
+        }
+
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl Serialize for ContentId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A function deriving the key an entity is indexed by
+pub type KeyFunction<T> = fn(&T) -> String;
+
+/// The identifier of an entity within the snapshot of a segment
+///
+/// Identifiers are assigned in ascending order as entities are created, and
+/// stay the same while they're updated (or other entities are deleted). They're
+/// kept when events are merged out of order, a projector is forked or segments
+/// are persisted, so they're only unique (not necessarily ascending) within a
+/// snapshot.
+pub type EntityId = u64;
+
+/**
+A secondary index of a segments snapshot.
+
+It maps the keys derived from the entities (see [`Projector::add_index`]) to
+the identifiers of the entities in the snapshot (see [`Segment::get_entity`]).
+It's maintained incrementally while events are pushed, and persisted along
+with the snapshot (but isn't part of the content identifier of the segment).
+
+[`Projector::add_index`]: struct.Projector.html#method.add_index
+[`Segment::get_entity`]: struct.Segment.html#method.get_entity
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Index {
+    /// The identifiers of the entities (in ascending order) by their keys
+    entries: BTreeMap<String, Vec<EntityId>>,
+}
+
+impl Index {
+    /// Builds an index of a snapshot, given the identifiers of its entities
+    pub(super) fn build<T: Clone>(
+        snapshot: &[Cow<'_, T>],
+        ids: &[EntityId],
+        key_function: KeyFunction<T>,
+    ) -> Self {
+        let mut index = Self::default();
+        for (entity, &id) in snapshot.iter().zip(ids) {
+            index.insert(key_function(entity), id);
+        }
+
+        index
+    }
+
+    /// Returns the identifiers of all entities with a key (in ascending order)
+    pub fn get(&self, key: &str) -> &[EntityId] {
+        self.entries.get(key).map_or(&[], |ids| ids)
+    }
+
+    /// Returns a reference to the identifiers of the entities by their keys
+    pub fn get_entries(&self) -> &BTreeMap<String, Vec<EntityId>> {
+        &self.entries
+    }
+
+    /// Adds the identifier of an entity with a key
+    pub(super) fn insert(&mut self, key: String, id: EntityId) {
+        let ids = self.entries.entry(key).or_default();
+        if let Err(position) = ids.binary_search(&id) {
+            ids.insert(position, id);
+        }
+    }
+
+    /// Removes the identifier of an entity with a key
+    pub(super) fn remove(&mut self, key: &str, id: EntityId) {
+        if let Some(ids) = self.entries.get_mut(key) {
+            if let Ok(position) = ids.binary_search(&id) {
+                ids.remove(position);
+            }
+            if ids.is_empty() {
+                self.entries.remove(key);
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::format;
+use anyhow::Result;
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// A conflict found by a three-way merge, concerning a single entity
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Both sides updated the entity differently (the merge keeps our version)
+    BothUpdated {
+        /// The entity before the updates
+        base: Cow<'a, T>,
+
+        /// The entity updated by our side
+        ours: Cow<'a, T>,
+
+        /// The entity updated by their side
+        theirs: Cow<'a, T>,
+    },
+
+    /// Our side updated the entity, while their side deleted it (the merge keeps our version)
+    UpdatedDeleted {
+        /// The entity before the update and deletion
+        base: Cow<'a, T>,
+
+        /// The entity updated by our side
+        ours: Cow<'a, T>,
+    },
+
+    /// Our side deleted the entity, while their side updated it (the merge keeps their version)
+    DeletedUpdated {
+        /// The entity before the deletion and update
+        base: Cow<'a, T>,
+
+        /// The entity updated by their side
+        theirs: Cow<'a, T>,
+    },
+
+    /// Both sides created the entity differently (the merge keeps our version)
+    DuplicateCreate {
+        /// The entity created by our side
+        ours: Cow<'a, T>,
+
+        /// The entity created by their side
+        theirs: Cow<'a, T>,
+    },
+}
+
+/**
+The result of a three-way merge of projections.
+
+See [`merge3`] for details.
+
+[`merge3`]: fn.merge3.html
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreeWayMerge<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The merged projection
+    merged: Vec<Cow<'a, T>>,
+
+    /// The conflicts found while merging
+    conflicts: Vec<MergeConflict<'a, T>>,
+}
+
+impl<'a, T> ThreeWayMerge<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Returns a reference to the merged projection
+    pub fn get_merged(&self) -> &Vec<Cow<'a, T>> {
+        &self.merged
+    }
+
+    /// Returns a reference to the conflicts found while merging
+    pub fn get_conflicts(&self) -> &Vec<MergeConflict<'a, T>> {
+        &self.conflicts
+    }
+
+    /// Checks if the merge didn't find any conflicts
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Consumes the merge, returning the merged projection and the conflicts
+    pub fn take(self) -> (Vec<Cow<'a, T>>, Vec<MergeConflict<'a, T>>) {
+        (self.merged, self.conflicts)
+    }
+}
+
+/**
+Merges two projections which diverged from a common base projection.
+
+Entities are identified using `PartialEq` (like everywhere else), while their
+contents are compared using their canonical encoding (see [`libocc::format`]),
+so changes to fields ignored by `PartialEq` are detected as well.
+
+Changes made by only one side are applied, and identical changes made by both
+sides are applied once. Diverging changes are reported as conflicts, keeping
+the updated (rather than deleted) version of an entity, and our version if
+both sides updated it. The merged projection contains the entities of the
+base first, followed by the ones created by our and by their side.
+
+[`libocc::format`]: ../format/index.html
+*/
+pub fn merge3<'a, T>(
+    base: &[Cow<'a, T>],
+    ours: &[Cow<'a, T>],
+    theirs: &[Cow<'a, T>],
+) -> Result<ThreeWayMerge<'a, T>>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    let mut merged = vec![];
+    let mut conflicts = vec![];
+
+    // Merge the entities of the base
+    for entity in base {
+        let our_version = find(ours, entity);
+        let their_version = find(theirs, entity);
+
+        match (our_version, their_version) {
+            (Some(our_version), Some(their_version)) => {
+                let ours_changed = !same_content(entity, our_version)?;
+                let theirs_changed = !same_content(entity, their_version)?;
+
+                if ours_changed && theirs_changed && !same_content(our_version, their_version)? {
+                    conflicts.push(MergeConflict::BothUpdated {
+                        base: entity.clone(),
+                        ours: our_version.clone(),
+                        theirs: their_version.clone(),
+                    });
+                }
+
+                // Prefer our changes over theirs
+                merged.push(if ours_changed || !theirs_changed {
+                    our_version.clone()
+                } else {
+                    their_version.clone()
+                });
+            }
+            (Some(our_version), None) => {
+                // Their deletion wins, unless we updated the entity
+                if !same_content(entity, our_version)? {
+                    conflicts.push(MergeConflict::UpdatedDeleted {
+                        base: entity.clone(),
+                        ours: our_version.clone(),
+                    });
+                    merged.push(our_version.clone());
+                }
+            }
+            (None, Some(their_version)) => {
+                // Our deletion wins, unless they updated the entity
+                if !same_content(entity, their_version)? {
+                    conflicts.push(MergeConflict::DeletedUpdated {
+                        base: entity.clone(),
+                        theirs: their_version.clone(),
+                    });
+                    merged.push(their_version.clone());
+                }
+            }
+            // Deleted by both sides
+            (None, None) => {}
+        }
+    }
+
+    // Add the entities created by our side
+    for entity in ours.iter().filter(|e| find(base, e).is_none()) {
+        if let Some(their_version) = find(theirs, entity) {
+            if !same_content(entity, their_version)? {
+                conflicts.push(MergeConflict::DuplicateCreate {
+                    ours: entity.clone(),
+                    theirs: their_version.clone(),
+                });
+            }
+        }
+
+        merged.push(entity.clone());
+    }
+
+    // Add the entities created by their side only
+    merged.extend(
+        theirs
+            .iter()
+            .filter(|e| find(base, e).is_none() && find(ours, e).is_none())
+            .cloned(),
+    );
+
+    Ok(ThreeWayMerge { merged, conflicts })
+}
+
+/// Finds an entity in a projection
+fn find<'b, 'a, T>(projection: &'b [Cow<'a, T>], entity: &T) -> Option<&'b Cow<'a, T>>
+where
+    T: Clone + PartialEq,
+{
+    projection.iter().find(|e| ***e == *entity)
+}
+
+/// Checks if two entities have the same contents (not only the same identity)
+fn same_content<T: Serialize>(first: &T, second: &T) -> Result<bool> {
+    Ok(format::to_vec(first)? == format::to_vec(second)?)
+}
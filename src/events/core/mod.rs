@@ -8,14 +8,50 @@
 
 use chrono::DateTime;
 
+mod aggregate;
+mod change;
+mod diff;
 mod event;
+#[cfg(feature = "git")]
+mod git;
+mod history;
+mod id;
+mod index;
+mod merge3;
+mod pending;
+mod projection;
 mod projector;
+mod query;
 mod segment;
+mod shared;
+#[cfg(feature = "async")]
+mod storage;
+mod store;
+mod transaction;
+mod version;
 // mod repository;
 
+pub use aggregate::{Series, MAX_STEPS};
+pub use change::Change;
+pub use diff::Diff;
 pub use event::*;
+#[cfg(feature = "git")]
+pub use git::*;
+pub use history::History;
+pub use id::ContentId;
+pub use index::*;
+pub use merge3::*;
+pub use pending::PendingEvent;
+pub use projection::Projection;
 pub use projector::*;
+pub use query::Query;
 pub use segment::*;
+pub use shared::*;
+#[cfg(feature = "async")]
+pub use storage::*;
+pub use store::*;
+pub use transaction::*;
+pub use version::*;
 // pub use repository::*;
 
 /// The timestamp type used in this library
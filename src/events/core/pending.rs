@@ -0,0 +1,65 @@
+use crate::events::{Event, Timestamp, VersionVector};
+use serde::{Deserialize, Serialize};
+
+/**
+An event held back by a [`Projector`] until its causal predecessors arrive.
+
+[`Projector`]: struct.Projector.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingEvent<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The event waiting to be applied
+    event: Event<'a, T>,
+
+    /// The moment in time the event was received
+    received: Timestamp,
+}
+
+impl<'a, T> PendingEvent<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Constructs a new pending event received at a given moment in time
+    pub(super) fn new(event: Event<'a, T>, received: Timestamp) -> Self {
+        Self { event, received }
+    }
+
+    /// Returns a reference to the event waiting to be applied
+    pub fn get_event(&self) -> &Event<'a, T> {
+        &self.event
+    }
+
+    /// Returns a reference to the moment in time the event was received
+    pub fn get_received(&self) -> &Timestamp {
+        &self.received
+    }
+
+    /// Consumes the pending event, returning the event waiting to be applied
+    pub fn take(self) -> Event<'a, T> {
+        self.event
+    }
+
+    /// Checks if all causal predecessors of the event are known to a given frontier
+    ///
+    /// Events without a version are always ready, as their predecessors are unknown.
+    pub(super) fn is_ready(&self, frontier: &VersionVector) -> bool {
+        let version = match self.event.get_version() {
+            Some(version) => version,
+            None => return true,
+        };
+        let replica = self.event.get_replica();
+
+        version.get_counters().iter().all(|(id, &counter)| {
+            if Some(id) == replica {
+                // The previous event of the originating replica must be known
+                counter <= frontier.get(id) + 1
+            } else {
+                // All events of other replicas seen by the originating replica must be known
+                counter <= frontier.get(id)
+            }
+        })
+    }
+}
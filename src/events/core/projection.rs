@@ -0,0 +1,98 @@
+use crate::events::Event;
+use std::{any::Any, collections::BTreeMap, fmt};
+
+/// The states of several custom projections by their names
+pub(super) type Projections<T> = BTreeMap<String, Box<dyn AnyProjection<T>>>;
+
+/**
+A user-defined projection (or read model) of the events of a [`Projector`].
+
+Besides the built-in projection (the list of current entities), a projector
+can maintain any number of custom projections, e.g. counts, aggregates or
+lookup tables (see [`Projector::add_projection`]). They're updated
+incrementally whenever an event is applied, and snapshotted per segment, so
+they can be projected at any moment in time just like the built-in one.
+
+```ignore
+#[derive(Clone, Default)]
+struct BookCount(usize);
+
+impl Projection<Book> for BookCount {
+    fn apply(&mut self, event: &Event<Book>) {
+        match event {
+            Event::Create(_) => self.0 += 1,
+            Event::Delete(_) => self.0 -= 1,
+            Event::Update(_) => {}
+        }
+    }
+}
+```
+
+[`Projector`]: struct.Projector.html
+[`Projector::add_projection`]: struct.Projector.html#method.add_projection
+*/
+pub trait Projection<T>: Clone + Send + Sync + 'static
+where
+    T: Clone + PartialEq,
+{
+    /// Updates the projection to reflect the changes of an event
+    fn apply(&mut self, event: &Event<'_, T>);
+}
+
+/// A projection of any type (used to store projections of different types together)
+pub(super) trait AnyProjection<T>: Send + Sync
+where
+    T: Clone + PartialEq,
+{
+    /// Updates the projection to reflect the changes of an event
+    fn apply(&mut self, event: &Event<'_, T>);
+
+    /// Returns a boxed copy of the projection
+    fn box_clone(&self) -> Box<dyn AnyProjection<T>>;
+
+    /// Returns a reference to the projection, so it can be downcast to its type
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns the name of the type of the projection
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T, P> AnyProjection<T> for P
+where
+    T: Clone + PartialEq,
+    P: Projection<T>,
+{
+    fn apply(&mut self, event: &Event<'_, T>) {
+        Projection::apply(self, event)
+    }
+
+    fn box_clone(&self) -> Box<dyn AnyProjection<T>> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<P>()
+    }
+}
+
+impl<T> Clone for Box<dyn AnyProjection<T>>
+where
+    T: Clone + PartialEq,
+{
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl<T> fmt::Debug for dyn AnyProjection<T>
+where
+    T: Clone + PartialEq,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Projection({})", self.type_name())
+    }
+}
@@ -1,19 +1,117 @@
-use crate::events::{Event, Segment, Timestamp};
+use super::{
+    change::Subscribers,
+    projection::{AnyProjection, Projections},
+};
+use crate::{
+    events::{
+        Change, Conflict, ContentId, Diff, Event, KeyFunction, PendingEvent, Projection, Query,
+        ReplicaId, Segment, Timestamp, Transaction, TransactionId, VersionVector,
+    },
+    format,
+};
 use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, ops::Deref};
+use std::{
+    borrow::{Borrow, Cow},
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+    sync::{mpsc::Receiver, Arc, Mutex, OnceLock},
+};
+
+/// The changes and conflicts resulting from merging a branch
+pub type BranchMerge<'a, T> = (Vec<Change<'a, T>>, Vec<Conflict<'a, T>>);
+
+/// Resolves the conflicts of a projector concerning the entities of some changes
+/// on behalf of a replica, returning the resulting changes
+pub(crate) type Resolver<'a, T> =
+    fn(&mut Projector<'a, T>, &str, &[Change<'a, T>]) -> Result<Vec<Change<'a, T>>>;
+
+/// The timestamps of the events of a projector by their content identifiers
+///
+/// Events are only hashed when looking one up (as hashing requires them to be
+/// serializable), so the timestamps of the events added since are kept until then.
+#[derive(Debug, Default)]
+struct EventIds(Mutex<HashedEvents>);
+
+/// The state of [`EventIds`], behind its lock
+#[derive(Debug, Default, Clone)]
+struct HashedEvents {
+    /// The timestamps of the hashed events by their identifiers (if all events were hashed)
+    timestamps: Option<HashMap<ContentId, Timestamp>>,
+
+    /// The timestamps of the events added after hashing
+    added: Vec<Timestamp>,
+}
+
+impl EventIds {
+    /// Remembers the timestamp of an event added to the projector
+    fn add(&mut self, timestamp: Timestamp) {
+        // Unwraps safely because the lock is never held while panicking
+        let hashed = self.0.get_mut().unwrap();
+        if hashed.timestamps.is_some() {
+            hashed.added.push(timestamp);
+        }
+    }
+}
+
+impl Clone for EventIds {
+    fn clone(&self) -> Self {
+        // Unwraps safely because the lock is never held while panicking
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
 
 /**
 Projects events from an event log
 
 Manages several segments internally
+
+Sealed segments are shared using copy-on-write (like the ones of a
+[`SharedProjector`]), so cloning or forking a projector only copies
+the segments modified afterwards.
+
+[`SharedProjector`]: struct.SharedProjector.html
 */
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Projector<'a, T>
 where
     T: Clone + PartialEq,
 {
-    segments: Vec<Segment<'a, T>>,
+    segments: Vec<Arc<Segment<'a, T>>>,
+
+    /// The identifier of the next transaction
+    #[serde(default)]
+    next_transaction: TransactionId,
+
+    /// The subscribers to be notified of changes
+    #[serde(skip, default = "Subscribers::default")]
+    subscribers: Subscribers<'a, T>,
+
+    /// The delivered events waiting for their causal predecessors
+    #[serde(default = "Vec::new")]
+    pending: Vec<PendingEvent<'a, T>>,
+
+    /// The moment in time this projector was forked from another one (if it's a fork)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    forked_at: Option<Timestamp>,
+
+    /// The initial states of the custom projections by their names
+    #[serde(skip, default = "BTreeMap::new")]
+    projections: Projections<T>,
+
+    /// The replica resolving conflicts whenever events are merged (if any)
+    #[serde(skip, default = "Option::default")]
+    resolver: Option<(ReplicaId, Resolver<'a, T>)>,
+
+    /// The frontier of all events known (computed on first use, e.g. after deserializing)
+    #[serde(skip, default = "OnceLock::new")]
+    frontier: OnceLock<VersionVector>,
+
+    /// The timestamps of the events by their content identifiers (hashed on first lookup)
+    #[serde(skip, default = "EventIds::default")]
+    event_ids: EventIds,
 }
 
 impl<'a, T> Projector<'a, T>
@@ -23,10 +121,53 @@ where
     /// Generates a new projector for a given type
     pub fn new() -> Projector<'a, T> {
         Self {
-            segments: vec![Segment::new()],
+            segments: vec![Arc::new(Segment::new())],
+            next_transaction: 0,
+            subscribers: Subscribers::default(),
+            pending: vec![],
+            forked_at: None,
+            projections: BTreeMap::new(),
+            resolver: None,
+            frontier: OnceLock::new(),
+            event_ids: EventIds::default(),
         }
     }
 
+    /// Generates a projector from previously created segments (e.g. loaded from a storage)
+    pub fn from_segments(segments: Vec<Segment<'a, T>>) -> Result<Projector<'a, T>> {
+        Self::from_shared_segments(segments.into_iter().map(Arc::new).collect())
+    }
+
+    /// Generates a projector from previously created segments shared with others
+    pub(super) fn from_shared_segments(
+        segments: Vec<Arc<Segment<'a, T>>>,
+    ) -> Result<Projector<'a, T>> {
+        // There must always be at least one segment
+        if segments.is_empty() {
+            bail!("Cannot create a projector without any segments")
+        }
+
+        // Continue after the latest known transaction
+        let next_transaction = segments
+            .iter()
+            .flat_map(|s| s.get_events())
+            .filter_map(|e| e.get_transaction())
+            .max()
+            .map_or(0, |id| id + 1);
+
+        Ok(Self {
+            segments,
+            next_transaction,
+            subscribers: Subscribers::default(),
+            pending: vec![],
+            forked_at: None,
+            projections: BTreeMap::new(),
+            resolver: None,
+            frontier: OnceLock::new(),
+            event_ids: EventIds::default(),
+        })
+    }
+
     /// Returns the current (cached) projection as a shared reference
     pub fn get_projection(&self) -> &Vec<Cow<'a, T>> {
         // Unwraps safely because there's always at least one segment
@@ -35,21 +176,33 @@ where
 
     /// Performs a projection using a copy of the previous segments' snapshot if available
     pub fn project_at(&self, timestamp: &Timestamp) -> Option<Vec<Cow<'a, T>>> {
+        Self::project_segments_at(&self.segments, timestamp)
+    }
+
+    /// Performs a projection of some segments using a copy of the previous segments' snapshot if available
+    pub(super) fn project_segments_at<S>(
+        segments: &[S],
+        timestamp: &Timestamp,
+    ) -> Option<Vec<Cow<'a, T>>>
+    where
+        S: Borrow<Segment<'a, T>>,
+    {
         // Find the segment containing the timestamp (if available):
         // The position of the segment containing the requested timestamp
-        let latest_segment_pos = self.get_latest_segment_pos(timestamp)?;
+        let latest_segment_pos = Self::get_latest_segment_pos(segments, timestamp)?;
 
         // The segment containing the timestamp
         // Unwraps safely because the index was found previously
-        let containing_segment = self.segments.get(latest_segment_pos).unwrap();
+        let containing_segment = segments.get(latest_segment_pos).unwrap().borrow();
 
         // Check if another segment exists which could provide a snapshot for projection
         let snapshot = if latest_segment_pos != 0 {
             // Return a copy of the snapshot of the previous segment
             // Unwraps safely because there're at least two segments (because != 0)
-            self.segments
+            segments
                 .get(latest_segment_pos - 1)
                 .unwrap()
+                .borrow()
                 .get_projection()
                 .clone()
         } else {
@@ -62,20 +215,712 @@ where
         containing_segment.project_at_onto(timestamp, snapshot)
     }
 
+    /// Adds (or replaces) a secondary index, mapping the keys derived from the
+    /// entities by a function to the entities
+    ///
+    /// The index is built for the snapshots of all segments, and maintained
+    /// whenever events are pushed. Indexes are persisted along with the snapshots
+    /// (but aren't part of the content identifiers of segments), while their key
+    /// functions aren't: Add the index again after deserializing a projector to
+    /// keep maintaining it (the persisted indexes are reused rather than rebuilt).
+    pub fn add_index(&mut self, name: &str, key_function: KeyFunction<T>) {
+        for segment in &mut self.segments {
+            Arc::make_mut(segment).add_index(name, key_function);
+        }
+    }
+
+    /// Returns references to the entities of the current projection with a key in an index
+    pub fn lookup(&self, name: &str, key: &str) -> Result<Vec<&Cow<'a, T>>> {
+        // Unwraps safely because there's always at least one segment
+        self.segments.last().unwrap().lookup(name, key)
+    }
+
+    /// Returns the entities with a key in an index at a moment in time
+    ///
+    /// Only the entities indexed in the snapshot of the previous segment and the
+    /// events of the segment containing the timestamp are considered, instead of
+    /// projecting the whole snapshot. The entities are returned in the order they
+    /// were indexed.
+    ///
+    /// Returns `None` if the timestamp predates the first segment.
+    pub fn lookup_at(
+        &self,
+        name: &str,
+        key: &str,
+        timestamp: &Timestamp,
+    ) -> Result<Option<Vec<Cow<'a, T>>>> {
+        let segment_pos = match Self::get_latest_segment_pos(&self.segments, timestamp) {
+            Some(segment_pos) => segment_pos,
+            None => return Ok(None),
+        };
+        let containing_segment = &self.segments[segment_pos];
+        let key_function = containing_segment
+            .get_key_function(name)
+            .ok_or_else(|| anyhow!("Unknown index {:?}", name))?;
+
+        // The matching entities of the previous snapshot (if any)
+        let mut entities: Vec<Cow<'a, T>> = if segment_pos != 0 {
+            self.segments[segment_pos - 1]
+                .lookup(name, key)?
+                .into_iter()
+                .cloned()
+                .collect()
+        } else {
+            vec![]
+        };
+
+        // Apply the events up to (and including) the timestamp
+        for event in containing_segment
+            .get_events()
+            .iter()
+            .take_while(|e| e.get_time() <= timestamp)
+        {
+            let position = entities.iter().position(|e| **e == **event);
+            let matches = !matches!(event, Event::Delete(_)) && key_function(event) == key;
+
+            match (position, matches) {
+                (Some(position), true) => entities[position] = event.clone().take(),
+                (Some(position), false) => {
+                    entities.remove(position);
+                }
+                (None, true) => entities.push(event.clone().take()),
+                (None, false) => {}
+            }
+        }
+
+        Ok(Some(entities))
+    }
+
+    /// Adds (or replaces) a custom projection (see [`Projection`](trait.Projection.html))
+    /// starting at an initial state
+    ///
+    /// The projection is computed for all segments, and updated whenever events
+    /// are applied. Custom projections aren't persisted: Add them again after
+    /// deserializing a projector.
+    pub fn add_projection<P: Projection<T>>(&mut self, name: &str, initial: P) {
+        self.add_any_projection(name, Box::new(initial));
+    }
+
+    /// Adds (or replaces) a custom projection of any type
+    fn add_any_projection(&mut self, name: &str, initial: Box<dyn AnyProjection<T>>) {
+        self.add_any_projection_from(name, initial, 0);
+    }
+
+    /// Adds (or replaces) a custom projection of any type to the segments starting at a
+    /// given position, continuing with its state in the previous segment (if any)
+    fn add_any_projection_from(
+        &mut self,
+        name: &str,
+        initial: Box<dyn AnyProjection<T>>,
+        start: usize,
+    ) {
+        let mut projection = match start.checked_sub(1) {
+            Some(previous) => self.segments[previous]
+                .get_custom_projection(name)
+                .map_or_else(|| initial.clone(), |p| p.box_clone()),
+            None => initial.clone(),
+        };
+        for segment in &mut self.segments[start..] {
+            let segment = Arc::make_mut(segment);
+            segment.reproject_custom_onto(name, projection);
+
+            // Unwraps safely because the projection was just added
+            projection = segment.get_custom_projection(name).unwrap().box_clone();
+        }
+
+        self.projections.insert(name.to_owned(), initial);
+    }
+
+    /// Returns a reference to the current state of a custom projection
+    pub fn get_custom_projection<P: Projection<T>>(&self, name: &str) -> Result<&P> {
+        // Unwraps safely because there's always at least one segment
+        let projection = self
+            .segments
+            .last()
+            .unwrap()
+            .get_custom_projection(name)
+            .ok_or_else(|| anyhow!("Unknown projection {:?}", name))?;
+
+        Self::downcast(name, projection)
+    }
+
+    /// Performs a custom projection using a copy of the previous segments' state
+    ///
+    /// Returns `None` if the timestamp predates the first segment.
+    pub fn project_custom_at<P: Projection<T>>(
+        &self,
+        name: &str,
+        timestamp: &Timestamp,
+    ) -> Result<Option<P>> {
+        let segment_pos = match Self::get_latest_segment_pos(&self.segments, timestamp) {
+            Some(segment_pos) => segment_pos,
+            None => return Ok(None),
+        };
+
+        // The state at the beginning of the segment containing the timestamp
+        let initial = if segment_pos != 0 {
+            self.segments[segment_pos - 1].get_custom_projection(name)
+        } else {
+            self.projections.get(name).map(|p| p.as_ref())
+        }
+        .ok_or_else(|| anyhow!("Unknown projection {:?}", name))?;
+        let mut projection = Self::downcast::<P>(name, initial)?.clone();
+
+        // Project all events up to (and including) the timestamp
+        for event in self.segments[segment_pos]
+            .get_events()
+            .iter()
+            .take_while(|e| e.get_time() <= timestamp)
+        {
+            projection.apply(event);
+        }
+
+        Ok(Some(projection))
+    }
+
+    /// Casts a custom projection to its type
+    fn downcast<'b, P: Projection<T>>(
+        name: &str,
+        projection: &'b dyn AnyProjection<T>,
+    ) -> Result<&'b P> {
+        projection.as_any().downcast_ref().ok_or_else(|| {
+            anyhow!(
+                "Projection {:?} is of type {}",
+                name,
+                projection.type_name()
+            )
+        })
+    }
+
+    /// Creates a query over the projection (see [`Query`](struct.Query.html))
+    pub fn query(&self) -> Query<'_, 'a, T> {
+        Query::new(self)
+    }
+
+    /// Compares the projections at two moments in time, returning the entities
+    /// created, updated and deleted in between
+    ///
+    /// Only the projection at `from` is computed (using the snapshot of the previous
+    /// segment). The events in between are then applied to a copy of it, keeping
+    /// track of the entities they concern by their positions, so every entity is
+    /// only compared once. Entities both created and deleted in between aren't
+    /// reported. The entities are reported in the order they were first changed.
+    ///
+    /// Returns `None` if `from` predates the first segment or `to` predates `from`.
+    pub fn diff(&self, from: &Timestamp, to: &Timestamp) -> Option<Diff<'a, T>> {
+        if to < from {
+            return None;
+        }
+
+        let old_projection = self.project_at(from)?;
+
+        // The positions of the entities in the old projection (if they existed before),
+        // and the order they were first changed in (if they were)
+        let mut projection = old_projection.clone();
+        let mut origins: Vec<Option<usize>> = (0..projection.len()).map(Some).collect();
+        let mut changed: Vec<Option<usize>> = vec![None; projection.len()];
+
+        // The positions of the deleted entities in the old projection, and the order
+        // they were first changed in
+        let mut deleted: Vec<(usize, usize)> = vec![];
+
+        // Apply the events after `from` up to (and including) `to`
+        let events = self
+            .get_events_from(from)
+            .into_iter()
+            .skip_while(|e| e.get_time() <= from)
+            .take_while(|e| e.get_time() <= to);
+        for (order, event) in events.enumerate() {
+            let position = projection.iter().position(|e| **e == **event);
+            let old_len = projection.len();
+            Segment::apply_event_to(&mut projection, event.clone()).ok()?;
+
+            match position {
+                // Updated
+                Some(position) if projection.len() == old_len => {
+                    changed[position].get_or_insert(order);
+                }
+                // Deleted
+                Some(position) => {
+                    let first = changed.remove(position).unwrap_or(order);
+                    if let Some(origin) = origins.remove(position) {
+                        deleted.push((origin, first));
+                    }
+                }
+                // Created (possibly again)
+                None => {
+                    let again = deleted
+                        .iter()
+                        .position(|&(origin, _)| *old_projection[origin] == **event)
+                        .map(|index| deleted.remove(index));
+                    origins.push(again.map(|(origin, _)| origin));
+                    changed.push(Some(again.map_or(order, |(_, first)| first)));
+                }
+            }
+        }
+
+        // Compare the changed entities in the order they were first changed
+        let mut entries: Vec<(usize, Option<usize>, Option<usize>)> = deleted
+            .into_iter()
+            .map(|(origin, first)| (first, Some(origin), None))
+            .collect();
+        for (position, (origin, first)) in origins.into_iter().zip(changed).enumerate() {
+            if let Some(first) = first {
+                entries.push((first, origin, Some(position)));
+            }
+        }
+        entries.sort_by_key(|&(first, _, _)| first);
+
+        let mut diff = Diff::new();
+        for (_, old, new) in entries {
+            diff.add(
+                old.map(|o| old_projection[o].clone()),
+                new.map(|n| projection[n].clone()),
+            );
+        }
+
+        Some(diff)
+    }
+
     /// Find the segment containing the timestamp (if available):  
     /// The position of the segment containing the requested timestamp
-    fn get_latest_segment_pos(&self, timestamp: &chrono::DateTime<chrono::Utc>) -> Option<usize> {
-        let latest_segment_pos = self
-            .segments
+    fn get_latest_segment_pos<S>(segments: &[S], timestamp: &Timestamp) -> Option<usize>
+    where
+        S: Borrow<Segment<'a, T>>,
+    {
+        let latest_segment_pos = segments
             .iter()
-            .rposition(|s| s.get_time() <= timestamp)?;
+            .rposition(|s| s.borrow().get_time() <= timestamp)?;
         Some(latest_segment_pos)
     }
 
     /// Pushes an event onto the latest segment, updating the projection
     pub fn push(&mut self, event: Event<'a, T>) -> Result<()> {
+        // Only keep a copy of the event (and the entity it replaces) if anyone is listening
+        let pending = if self.subscribers.is_empty() {
+            None
+        } else {
+            let old = Self::find(self.get_projection(), &event).cloned();
+            Some((event.clone(), old))
+        };
+
+        // Unwraps safely because there's always at least one segment
+        let version = event.get_version().cloned();
+        let timestamp = *event.get_time();
+        Arc::make_mut(self.segments.last_mut().unwrap()).push(event)?;
+        self.extend_frontier(version.as_ref());
+        self.event_ids.add(timestamp);
+
+        // Notify the subscribers
+        if let Some((event, old)) = pending {
+            let new = Self::find(self.get_projection(), &event).cloned();
+            self.subscribers.notify(&[Change::new(event, old, new)]);
+        }
+
+        Ok(())
+    }
+
+    /// Merges (possibly out-of-order) events into the history of this projector,
+    /// returning the resulting changes of the current projection
+    ///
+    /// Each event is inserted into the segment containing its timestamp, and the
+    /// snapshots of all following segments are projected again. Events predating
+    /// the first segment are inserted into it, moving its timestamp back. Events
+    /// not predating the latest event are applied directly instead, like pushed ones.
+    /// Events already contained in the history are skipped, so merging is idempotent.
+    /// If any of the events fails, none of them are merged.
+    ///
+    /// The returned changes contain an entry for every entity concerned by the
+    /// merged events, along with the latest merged event concerning it.
+    ///
+    /// Version vectors aren't used for ordering: Concurrent events (see
+    /// [`get_conflicts`](#method.get_conflicts)) are projected in the order of their
+    /// wall-clock timestamps like all other events, so the latest one wins until
+    /// their conflict is resolved. Events predating an event they causally follow
+    /// (e.g. due to clock skew) are rejected, as they would be projected before it.
+    /// [`stamp`](#method.stamp) never creates such events.
+    pub fn merge(&mut self, events: Vec<Event<'a, T>>) -> Result<Vec<Change<'a, T>>> {
+        // The events to merge along with the positions of their segments
+        let mut merged: Vec<(usize, Event<'a, T>)> = vec![];
+
+        // The positions of the events to merge by their timestamps
+        let mut merged_times: BTreeMap<Timestamp, Vec<usize>> = BTreeMap::new();
+
+        for event in events {
+            // Find the segment containing the timestamp of the event
+            // Events predating all segments extend the first one
+            let segment_pos = self
+                .segments
+                .iter()
+                .rposition(|s| s.get_time() <= event.get_time())
+                .unwrap_or(0);
+
+            // Skip known events
+            if self.segments[segment_pos].contains(&event) {
+                continue;
+            }
+
+            // Skip events occurring several times in the batch
+            let same_time = merged_times.entry(*event.get_time()).or_default();
+            if same_time
+                .iter()
+                .any(|&position| merged[position].1 == event)
+            {
+                continue;
+            }
+            same_time.push(merged.len());
+            merged.push((segment_pos, event));
+        }
+
+        // Nothing to merge
+        if merged.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Check if any event causally follows a known or merged event with a later timestamp
+        for (_, event) in merged.iter().filter(|(_, e)| e.get_version().is_some()) {
+            let later_known = self
+                .get_events_from(event.get_time())
+                .into_iter()
+                .filter(|e| e.get_time() > event.get_time());
+            let later_merged = merged
+                .iter()
+                .map(|(_, e)| e)
+                .filter(|e| e.get_time() > event.get_time());
+            if later_known
+                .chain(later_merged)
+                .any(|e| e.happened_before(event))
+            {
+                bail!("Cannot merge events predating the events they causally follow")
+            }
+        }
+
+        // Order the events by time, keeping the order of the batch for equal timestamps
+        merged.sort_by(|a, b| a.1.get_time().cmp(b.1.get_time()));
+
+        // The versions and timestamps of the merged events, to extend the frontier
+        // after committing them
+        let versions: Vec<VersionVector> = merged
+            .iter()
+            .filter_map(|(_, e)| e.get_version().cloned())
+            .collect();
+        let timestamps: Vec<Timestamp> = merged.iter().map(|(_, e)| *e.get_time()).collect();
+
+        // Keep the latest event concerning each entity along with its current version
+        let mut reported: Vec<(Event<'a, T>, Option<Cow<'a, T>>)> = vec![];
+        for (_, event) in merged.iter().rev() {
+            if reported.iter().any(|(e, _)| **e == **event) {
+                continue;
+            }
+
+            let old = Self::find(self.get_projection(), event).cloned();
+            reported.push((event.clone(), old));
+        }
+        reported.reverse();
+
+        // The position of the earliest segment affected by the merge
+        // Unwraps safely because there's at least one event
+        let earliest_segment_pos = merged.iter().map(|(pos, _)| *pos).min().unwrap();
+
+        // Unwraps safely because there's always at least one segment
+        let latest_segment_pos = self.segments.len() - 1;
+        let latest_segment = Arc::make_mut(self.segments.last_mut().unwrap());
+        let latest_event_time = latest_segment
+            .get_events()
+            .last()
+            .map_or(*latest_segment.get_time(), |e| *e.get_time());
+
+        if earliest_segment_pos == latest_segment_pos
+            && merged[0].1.get_time() >= &latest_event_time
+        {
+            // Apply events following the latest one directly (both are atomic)
+            let mut events: Vec<_> = merged.into_iter().map(|(_, event)| event).collect();
+            if events.len() == 1 {
+                // Unwraps safely because there's exactly one event
+                latest_segment.push(events.pop().unwrap())?;
+            } else {
+                latest_segment.push_all(events)?;
+            }
+        } else {
+            let next_id = latest_segment.get_next_id();
+
+            // Work on a copy of the affected segments, so failures leave this projector unchanged
+            let mut segments = self.segments[earliest_segment_pos..].to_vec();
+
+            // Insert the events into their segments
+            for (segment_pos, event) in merged {
+                Arc::make_mut(&mut segments[segment_pos - earliest_segment_pos])
+                    .insert_unchecked(event);
+            }
+
+            // Project the affected segments again, keeping the identifiers of the entities
+            // (new ones follow all identifiers in use, to avoid reusing them)
+            for position in 0..segments.len() {
+                let previous = if position != 0 {
+                    Some(&segments[position - 1])
+                } else if earliest_segment_pos != 0 {
+                    Some(&self.segments[earliest_segment_pos - 1])
+                } else {
+                    None
+                };
+                let (snapshot, ids, projections) = match previous {
+                    Some(previous) => (
+                        previous.get_projection().clone(),
+                        previous.get_ids().clone(),
+                        previous.get_custom_projections().clone(),
+                    ),
+                    None => (vec![], vec![], self.projections.clone()),
+                };
+                let next_id = next_id.max(previous.map_or(0, |p| p.get_next_id()));
+
+                Arc::make_mut(&mut segments[position]).reproject_onto(
+                    snapshot,
+                    ids,
+                    next_id,
+                    projections,
+                )?;
+            }
+
+            // Commit the merge
+            self.segments.truncate(earliest_segment_pos);
+            self.segments.extend(segments);
+        }
+        for version in &versions {
+            self.extend_frontier(Some(version));
+        }
+        for timestamp in timestamps {
+            self.event_ids.add(timestamp);
+        }
+
+        // Compare the projections before and after the merge
+        let changes: Vec<Change<'a, T>> = reported
+            .into_iter()
+            .map(|(event, old)| {
+                let new = Self::find(self.get_projection(), &event).cloned();
+                Change::new(event, old, new)
+            })
+            .collect();
+
+        // Notify the subscribers
+        self.subscribers.notify(&changes);
+
+        // Resolve the resulting conflicts of the merged entities (if enabled)
+        let mut changes = changes;
+        if let Some((replica, resolver)) = self.resolver.clone() {
+            let resolutions = resolver(self, &replica, &changes)?;
+            changes.extend(resolutions);
+        }
+
+        Ok(changes)
+    }
+
+    /// Resolves conflicts using a given resolver on behalf of a replica whenever events are merged
+    pub(crate) fn set_resolver(&mut self, replica: &str, resolver: Resolver<'a, T>) {
+        self.resolver = Some((replica.to_owned(), resolver));
+    }
+
+    /// Inserts a single (possibly out-of-order) event into the history of this projector
+    ///
+    /// See [`merge`](#method.merge) for details.
+    pub fn insert(&mut self, event: Event<'a, T>) -> Result<Vec<Change<'a, T>>> {
+        self.merge(vec![event])
+    }
+
+    /// Delivers (possibly out-of-order) events from another replica, holding back
+    /// events whose causal predecessors haven't arrived yet
+    ///
+    /// An event is held back if its version shows unknown predecessors, or if it can't
+    /// be merged (e.g. an update arriving before the create of its entity). All pending
+    /// events are retried whenever events are delivered, so they're applied as soon as
+    /// the gaps are filled. Deliver no events to retry them after pushing locally.
+    ///
+    /// Returns the changes caused by all events applied, like [`merge`](#method.merge):
+    /// The events ready at the same time are merged at once, so their changes contain
+    /// an entry for every entity concerned, rather than for every event.
+    pub fn deliver(&mut self, events: Vec<Event<'a, T>>) -> Result<Vec<Change<'a, T>>> {
+        let received = Utc::now();
+        for event in events {
+            if !self.pending.iter().any(|p| *p.get_event() == event) {
+                self.pending.push(PendingEvent::new(event, received));
+            }
+        }
+
+        // Apply the pending events in order until no more of them are ready
+        self.pending
+            .sort_by(|a, b| a.get_event().get_time().cmp(b.get_event().get_time()));
+        let mut frontier = self.get_frontier();
+        let mut changes = vec![];
+        loop {
+            // Take the events whose predecessors are known, including the ones taken before
+            let mut known = frontier.clone();
+            let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+                .into_iter()
+                .partition(|pending| {
+                    let ready = pending.is_ready(&known);
+                    if let (true, Some(version)) = (ready, pending.get_event().get_version()) {
+                        known.merge(version);
+                    }
+                    ready
+                });
+            self.pending = waiting;
+
+            // Nothing to do
+            if ready.is_empty() {
+                break;
+            }
+
+            // Merge the ready events at once (resolving their conflicts once)
+            let events = ready.iter().map(|p| p.get_event().clone()).collect();
+            match self.merge(events) {
+                Ok(applied) => {
+                    // Keep the frontier up to date, including the resolutions (if any)
+                    frontier = known;
+                    for version in applied.iter().filter_map(|c| c.get_event().get_version()) {
+                        frontier.merge(version);
+                    }
+                    changes.extend(applied);
+                }
+                Err(_) => {
+                    // Merge them one by one instead, keeping events which can't be applied yet
+                    let mut progress = false;
+                    for pending in ready {
+                        match self.merge(vec![pending.get_event().clone()]) {
+                            Ok(applied) => {
+                                changes.extend(applied);
+                                progress = true;
+                            }
+                            Err(_) => self.pending.push(pending),
+                        }
+                    }
+                    self.pending
+                        .sort_by(|a, b| a.get_event().get_time().cmp(b.get_event().get_time()));
+
+                    if !progress {
+                        break;
+                    }
+                    frontier = self.get_frontier();
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Returns a reference to the events waiting for their causal predecessors
+    pub fn get_pending(&self) -> &Vec<PendingEvent<'a, T>> {
+        &self.pending
+    }
+
+    /// Removes and returns all pending events received before a given moment in time
+    ///
+    /// Use this to give up on events whose predecessors will likely never arrive.
+    pub fn expire_pending(&mut self, received_before: &Timestamp) -> Vec<Event<'a, T>> {
+        let (expired, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|p| p.get_received() < received_before);
+        self.pending = pending;
+
+        expired.into_iter().map(PendingEvent::take).collect()
+    }
+
+    /// Subscribes to the changes of the current projection
+    ///
+    /// The returned receiver gets a [`Change`] for every modification caused by
+    /// pushed events, committed transactions and merged events. Dropping the
+    /// receiver ends the subscription.
+    ///
+    /// [`Change`]: struct.Change.html
+    pub fn subscribe(&mut self) -> Receiver<Change<'a, T>> {
+        self.subscribers.subscribe()
+    }
+
+    /// Finds the entity concerned by an event in a projection
+    fn find<'b>(projection: &'b [Cow<'a, T>], entity: &T) -> Option<&'b Cow<'a, T>> {
+        projection.iter().find(|e| ***e == *entity)
+    }
+
+    /// Applies several events atomically, returning the id of the transaction
+    ///
+    /// The events are added to the transaction by the given closure. They all share
+    /// the same timestamp and transaction id, and are validated against a copy of the
+    /// current projection before being committed. If any of them fails, none of them
+    /// are applied and the projector is left unchanged.
+    pub fn transaction<F>(&mut self, f: F) -> Result<TransactionId>
+    where
+        F: FnOnce(&mut Transaction<'a, T>),
+    {
+        // Begin a new transaction
+        let mut transaction = self.begin_transaction(Utc::now());
+
+        // Let the caller add the events of the transaction
+        f(&mut transaction);
+        self.commit_transaction(transaction)
+    }
+
+    /// Begins a new, empty transaction at a given moment in time
+    pub(super) fn begin_transaction(&self, timestamp: Timestamp) -> Transaction<'a, T> {
+        Transaction::new(self.next_transaction, timestamp)
+    }
+
+    /// Validates the events of a transaction against a copy of the current projection
+    /// (without committing them)
+    pub(super) fn validate_transaction(&self, transaction: &Transaction<'a, T>) -> Result<()> {
         // Unwraps safely because there's always at least one segment
-        self.segments.last_mut().unwrap().push(event)
+        self.segments
+            .last()
+            .unwrap()
+            .validate(transaction.get_events())?;
+
+        Ok(())
+    }
+
+    /// Validates and commits the events of a transaction, returning its id
+    pub(super) fn commit_transaction(
+        &mut self,
+        transaction: Transaction<'a, T>,
+    ) -> Result<TransactionId> {
+        let id = transaction.get_id();
+        let events = transaction.take();
+
+        // Only keep a copy of the events (and the projection) if anyone is listening
+        let pending = if self.subscribers.is_empty() {
+            None
+        } else {
+            Some((events.clone(), self.get_projection().clone()))
+        };
+
+        // Validate and commit the events
+        // Unwraps safely because there's always at least one segment
+        let versions: Vec<VersionVector> = events
+            .iter()
+            .filter_map(|e| e.get_version().cloned())
+            .collect();
+        let timestamps: Vec<Timestamp> = events.iter().map(|e| *e.get_time()).collect();
+        Arc::make_mut(self.segments.last_mut().unwrap()).push_all(events)?;
+        for version in &versions {
+            self.extend_frontier(Some(version));
+        }
+        for timestamp in timestamps {
+            self.event_ids.add(timestamp);
+        }
+
+        // Only consume the id once the transaction was committed
+        // (transactions of a store may skip the ids of other collections)
+        self.next_transaction = self.next_transaction.max(id + 1);
+
+        // Notify the subscribers, replaying the events one by one
+        if let Some((events, mut projection)) = pending {
+            let mut changes = vec![];
+            for event in events {
+                let old = Self::find(&projection, &event).cloned();
+                Segment::apply_event_to(&mut projection, event.clone())?;
+                let new = Self::find(&projection, &event).cloned();
+                changes.push(Change::new(event, old, new));
+            }
+            self.subscribers.notify(&changes);
+        }
+
+        Ok(id)
     }
 
     /// Makes a new snapshot of the projector by creating a new segment
@@ -84,11 +929,11 @@ where
         // Unwraps safely because there's always at least one segment
         let latest_segment = self.segments.last().unwrap();
 
-        // Make a new segment with the previously-latest segments snapshot
-        let new_segment = Segment::from_projection(latest_segment.get_projection().clone(), vec![]);
+        // Make a new segment with the previously-latest segments snapshot (and indexes)
+        let new_segment = latest_segment.next();
 
         // Push the new segment onto the segments vector of this projector
-        self.segments.push(new_segment);
+        self.segments.push(Arc::new(new_segment));
     }
 
     /// Attempts to merge two segments/snapshots.  
@@ -115,53 +960,430 @@ where
         };
 
         // The segment containing the timestamp
-        self.segments
-            .get_mut(latest_segment_pos)
-            // Unwraps safely because the index was found previously
-            .unwrap()
-            // Perform the merge
-            .prepend(predating_segment)
+        Arc::make_mut(
+            self.segments
+                .get_mut(latest_segment_pos)
+                // Unwraps safely because the index was found previously
+                .unwrap(),
+        )
+        // Perform the merge
+        .prepend(Arc::unwrap_or_clone(predating_segment))
+    }
+
+    /// Returns the frontier of this projector, which is the least version
+    /// following all (versioned) events known to it
+    ///
+    /// The frontier is computed once (e.g. after deserializing the projector),
+    /// and extended whenever events are pushed, committed or merged.
+    pub fn get_frontier(&self) -> VersionVector {
+        self.frontier
+            .get_or_init(|| {
+                let mut frontier = VersionVector::new();
+                for version in self
+                    .segments
+                    .iter()
+                    .flat_map(|s| s.get_events())
+                    .filter_map(|e| e.get_version())
+                {
+                    frontier.merge(version);
+                }
+
+                frontier
+            })
+            .clone()
+    }
+
+    /// Extends the cached frontier (if it was computed already) by the version of a new event
+    fn extend_frontier(&mut self, version: Option<&VersionVector>) {
+        if let (Some(frontier), Some(version)) = (self.frontier.get_mut(), version) {
+            frontier.merge(version);
+        }
+    }
+
+    /// Stamps a new event of a replica with the next version of the replica,
+    /// so it causally follows all events known to this projector
+    ///
+    /// If the event doesn't follow the latest known event in time (e.g. as the clock
+    /// of the replica lags behind), its timestamp is moved right after it, so the
+    /// event is projected after all of its causal predecessors.
+    /// Pushing a stamped event concerning an entity resolves its conflicts.
+    pub fn stamp(&self, mut event: Event<'a, T>, replica: &str) -> Event<'a, T> {
+        let mut version = self.get_frontier();
+        version.increment(replica);
+
+        // Never precede a causal predecessor in time
+        if let Some(latest) = self
+            .segments
+            .iter()
+            .rev()
+            .find_map(|s| s.get_events().last())
+        {
+            if event.get_time() <= latest.get_time() {
+                event.set_time(*latest.get_time() + Duration::nanoseconds(1));
+            }
+        }
+
+        event.with_version(replica, version)
+    }
+
+    /// Forks the history of this projector at a given moment in time
+    ///
+    /// The fork contains all events up to (and including) the timestamp, and
+    /// continues with a new segment starting at the timestamp, so it can be edited
+    /// independently. The sealed segments predating the timestamp are shared with
+    /// this projector (using copy-on-write), so only the segment containing the
+    /// timestamp is copied.
+    ///
+    /// Use [`merge_branch`](#method.merge_branch) to merge the fork back.
+    pub fn fork_at(&self, timestamp: &Timestamp) -> Projector<'a, T> {
+        let mut segments = vec![];
+
+        // The number of segments shared with the fork
+        let mut shared = 0;
+
+        if let Some(segment_pos) = Self::get_latest_segment_pos(&self.segments, timestamp) {
+            // Share all segments before the one containing the timestamp
+            segments.extend(self.segments[..segment_pos].iter().cloned());
+            shared = segment_pos;
+
+            // Seal the events of the containing segment up to the timestamp, keeping the
+            // identifiers of the entities
+            // Unwraps safely because projecting the events again yields the same result
+            let sealed = self.segments[segment_pos]
+                .truncated_at(
+                    timestamp,
+                    segment_pos.checked_sub(1).map(|p| &*self.segments[p]),
+                )
+                .unwrap();
+
+            // Continue with a new segment
+            let next = sealed.next_at(*timestamp);
+            segments.push(Arc::new(sealed));
+            segments.push(Arc::new(next));
+        } else {
+            // The fork predates all events
+            segments.push(Arc::new(Segment::from_parts(*timestamp, vec![], vec![])));
+        }
+
+        // Unwraps safely because there's always at least one segment
+        let mut fork = Self::from_shared_segments(segments).unwrap();
+        fork.forked_at = Some(*timestamp);
+        fork.resolver = self.resolver.clone();
+
+        // Keep the custom projections (the shared segments contain them already)
+        for (name, projection) in &self.projections {
+            fork.add_any_projection_from(name, projection.clone(), shared);
+        }
+
+        // Keep the indexes (of the new segments)
+        // Unwraps safely because there's always at least one segment
+        let latest_segment = self.segments.last().unwrap();
+        for name in latest_segment.get_index_names() {
+            if let Some(key_function) = latest_segment.get_key_function(name) {
+                for segment in &mut fork.segments[shared..] {
+                    Arc::make_mut(segment).add_index(name, key_function);
+                }
+            }
+        }
+
+        fork
     }
 
-    /// Returns a reference to all segments held by this projector
-    pub fn get_segments(&self) -> &Vec<Segment<'a, T>> {
+    /// Returns the moment in time this projector was forked from another one (if it's a fork)
+    pub fn get_forked_at(&self) -> Option<&Timestamp> {
+        self.forked_at.as_ref()
+    }
+
+    /// Merges the events of a fork (see [`fork_at`](#method.fork_at)) made after
+    /// forking into this projector, returning the resulting changes and conflicts
+    ///
+    /// An entity is in conflict if both this projector and the fork modified it after
+    /// forking. Conflicting events are still merged (ordered by their timestamps),
+    /// and reported along with the events of this projector. If the events of both
+    /// lines can't be combined (e.g. one line deleted the entity while the other one
+    /// updated it), the events of the fork concerning the entity are skipped, keeping
+    /// the version of this projector. If any of the other events fails, none of them
+    /// are merged.
+    pub fn merge_branch(&mut self, branch: &Projector<'a, T>) -> Result<BranchMerge<'a, T>> {
+        let forked_at = branch
+            .get_forked_at()
+            .ok_or_else(|| anyhow!("Cannot merge a projector which isn't a fork"))?;
+
+        // The events of both lines after forking
+        let branch_events: Vec<Event<'a, T>> = branch
+            .get_events_from(forked_at)
+            .into_iter()
+            .filter(|e| e.get_time() > forked_at)
+            .cloned()
+            .collect();
+        let main_events: Vec<&Event<'a, T>> = self
+            .get_events_from(forked_at)
+            .into_iter()
+            .filter(|e| e.get_time() > forked_at)
+            .collect();
+
+        // Find the entities modified by both lines
+        let mut conflicts: Vec<Conflict<'a, T>> = vec![];
+        for event in &branch_events {
+            let entity: &T = event;
+            if conflicts.iter().any(|c| *c.get_events()[0] == *entity) {
+                continue;
+            }
+
+            let mut events: Vec<Event<'a, T>> = main_events
+                .iter()
+                .filter(|e| ****e == *entity)
+                .map(|e| Event::clone(e))
+                .collect();
+            if events.is_empty() {
+                continue;
+            }
+
+            events.extend(branch_events.iter().filter(|e| ***e == *entity).cloned());
+            events.sort_by(|a, b| a.get_time().cmp(b.get_time()));
+            conflicts.push(Conflict::new(events));
+        }
+
+        // Check if the events of both lines can be combined, starting at the fork point
+        let base = self.project_at(forked_at).unwrap_or_default();
+        let mut skipped: Vec<&T> = vec![];
+        for conflict in &conflicts {
+            let entity: &T = &conflict.get_events()[0];
+            let mut projection: Vec<Cow<'a, T>> =
+                Self::find(&base, entity).cloned().into_iter().collect();
+            for event in conflict.get_events() {
+                if Segment::apply_event_to(&mut projection, event.clone()).is_err() {
+                    skipped.push(entity);
+                    break;
+                }
+            }
+        }
+
+        // Replay the events of the branch (keeping our version of irreconcilable entities)
+        let branch_events = branch_events
+            .into_iter()
+            .filter(|e| !skipped.iter().any(|entity| **entity == **e))
+            .collect();
+        let changes = self.merge(branch_events)?;
+
+        Ok((changes, conflicts))
+    }
+
+    /// Returns a reference to all (shared) segments held by this projector
+    ///
+    /// Segments are shared with clones and forks of this projector since version 0.6
+    /// (returning `&Vec<Segment>` before). Use `Arc::unwrap_or_clone` to take one.
+    pub fn get_segments(&self) -> &Vec<Arc<Segment<'a, T>>> {
         &self.segments
     }
 
+    /// Replaces the segments of this projector with previously saved ones (e.g. to
+    /// roll back an operation), keeping its subscribers, indexes and custom projections
+    #[cfg(feature = "async")]
+    pub(super) fn restore_segments(&mut self, segments: Vec<Arc<Segment<'a, T>>>) {
+        self.segments = segments;
+        self.frontier = OnceLock::new();
+        self.event_ids = EventIds::default();
+    }
+
+    /// Holds back the changes of all following operations until they're released
+    /// (e.g. until they're persisted)
+    #[cfg(feature = "async")]
+    pub(super) fn hold_changes(&mut self) {
+        self.subscribers.hold();
+    }
+
+    /// Notifies the subscribers of the changes held back (or drops them, e.g. if
+    /// the operations were rolled back), and stops holding back changes
+    #[cfg(feature = "async")]
+    pub(super) fn release_changes(&mut self, notify: bool) {
+        self.subscribers.release(notify);
+    }
+
+    /// Checks if an event is part of the event log of this projector
+    pub fn contains(&self, event: &Event<'a, T>) -> bool {
+        self.segments.iter().any(|s| s.contains(event))
+    }
+
     /// Returns a vector containing references to all events in this projector's segments
-    /// after a given timestamp. The returned vector may be empty if no events occurred.
+    /// at or after a given timestamp. The returned vector may be empty if no events occurred.
     pub fn get_events_from(&self, starting_date: &Timestamp) -> Vec<&Event<'a, T>> {
-        let mut events = vec![];
+        Self::get_segment_events_from(&self.segments, starting_date)
+    }
+
+    /// Returns a vector containing references to all events in some segments
+    /// at or after a given timestamp. The returned vector may be empty if no events occurred.
+    pub(super) fn get_segment_events_from<'b, S>(
+        segments: &'b [S],
+        starting_date: &Timestamp,
+    ) -> Vec<&'b Event<'a, T>>
+    where
+        S: Borrow<Segment<'a, T>>,
+    {
+        Self::iter_segment_events_from(segments, starting_date).collect()
+    }
 
+    /// Iterates lazily over all events in some segments at or after a given timestamp
+    pub(super) fn iter_segment_events_from<'b, S>(
+        segments: &'b [S],
+        starting_date: &Timestamp,
+    ) -> impl Iterator<Item = &'b Event<'a, T>> + 'b
+    where
+        S: Borrow<Segment<'a, T>>,
+        'a: 'b,
+    {
         // Find the segment containing the timestamp (if available):
         // The position of the segment containing the requested timestamp
-        if let Some(latest_segment_pos) = self.get_latest_segment_pos(starting_date) {
-            // The segment containing the timestamp
-            // Unwraps safely because the index was found previously
-            let containing_segment = self.segments.get(latest_segment_pos).unwrap();
+        // (or the first one, if the timestamp predates all segments)
+        let latest_segment_pos = Self::get_latest_segment_pos(segments, starting_date).unwrap_or(0);
+        let starting_date = *starting_date;
 
-            if let Some(position) = containing_segment
-                .get_events()
-                .iter()
-                .rposition(|e| e.get_time() <= starting_date)
-            {
-                // Append the current events
-                containing_segment
-                    .get_events()
-                    .iter()
-                    .skip(position)
-                    .for_each(|event| events.push(event));
+        // Iterate over the events of the containing segment and the following ones (if any)
+        segments
+            .iter()
+            .skip(latest_segment_pos)
+            .flat_map(|s| s.borrow().get_events())
+            .skip_while(move |e| e.get_time() < &starting_date)
+    }
+}
+
+impl<'a, T> Projector<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    /// Finds all conflicts between concurrent events concerning the same entity
+    ///
+    /// The events of an entity are in conflict if more than one of them isn't
+    /// causally followed by another event concerning the entity, unless all of them
+    /// have identical content. Events without a version are ignored, as they're
+    /// ordered by their timestamps only.
+    pub fn get_conflicts(&self) -> Vec<Conflict<'a, T>> {
+        let events: Vec<&Event<'a, T>> = self
+            .segments
+            .iter()
+            .flat_map(|s| s.get_events())
+            .filter(|e| e.get_version().is_some())
+            .collect();
+
+        // Only entities concerned by events not following all events before them can
+        // be in conflict (so the common case of no concurrent events is linear)
+        let mut frontier = VersionVector::new();
+        let mut suspects: Vec<&T> = vec![];
+        for &event in &events {
+            // Unwraps safely because only events with a version are kept
+            let version = event.get_version().unwrap();
+            let entity: &T = event;
+            let follows_all = matches!(
+                frontier.partial_cmp(version),
+                Some(Ordering::Less) | Some(Ordering::Equal)
+            );
+            if !follows_all && !suspects.contains(&entity) {
+                suspects.push(entity);
+            }
+            frontier.merge(version);
+        }
+
+        Self::find_conflicts(&events, &suspects)
+    }
+
+    /// Finds the conflicts between concurrent events concerning some entities
+    /// (e.g. the ones concerned by merged events)
+    ///
+    /// See [`get_conflicts`](#method.get_conflicts) for details.
+    pub(crate) fn get_conflicts_of(&self, entities: &[&T]) -> Vec<Conflict<'a, T>> {
+        let events: Vec<&Event<'a, T>> = self
+            .segments
+            .iter()
+            .flat_map(|s| s.get_events())
+            .filter(|e| e.get_version().is_some() && entities.contains(&&***e))
+            .collect();
 
-                // Append the events from the following segments (if any)
-                self.segments
+        Self::find_conflicts(&events, entities)
+    }
+
+    /// Finds the conflicts concerning some entities among versioned events (in order)
+    fn find_conflicts(events: &[&Event<'a, T>], entities: &[&T]) -> Vec<Conflict<'a, T>> {
+        let mut conflicts = vec![];
+        for &entity in entities {
+            // The events concerning the entity which aren't followed by any other one
+            // As causally related events are ordered by time, only later events may follow
+            let mut heads: Vec<Event<'a, T>> = vec![];
+            for &event in events.iter().filter(|e| ****e == *entity) {
+                heads.retain(|head| !head.happened_before(event));
+                heads.push(Event::clone(event));
+            }
+
+            if heads.len() < 2 {
+                continue;
+            }
+
+            // Concurrent events with identical content (e.g. resolutions of the same
+            // conflict by several replicas) don't conflict
+            let encoded: Vec<_> = heads.iter().map(|e| format::to_vec(&**e).ok()).collect();
+            let identical = encoded[0].is_some() && encoded.iter().all(|e| *e == encoded[0]);
+
+            if !identical {
+                conflicts.push(Conflict::new(heads));
+            }
+        }
+
+        conflicts
+    }
+
+    /// Finds an event by its content identifier
+    ///
+    /// Returns `None` if no event of this projector has the identifier. All events
+    /// are hashed on the first lookup, later ones only hash the events added since.
+    pub fn get_event(&self, id: &ContentId) -> Result<Option<&Event<'a, T>>> {
+        let timestamp = {
+            // Unwraps safely because the lock is never held while panicking
+            let mut hashed = self.event_ids.0.lock().unwrap();
+
+            // Hash the events added since the last lookup (or all of them)
+            let events: Vec<&Event<'a, T>> = match hashed.timestamps {
+                Some(_) => hashed
+                    .added
                     .iter()
-                    .skip(latest_segment_pos + 1)
-                    .for_each(|s| s.get_events().iter().for_each(|e| events.push(e)));
+                    .flat_map(|timestamp| self.get_events_at(timestamp))
+                    .collect(),
+                None => self.segments.iter().flat_map(|s| s.get_events()).collect(),
+            };
+            let mut timestamps = hashed.timestamps.take().unwrap_or_default();
+            for event in events {
+                match event.id() {
+                    Ok(id) => timestamps.insert(id, *event.get_time()),
+                    Err(e) => {
+                        // Hash all events again on the next lookup
+                        *hashed = HashedEvents::default();
+                        return Err(e);
+                    }
+                };
+            }
+            hashed.added.clear();
+
+            let timestamp = timestamps.get(id).copied();
+            hashed.timestamps = Some(timestamps);
+            timestamp
+        };
+
+        // Compare the events sharing the timestamp of the event
+        if let Some(timestamp) = timestamp {
+            for event in self.get_events_at(&timestamp) {
+                if &event.id()? == id {
+                    return Ok(Some(event));
+                }
             }
         }
 
-        // Return the events
-        events
+        // Return Ok
+        Ok(None)
+    }
+
+    /// Returns a slice of the events occurring at a given moment in time
+    fn get_events_at(&self, timestamp: &Timestamp) -> &[Event<'a, T>] {
+        // Events predating all segments are part of the first one
+        let segment_pos = Self::get_latest_segment_pos(&self.segments, timestamp).unwrap_or(0);
+        self.segments[segment_pos].get_events_at(timestamp)
     }
 }
 
@@ -178,9 +1400,11 @@ impl<'a, T> Deref for Projector<'a, T>
 where
     T: Clone + PartialEq,
 {
-    type Target = Vec<Segment<'a, T>>;
+    type Target = Vec<Arc<Segment<'a, T>>>;
 
-    /// Returns a reference to all segments held by this projector
+    /// Returns a reference to all (shared) segments held by this projector
+    ///
+    /// See [`get_segments`](struct.Projector.html#method.get_segments) for details.
     fn deref(&self) -> &Self::Target {
         &self.segments
     }
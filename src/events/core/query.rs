@@ -0,0 +1,151 @@
+use crate::events::{Projector, Timestamp};
+use std::{borrow::Cow, cmp::Ordering};
+
+/// A predicate entities are filtered by
+type Filter<'q, T> = Box<dyn Fn(&T) -> bool + 'q>;
+
+/// A comparison entities are sorted by
+type Comparison<'q, T> = Box<dyn Fn(&T, &T) -> Ordering + 'q>;
+
+/**
+A query over the projection of a [`Projector`], created by [`Projector::query`].
+
+Queries are built by chaining filters, sorting, pagination and optionally a
+moment in time, and are run using [`execute`]:
+
+```ignore
+let page = books
+    .query()
+    .filter(|b| b.some_number > 10)
+    .sort_by(|a, b| a.some_number.cmp(&b.some_number))
+    .skip(20)
+    .take(10)
+    .at(&timestamp)
+    .execute();
+```
+
+Queries of the current projection only clone the entities they return, while
+queries of historical projections reuse segment snapshots (see
+[`Projector::project_at`]).
+
+[`Projector`]: struct.Projector.html
+[`Projector::query`]: struct.Projector.html#method.query
+[`Projector::project_at`]: struct.Projector.html#method.project_at
+[`execute`]: #method.execute
+*/
+pub struct Query<'q, 'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The queried projector
+    projector: &'q Projector<'a, T>,
+
+    /// The filters all returned entities match
+    filters: Vec<Filter<'q, T>>,
+
+    /// The comparisons the entities are sorted by (the first one taking precedence)
+    comparisons: Vec<Comparison<'q, T>>,
+
+    /// The number of entities to skip
+    skip: usize,
+
+    /// The maximum number of entities to return (if limited)
+    take: Option<usize>,
+
+    /// The moment in time to query (if not the current projection)
+    timestamp: Option<Timestamp>,
+}
+
+impl<'q, 'a, T> Query<'q, 'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Creates a new query returning all entities of the current projection
+    pub(super) fn new(projector: &'q Projector<'a, T>) -> Self {
+        Self {
+            projector,
+            filters: vec![],
+            comparisons: vec![],
+            skip: 0,
+            take: None,
+            timestamp: None,
+        }
+    }
+
+    /// Only returns entities matching a predicate (in addition to previous filters)
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&T) -> bool + 'q,
+    {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Sorts the entities using a comparison
+    ///
+    /// If called several times, later comparisons only sort entities
+    /// considered equal by the previous ones. Unsorted queries return
+    /// the entities in the order of the projection.
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: Fn(&T, &T) -> Ordering + 'q,
+    {
+        self.comparisons.push(Box::new(compare));
+        self
+    }
+
+    /// Skips a number of (filtered and sorted) entities
+    pub fn skip(mut self, count: usize) -> Self {
+        self.skip = count;
+        self
+    }
+
+    /// Returns at most a number of (filtered and sorted) entities
+    pub fn take(mut self, count: usize) -> Self {
+        self.take = Some(count);
+        self
+    }
+
+    /// Queries the projection at a moment in time instead of the current one
+    pub fn at(mut self, timestamp: &Timestamp) -> Self {
+        self.timestamp = Some(*timestamp);
+        self
+    }
+
+    /// Runs the query, returning the matching entities
+    ///
+    /// Returns `None` if the queried moment in time predates the first segment.
+    pub fn execute(self) -> Option<Vec<Cow<'a, T>>> {
+        let projection: Cow<[Cow<'a, T>]> = match &self.timestamp {
+            Some(timestamp) => Cow::Owned(self.projector.project_at(timestamp)?),
+            None => Cow::Borrowed(self.projector.get_projection().as_slice()),
+        };
+
+        // Filter the entities
+        let mut entities: Vec<&Cow<'a, T>> = projection
+            .iter()
+            .filter(|e| self.filters.iter().all(|filter| filter(e)))
+            .collect();
+
+        // Sort the entities (keeping the order of equal ones)
+        if !self.comparisons.is_empty() {
+            entities.sort_by(|a, b| {
+                self.comparisons
+                    .iter()
+                    .map(|compare| compare(a, b))
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        // Paginate the entities
+        Some(
+            entities
+                .into_iter()
+                .skip(self.skip)
+                .take(self.take.unwrap_or(usize::MAX))
+                .cloned()
+                .collect(),
+        )
+    }
+}
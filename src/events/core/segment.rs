@@ -1,8 +1,15 @@
-use crate::events::{Event, Timestamp};
+use super::projection::{AnyProjection, Projections};
+use crate::events::{
+    merge3, ContentId, EntityId, Event, Index, KeyFunction, ThreeWayMerge, Timestamp,
+};
 use anyhow::{anyhow, bail, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+};
 
 /**
 A segment is a part of an event log.
@@ -17,6 +24,7 @@ entire event log needs to be replayed in order to project
 an earlier state, unlike a single-segment event log.
 */
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "PersistedSegment<'a, T>")]
 pub struct Segment<'a, T>
 where
     T: Clone + PartialEq,
@@ -29,6 +37,31 @@ where
 
     /// The event log of this segment
     events: Vec<Event<'a, T>>,
+
+    /// The identifiers of the entities of the snapshot (in the same order)
+    ids: Vec<EntityId>,
+
+    /// The identifier of the next entity created
+    next_id: EntityId,
+
+    /// The secondary indexes of the snapshot by their names
+    ///
+    /// Indexes are persisted along with the snapshot, but aren't part of the content
+    /// identifier. They're only maintained while their key functions are known.
+    indexes: BTreeMap<String, Index>,
+
+    /// The names of the indexes which weren't maintained, as the snapshot changed
+    /// while their key functions were unknown
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    stale: BTreeSet<String>,
+
+    /// The functions deriving the keys of the indexes by their names
+    #[serde(skip, default = "BTreeMap::new")]
+    key_functions: BTreeMap<String, KeyFunction<T>>,
+
+    /// The custom projections after the events of this segment by their names
+    #[serde(skip, default = "BTreeMap::new")]
+    projections: Projections<T>,
 }
 
 impl<'a, T> Segment<'a, T>
@@ -47,11 +80,73 @@ where
     pub fn from_projection(
         projection: Vec<Cow<'a, T>>,
         events: Vec<Event<'a, T>>,
+    ) -> Segment<'a, T> {
+        Self::from_parts(Utc::now(), projection, events)
+    }
+
+    /// Creates a new (empty) segment at the current time, continuing with the
+    /// snapshot, indexes and custom projections of this one
+    pub fn next(&self) -> Segment<'a, T> {
+        self.next_at(Utc::now())
+    }
+
+    /// Creates a new (empty) segment at a given moment in time, continuing with
+    /// the snapshot, indexes and custom projections of this one
+    pub(super) fn next_at(&self, timestamp: Timestamp) -> Segment<'a, T> {
+        Self {
+            ids: self.ids.clone(),
+            next_id: self.next_id,
+            indexes: self.indexes.clone(),
+            stale: self.stale.clone(),
+            key_functions: self.key_functions.clone(),
+            projections: self.projections.clone(),
+            ..Self::from_parts(timestamp, self.snapshot.clone(), vec![])
+        }
+    }
+
+    /// Creates a copy of this segment containing only the events up to (and including)
+    /// a given moment in time, continuing with the snapshot of the previous one (if any)
+    ///
+    /// Entities keep their identifiers, unless they're deleted after the timestamp.
+    pub(super) fn truncated_at(
+        &self,
+        timestamp: &Timestamp,
+        previous: Option<&Self>,
+    ) -> Result<Segment<'a, T>> {
+        let mut segment = self.clone();
+        segment.events.retain(|e| e.get_time() <= timestamp);
+
+        match previous {
+            Some(previous) => segment.reproject_onto(
+                previous.snapshot.clone(),
+                previous.ids.clone(),
+                previous.next_id,
+                previous.projections.clone(),
+            )?,
+            None => segment.reproject_onto(vec![], vec![], 0, BTreeMap::new())?,
+        };
+
+        Ok(segment)
+    }
+
+    /// Assembles a segment from its parts (e.g. previously persisted ones)
+    ///
+    /// The entities of the snapshot are identified in order.
+    pub(super) fn from_parts(
+        timestamp: Timestamp,
+        snapshot: Vec<Cow<'a, T>>,
+        events: Vec<Event<'a, T>>,
     ) -> Segment<'a, T> {
         Self {
-            timestamp: Utc::now(),
-            snapshot: projection,
+            timestamp,
+            ids: (0..snapshot.len() as EntityId).collect(),
+            next_id: snapshot.len() as EntityId,
+            snapshot,
             events,
+            indexes: BTreeMap::new(),
+            stale: BTreeSet::new(),
+            key_functions: BTreeMap::new(),
+            projections: BTreeMap::new(),
         }
     }
 
@@ -65,6 +160,137 @@ where
         &self.snapshot
     }
 
+    /// Returns a reference to the identifiers of the entities of the snapshot (in the same order)
+    pub fn get_ids(&self) -> &Vec<EntityId> {
+        &self.ids
+    }
+
+    /// Replaces the identifiers of the entities of the snapshot (e.g. previously persisted ones)
+    #[cfg(feature = "git")]
+    pub(super) fn set_ids(&mut self, ids: Vec<EntityId>, next_id: EntityId) -> Result<()> {
+        self.check_ids(&ids, next_id)?;
+
+        self.ids = ids;
+        self.next_id = next_id;
+        self.rebuild_indexes();
+
+        Ok(())
+    }
+
+    /// Checks if identifiers can identify the entities of the snapshot
+    fn check_ids(&self, ids: &[EntityId], next_id: EntityId) -> Result<()> {
+        if ids.len() != self.snapshot.len() || ids.iter().any(|&id| id >= next_id) {
+            bail!("Cannot identify the entities of the snapshot using the identifiers")
+        }
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Returns the identifier of the next entity created
+    pub(super) fn get_next_id(&self) -> EntityId {
+        self.next_id
+    }
+
+    /// Returns a reference to an entity of the snapshot by its identifier (if it exists)
+    pub fn get_entity(&self, id: EntityId) -> Option<&Cow<'a, T>> {
+        // The identifiers are ascending as new entities are appended, unless
+        // entities were created by events merged out of order
+        let position = match self.ids.binary_search(&id) {
+            Ok(position) => position,
+            Err(_) => self.ids.iter().position(|&i| i == id)?,
+        };
+        self.snapshot.get(position)
+    }
+
+    /// Adds (or replaces) a secondary index of the snapshot, maintained whenever events are pushed
+    ///
+    /// A persisted index whose key function is unknown (i.e. after deserializing
+    /// the segment) is kept as it is, instead of rebuilding it, unless it's stale.
+    pub fn add_index(&mut self, name: &str, key_function: KeyFunction<T>) {
+        if !self.indexes.contains_key(name)
+            || self.key_functions.contains_key(name)
+            || self.stale.contains(name)
+        {
+            self.indexes.insert(
+                name.to_owned(),
+                Index::build(&self.snapshot, &self.ids, key_function),
+            );
+        }
+        self.stale.remove(name);
+        self.key_functions.insert(name.to_owned(), key_function);
+    }
+
+    /// Returns a reference to a secondary index of the snapshot (if it exists)
+    ///
+    /// The returned index may be stale (see [`is_stale`](#method.is_stale)).
+    pub fn get_index(&self, name: &str) -> Option<&Index> {
+        self.indexes.get(name)
+    }
+
+    /// Checks if a secondary index is stale, as the snapshot changed while its key
+    /// function was unknown (i.e. after deserializing the segment)
+    ///
+    /// Stale indexes are kept, but can't be looked up until they're added again.
+    pub fn is_stale(&self, name: &str) -> bool {
+        self.stale.contains(name)
+    }
+
+    /// Returns the names of all secondary indexes of the snapshot
+    pub fn get_index_names(&self) -> Vec<&str> {
+        self.indexes.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the function deriving the keys of a secondary index (if it's known)
+    ///
+    /// Key functions aren't persisted, so they're unknown after deserializing a segment
+    /// until the index is added again.
+    pub fn get_key_function(&self, name: &str) -> Option<KeyFunction<T>> {
+        self.key_functions.get(name).copied()
+    }
+
+    /// Returns a reference to the state of a custom projection after the events of this segment
+    pub(super) fn get_custom_projection(&self, name: &str) -> Option<&dyn AnyProjection<T>> {
+        self.projections.get(name).map(|p| p.as_ref())
+    }
+
+    /// Returns a reference to the states of all custom projections after the events of this segment
+    pub(super) fn get_custom_projections(&self) -> &Projections<T> {
+        &self.projections
+    }
+
+    /// Replaces the state of a custom projection with the projection of all events onto a given state
+    pub(super) fn reproject_custom_onto(
+        &mut self,
+        name: &str,
+        mut projection: Box<dyn AnyProjection<T>>,
+    ) {
+        for event in &self.events {
+            projection.apply(event);
+        }
+
+        self.projections.insert(name.to_owned(), projection);
+    }
+
+    /// Returns references to the entities of the snapshot with a key in a secondary index
+    pub fn lookup(&self, name: &str, key: &str) -> Result<Vec<&Cow<'a, T>>> {
+        let index = self
+            .get_index(name)
+            .ok_or_else(|| anyhow!("Unknown index {:?}", name))?;
+        if self.is_stale(name) {
+            bail!(
+                "Index {:?} is stale, as its key function is unknown; add it again",
+                name
+            )
+        }
+
+        Ok(index
+            .get(key)
+            .iter()
+            .filter_map(|&id| self.get_entity(id))
+            .collect())
+    }
+
     /// Projects the segments events predating a specified timestamp onto a given snapshot
     pub fn project_at_onto(
         &self,
@@ -97,9 +323,63 @@ where
 
     /// Applies and appends an event to the segments snapshot and log, respectively (checked)
     pub fn push(&mut self, event: Event<'a, T>) -> Result<()> {
-        // Get the time of the new event
-        let new_event_time = event.get_time();
+        // Check if the new event may be appended to this segment
+        self.check_time(event.get_time())?;
+
+        // Perform the push using push_unchecked
+        self.push_unchecked(event)?;
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Validates several events against a copy of the segments snapshot (without
+    /// applying them), returning the resulting snapshot
+    pub(super) fn validate(&self, events: &[Event<'a, T>]) -> Result<Vec<Cow<'a, T>>> {
+        // The projection to validate the events against
+        let mut snapshot = self.snapshot.clone();
+
+        // The time of the latest event validated so far
+        let mut last_event_time = None;
+
+        for event in events {
+            // Check if the new event may be appended to this segment
+            self.check_time(event.get_time())?;
+
+            // Check if the events of the batch are in order
+            if let Some(last_event_time) = last_event_time {
+                if event.get_time() < last_event_time {
+                    bail!("Cannot accept events predating the lastest logged event")
+                }
+            }
+            last_event_time = Some(event.get_time());
+
+            // Apply the event to the scratch projection
+            Self::apply_event_to(&mut snapshot, event.clone())?;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Applies and appends several events to the segments snapshot and log atomically (checked)
+    ///
+    /// The events are validated against a copy of the snapshot first.
+    /// If any of them fails, none of them are applied.
+    pub fn push_all(&mut self, events: Vec<Event<'a, T>>) -> Result<()> {
+        // Validate the events against a copy of the snapshot
+        self.validate(&events)?;
+
+        // Commit the validated events (which can't fail anymore)
+        for event in events {
+            self.push_unchecked(event)?;
+        }
+
+        // Return Ok
+        Ok(())
+    }
 
+    /// Checks if an event occurring at a given time may be appended to this segment
+    fn check_time(&self, new_event_time: &Timestamp) -> Result<()> {
         // Check if the new event predates the segments timestamp
         if new_event_time < &self.timestamp {
             bail!("Cannot accept events before the segment started")
@@ -111,9 +391,6 @@ where
             }
         }
 
-        // Perform the push using push_unchecked
-        self.push_unchecked(event)?;
-
         // Return Ok
         Ok(())
     }
@@ -129,13 +406,105 @@ where
         Ok(())
     }
 
-    /// Modifies the segments snapshot to reflect the changes of the event
+    /// Modifies the segments snapshot, indexes and custom projections to reflect the changes of the event
     fn apply_event(&mut self, event: Event<'a, T>) -> Result<()> {
-        Self::apply_event_to(&mut self.snapshot, event)
+        // Only keep a copy of the event if there are any custom projections
+        let applied = if self.projections.is_empty() {
+            None
+        } else {
+            Some(event.clone())
+        };
+
+        self.apply_event_to_snapshot(event)?;
+
+        // Update the custom projections
+        if let Some(event) = applied {
+            for projection in self.projections.values_mut() {
+                projection.apply(&event);
+            }
+        }
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Modifies the segments snapshot (and its indexes) to reflect the changes of the event
+    fn apply_event_to_snapshot(&mut self, event: Event<'a, T>) -> Result<()> {
+        // The position and keys of the entity before the event (if it existed)
+        let position = self.snapshot.iter().position(|e| **e == *event);
+        let old_keys: Vec<Option<String>> = self
+            .key_functions
+            .values()
+            .map(|f| position.map(|p| f(&self.snapshot[p])))
+            .collect();
+        let old_len = self.snapshot.len();
+
+        Self::apply_event_to(&mut self.snapshot, event)?;
+
+        // Keep the identifiers in line with the snapshot
+        let id = match position {
+            // Updated
+            Some(position) if self.snapshot.len() == old_len => self.ids[position],
+            // Deleted
+            Some(position) => self.ids.remove(position),
+            // Created (appended to the snapshot)
+            None => {
+                self.ids.push(self.next_id);
+                self.next_id += 1;
+                self.next_id - 1
+            }
+        };
+
+        // Persisted indexes can't be maintained without their key functions
+        self.mark_stale();
+
+        // Update the indexes
+        for ((name, f), old_key) in self.key_functions.iter().zip(old_keys) {
+            let index = self.indexes.entry(name.clone()).or_default();
+
+            match (position, old_key) {
+                // Updated
+                (Some(position), Some(old_key)) if self.snapshot.len() == old_len => {
+                    let new_key = f(&self.snapshot[position]);
+                    if new_key != old_key {
+                        index.remove(&old_key, id);
+                        index.insert(new_key, id);
+                    }
+                }
+                // Deleted
+                (Some(_), Some(old_key)) => index.remove(&old_key, id),
+                // Created (appended to the snapshot)
+                _ => index.insert(f(&self.snapshot[old_len]), id),
+            }
+        }
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Marks the indexes whose key functions are unknown as stale
+    fn mark_stale(&mut self) {
+        for name in self.indexes.keys() {
+            if !self.key_functions.contains_key(name) {
+                self.stale.insert(name.clone());
+            }
+        }
+    }
+
+    /// Rebuilds all indexes whose key functions are known (marking the other ones as stale)
+    fn rebuild_indexes(&mut self) {
+        self.mark_stale();
+        for (name, &f) in &self.key_functions {
+            self.indexes
+                .insert(name.clone(), Index::build(&self.snapshot, &self.ids, f));
+        }
     }
 
     /// Modifies a given snapshot to reflect the changes of the event
-    fn apply_event_to(snapshot: &mut Vec<Cow<'a, T>>, event: Event<'a, T>) -> Result<()> {
+    pub(super) fn apply_event_to(
+        snapshot: &mut Vec<Cow<'a, T>>,
+        event: Event<'a, T>,
+    ) -> Result<()> {
         // The pre-existing element
         let prev_position = snapshot.iter_mut().position(|e| **e == *event);
 
@@ -177,6 +546,83 @@ where
         }
     }
 
+    /// Inserts an event into the segments log according to its timestamp (unchecked)
+    ///
+    /// Events sharing the timestamp of the new one are kept before it.
+    /// Events predating the segment move its timestamp back.
+    /// The snapshot isn't updated; use `reproject_onto` afterwards.
+    pub(super) fn insert_unchecked(&mut self, event: Event<'a, T>) {
+        if event.get_time() < &self.timestamp {
+            self.timestamp = *event.get_time();
+        }
+
+        let position = self
+            .events
+            .iter()
+            .rposition(|e| e.get_time() <= event.get_time())
+            .map_or(0, |position| position + 1);
+
+        self.events.insert(position, event);
+    }
+
+    /// Replaces the segments snapshot (and custom projections) with the projection
+    /// of its entire log onto a given snapshot (and custom projections)
+    ///
+    /// The entities of the given snapshot keep the given identifiers, while the
+    /// entities created by the events keep the ones they had before (if any).
+    /// Other entities are identified starting at a given identifier (or the next
+    /// one of this segment, if it's greater).
+    pub(super) fn reproject_onto(
+        &mut self,
+        mut snapshot: Vec<Cow<'a, T>>,
+        mut ids: Vec<EntityId>,
+        next_id: EntityId,
+        projections: Projections<T>,
+    ) -> Result<()> {
+        let mut next_id = next_id.max(self.next_id);
+
+        // Project all events of this segment
+        for event in &self.events {
+            let position = snapshot.iter().position(|e| **e == **event);
+            let old_len = snapshot.len();
+
+            Self::apply_event_to(&mut snapshot, event.clone())?;
+
+            // Keep the identifiers in line with the snapshot
+            match position {
+                // Updated
+                Some(_) if snapshot.len() == old_len => {}
+                // Deleted
+                Some(position) => {
+                    ids.remove(position);
+                }
+                // Created (appended to the snapshot), keeping its previous identifier
+                None => {
+                    let previous = self.snapshot.iter().position(|e| **e == **event);
+                    ids.push(match previous {
+                        Some(previous) => self.ids[previous],
+                        None => {
+                            next_id += 1;
+                            next_id - 1
+                        }
+                    });
+                }
+            }
+        }
+
+        // Replace the snapshot
+        self.snapshot = snapshot;
+        self.ids = ids;
+        self.next_id = next_id;
+        self.rebuild_indexes();
+        for (name, projection) in projections {
+            self.reproject_custom_onto(&name, projection);
+        }
+
+        // Return Ok
+        Ok(())
+    }
+
     /// Merges two consecutive segments by prepending the other before this one (checked)
     pub fn prepend(&mut self, other: Self) -> Result<()> {
         // Avoid a panic in append()
@@ -215,9 +661,49 @@ where
         self.timestamp = other.timestamp;
     }
 
-    pub(super) fn get_events(&self) -> &Vec<Event<'a, T>> {
+    /// Returns a reference to the event log of this segment
+    pub fn get_events(&self) -> &Vec<Event<'a, T>> {
         &self.events
     }
+
+    /// Checks if the event log of this segment contains an event
+    ///
+    /// Only the events sharing its timestamp are compared, as the log is ordered by time.
+    pub(super) fn contains(&self, event: &Event<'a, T>) -> bool {
+        self.get_events_at(event.get_time()).contains(event)
+    }
+
+    /// Returns a slice of the events of this segment occurring at a given moment in time
+    pub(super) fn get_events_at(&self, timestamp: &Timestamp) -> &[Event<'a, T>] {
+        let start = self.events.partition_point(|e| e.get_time() < timestamp);
+        let end = self.events.partition_point(|e| e.get_time() <= timestamp);
+        &self.events[start..end]
+    }
+}
+
+impl<'a, T> Segment<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    /// Computes the content identifier of this segment (its timestamp, snapshot and events)
+    ///
+    /// The identifier of the latest segment of a projector changes whenever an event is
+    /// pushed, so it's only meaningful for sealed segments. The identifiers of the
+    /// entities and the indexes aren't part of it.
+    pub fn id(&self) -> Result<ContentId> {
+        ContentId::of(&(&self.timestamp, &self.snapshot, &self.events))
+    }
+
+    /// Merges two projections which diverged from the snapshot of this segment
+    ///
+    /// See [`merge3`](fn.merge3.html) for details.
+    pub fn merge3(
+        &self,
+        ours: &[Cow<'a, T>],
+        theirs: &[Cow<'a, T>],
+    ) -> Result<ThreeWayMerge<'a, T>> {
+        merge3(&self.snapshot, ours, theirs)
+    }
 }
 
 impl<'a, T> Default for Segment<'a, T>
@@ -228,3 +714,48 @@ where
         Self::new()
     }
 }
+
+/// The persisted fields of a [`Segment`], which lacked the identifiers of the
+/// entities and the indexes before version 0.6
+///
+/// [`Segment`]: struct.Segment.html
+#[derive(Deserialize)]
+struct PersistedSegment<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    timestamp: Timestamp,
+    snapshot: Vec<Cow<'a, T>>,
+    events: Vec<Event<'a, T>>,
+    #[serde(default)]
+    ids: Option<Vec<EntityId>>,
+    #[serde(default)]
+    next_id: Option<EntityId>,
+    #[serde(default = "BTreeMap::new")]
+    indexes: BTreeMap<String, Index>,
+    #[serde(default = "BTreeSet::new")]
+    stale: BTreeSet<String>,
+}
+
+impl<'a, T> TryFrom<PersistedSegment<'a, T>> for Segment<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(persisted: PersistedSegment<'a, T>) -> Result<Self> {
+        let mut segment =
+            Self::from_parts(persisted.timestamp, persisted.snapshot, persisted.events);
+
+        // Segments persisted before version 0.6 identify their entities in order
+        if let (Some(ids), Some(next_id)) = (persisted.ids, persisted.next_id) {
+            segment.check_ids(&ids, next_id)?;
+            segment.ids = ids;
+            segment.next_id = next_id;
+        }
+        segment.indexes = persisted.indexes;
+        segment.stale = persisted.stale;
+
+        Ok(segment)
+    }
+}
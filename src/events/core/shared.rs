@@ -0,0 +1,186 @@
+use crate::events::{Event, Projector, Segment, Timestamp};
+use anyhow::Result;
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex, RwLock},
+};
+
+/// The segments of a shared projector at some point in time
+type Segments<T> = Arc<Vec<Arc<Segment<'static, T>>>>;
+
+/**
+A thread-safe projector allowing concurrent readers.
+
+Readers get an immutable [`ProjectorView`] of the segments at the time of
+the request, which stays consistent while new events are pushed.
+
+Segments are shared using copy-on-write: Sealed segments are shared between
+all views and threads for free. If no view uses the current segments, pushing
+an event modifies the latest segment in place, blocking readers requesting a
+new view meanwhile. Otherwise, only the latest segment is copied, without
+blocking readers for longer than it takes to swap a pointer.
+
+[`ProjectorView`]: struct.ProjectorView.html
+*/
+#[derive(Debug)]
+pub struct SharedProjector<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// The latest version of the segments
+    current: RwLock<Segments<T>>,
+
+    /// Serializes the writers
+    writer: Mutex<()>,
+}
+
+/**
+An immutable view of the segments of a [`SharedProjector`].
+
+[`SharedProjector`]: struct.SharedProjector.html
+*/
+#[derive(Debug)]
+pub struct ProjectorView<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    segments: Segments<T>,
+}
+
+impl<T> SharedProjector<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// Generates a new shared projector for a given type
+    pub fn new() -> SharedProjector<T> {
+        Self::from(Projector::new())
+    }
+
+    /// Returns an immutable view of the current segments
+    pub fn view(&self) -> ProjectorView<T> {
+        ProjectorView {
+            // Unwraps safely because the lock is never held while panicking
+            segments: self.current.read().unwrap().clone(),
+        }
+    }
+
+    /// Pushes an event onto the latest segment, updating the projection
+    pub fn push(&self, event: Event<'static, T>) -> Result<()> {
+        self.write(|segments| {
+            // Copy the latest segment if it's used by any view (atomic)
+            // Unwraps safely because there's always at least one segment
+            Arc::make_mut(segments.last_mut().unwrap()).push(event)
+        })
+    }
+
+    /// Makes a new snapshot of the projector by creating a new segment
+    ///
+    /// The previously latest segment is sealed and shared from now on.
+    pub fn make_snapshot(&self) {
+        // Never fails, as creating a segment is infallible
+        let _ = self.write(|segments| {
+            // Unwraps safely because there's always at least one segment
+            let segment = segments.last().unwrap().next();
+            segments.push(Arc::new(segment));
+            Ok(())
+        });
+    }
+
+    /// Modifies the current segments, or a copy of them if they're used by any view
+    ///
+    /// The modification must leave the segments unchanged if it fails.
+    fn write<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<Arc<Segment<'static, T>>>) -> Result<()>,
+    {
+        // Only one writer at a time
+        // Unwraps safely because the lock is never held while panicking
+        let _writer = self.writer.lock().unwrap();
+
+        // Modify the segments in place if no view uses them
+        // Unwraps safely because the lock is never held while panicking
+        let mut current = self.current.write().unwrap();
+        if let Some(segments) = Arc::get_mut(&mut current) {
+            return f(segments);
+        }
+        drop(current);
+
+        // Copy the list of segments (not the segments themselves)
+        // The latest segment is used by a view then, so it must be copied anyway
+        let mut segments = Vec::clone(&self.view().segments);
+
+        // Perform the modification
+        f(&mut segments)?;
+
+        // Publish the new version
+        // Unwraps safely because the lock is never held while panicking
+        *self.current.write().unwrap() = Arc::new(segments);
+
+        Ok(())
+    }
+}
+
+impl<T> Default for SharedProjector<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Projector<'static, T>> for SharedProjector<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    fn from(projector: Projector<'static, T>) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(projector.get_segments().clone())),
+            writer: Mutex::new(()),
+        }
+    }
+}
+
+impl<T> ProjectorView<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// Returns the projection at the time of the view as a shared reference
+    pub fn get_projection(&self) -> &Vec<Cow<'static, T>> {
+        // Unwraps safely because there's always at least one segment
+        self.segments.last().unwrap().get_projection()
+    }
+
+    /// Performs a projection using a copy of the previous segments' snapshot if available
+    pub fn project_at(&self, timestamp: &Timestamp) -> Option<Vec<Cow<'static, T>>> {
+        Projector::project_segments_at(&self.segments, timestamp)
+    }
+
+    /// Returns a vector containing references to all events in the viewed segments
+    /// at or after a given timestamp. The returned vector may be empty if no events occurred.
+    pub fn get_events_from(&self, starting_date: &Timestamp) -> Vec<&Event<'static, T>> {
+        Projector::get_segment_events_from(&self.segments, starting_date)
+    }
+
+    /// Returns a reference to all (shared) segments of the view
+    pub fn get_segments(&self) -> &Vec<Arc<Segment<'static, T>>> {
+        &self.segments
+    }
+
+    /// Creates a new (independent) projector sharing the viewed segments (using copy-on-write)
+    pub fn to_projector(&self) -> Projector<'static, T> {
+        // Unwraps safely because there's always at least one segment
+        Projector::from_shared_segments(self.segments.to_vec()).unwrap()
+    }
+}
+
+impl<T> Clone for ProjectorView<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            segments: self.segments.clone(),
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use crate::events::{
+    Change, Event, KeyFunction, Projection, Projector, Segment, Timestamp, Transaction,
+    TransactionId,
+};
+use anyhow::{anyhow, Result};
+use futures::{
+    future::BoxFuture,
+    stream::{self, Stream},
+    FutureExt,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::ErrorKind,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{mpsc::Receiver, Arc},
+};
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::Mutex,
+};
+
+/**
+An asynchronous storage for the segments of a projector.
+
+Implementations persist the segments of an [`AsyncProjector`] without
+blocking the executor. The events an operation appends to the latest segment
+are appended as a batch (all or none of them), while operations rewriting the
+history (such as snapshots and out-of-order merges) replace all persisted segments.
+
+[`AsyncProjector`]: struct.AsyncProjector.html
+*/
+pub trait AsyncStorage<T>: Send + Sync
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+{
+    /// Loads all persisted segments (the returned vector is empty for a new storage)
+    fn load(&self) -> BoxFuture<'_, Result<Vec<Segment<'static, T>>>>;
+
+    /// Persists an event appended to the latest segment
+    fn append<'b>(&'b self, event: &'b Event<'static, T>) -> BoxFuture<'b, Result<()>> {
+        self.append_all(std::slice::from_ref(event))
+    }
+
+    /// Persists several events appended to the latest segment atomically
+    ///
+    /// If persisting fails (or is interrupted), none of the events may be loaded again.
+    fn append_all<'b>(&'b self, events: &'b [Event<'static, T>]) -> BoxFuture<'b, Result<()>>;
+
+    /// Persists all segments, replacing the previously persisted ones
+    fn store<'b>(&'b self, segments: &'b [Arc<Segment<'static, T>>]) -> BoxFuture<'b, Result<()>>;
+}
+
+/**
+A projector persisting its segments using an [`AsyncStorage`].
+
+It offers the mutating operations of a [`Projector`] as futures, and
+dereferences to the underlying projector for all read-only operations.
+
+[`AsyncStorage`]: trait.AsyncStorage.html
+[`Projector`]: struct.Projector.html
+*/
+pub struct AsyncProjector<T, S>
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+    S: AsyncStorage<T>,
+{
+    /// The in-memory projector
+    projector: Projector<'static, T>,
+
+    /// The storage persisting the segments of the projector
+    storage: S,
+}
+
+impl<T, S> AsyncProjector<T, S>
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+    S: AsyncStorage<T>,
+{
+    /// Loads a projector from a storage, initializing the storage if it's empty
+    pub async fn load(storage: S) -> Result<Self> {
+        let segments = storage.load().await?;
+
+        let projector = if segments.is_empty() {
+            // Persist the initial segment of a new projector
+            let projector = Projector::new();
+            storage.store(projector.get_segments()).await?;
+            projector
+        } else {
+            Projector::from_segments(segments)?
+        };
+
+        Ok(Self { projector, storage })
+    }
+
+    /// Pushes an event onto the latest segment and persists it
+    ///
+    /// If persisting fails, the event is rolled back. The subscribers are only
+    /// notified once the event was persisted.
+    pub async fn push(&mut self, event: Event<'static, T>) -> Result<()> {
+        let saved = self.save();
+        if let Err(error) = self.projector.push(event) {
+            return self.recover(Err(error), saved);
+        }
+
+        // Unwraps safely because there's always at least one segment and the event was just pushed
+        let event = self.projector.last().unwrap().get_events().last().unwrap();
+        let result = self.storage.append(event).await;
+        self.recover(result, saved)
+    }
+
+    /// Applies several events atomically and persists them,
+    /// returning the id of the transaction
+    ///
+    /// If persisting fails, the events of the transaction are rolled back.
+    /// The subscribers are only notified once the events were persisted.
+    pub async fn transaction<F>(&mut self, f: F) -> Result<TransactionId>
+    where
+        F: FnOnce(&mut Transaction<'static, T>),
+    {
+        let saved = self.save();
+
+        // Unwraps safely because there's always at least one segment
+        let known_events = self.projector.last().unwrap().get_events().len();
+        let id = match self.projector.transaction(f) {
+            Ok(id) => id,
+            Err(error) => return self.recover(Err(error), saved),
+        };
+
+        // Persist the events of the transaction
+        let events = &self.projector.last().unwrap().get_events()[known_events..];
+        let result = self.storage.append_all(events).await;
+        self.recover(result.map(|_| id), saved)
+    }
+
+    /// Merges (possibly out-of-order) events and persists them
+    ///
+    /// Events following the latest one are appended, while merging earlier
+    /// events persists all segments again. If persisting fails, the merged
+    /// events are rolled back. The subscribers are only notified once the
+    /// events were persisted.
+    pub async fn merge(
+        &mut self,
+        events: Vec<Event<'static, T>>,
+    ) -> Result<Vec<Change<'static, T>>> {
+        let saved = self.save();
+        let changes = match self.projector.merge(events) {
+            Ok(changes) => changes,
+            Err(error) => return self.recover(Err(error), saved),
+        };
+
+        let result = match Self::get_appended(&saved, self.projector.get_segments()) {
+            Some(events) => self.storage.append_all(events).await,
+            None => self.storage.store(self.projector.get_segments()).await,
+        };
+        self.recover(result.map(|_| changes), saved)
+    }
+
+    /// Makes a new snapshot of the projector and persists it
+    ///
+    /// If persisting fails, the snapshot is rolled back.
+    pub async fn make_snapshot(&mut self) -> Result<()> {
+        let saved = self.save();
+        self.projector.make_snapshot();
+
+        let result = self.storage.store(self.projector.get_segments()).await;
+        self.recover(result, saved)
+    }
+
+    /// Subscribes to the changes of the current projection
+    ///
+    /// See [`Projector::subscribe`](struct.Projector.html#method.subscribe) for details.
+    pub fn subscribe(&mut self) -> Receiver<Change<'static, T>> {
+        self.projector.subscribe()
+    }
+
+    /// Adds (or replaces) a secondary index of the projector
+    ///
+    /// See [`Projector::add_index`](struct.Projector.html#method.add_index) for details.
+    pub fn add_index(&mut self, name: &str, key_function: KeyFunction<T>) {
+        self.projector.add_index(name, key_function);
+    }
+
+    /// Adds (or replaces) a custom projection of the projector
+    ///
+    /// See [`Projector::add_projection`](struct.Projector.html#method.add_projection) for details.
+    pub fn add_projection<P: Projection<T>>(&mut self, name: &str, initial: P) {
+        self.projector.add_projection(name, initial);
+    }
+
+    /// Returns a stream of all events at or after a given timestamp
+    ///
+    /// The events are read lazily from the in-memory segments.
+    pub fn stream_events_from(
+        &self,
+        starting_date: &Timestamp,
+    ) -> impl Stream<Item = &Event<'static, T>> + '_ {
+        stream::iter(Projector::iter_segment_events_from(
+            self.projector.get_segments(),
+            starting_date,
+        ))
+    }
+
+    /// Returns a reference to the storage of this projector
+    pub fn get_storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Consumes the projector, returning the in-memory projector and the storage
+    pub fn take(self) -> (Projector<'static, T>, S) {
+        (self.projector, self.storage)
+    }
+
+    /// Returns the events appended to the latest segment by an operation, unless
+    /// it changed anything else (e.g. merged events out of order)
+    fn get_appended<'b>(
+        saved: &[Arc<Segment<'static, T>>],
+        segments: &'b [Arc<Segment<'static, T>>],
+    ) -> Option<&'b [Event<'static, T>]> {
+        let (saved_latest, saved_sealed) = saved.split_last()?;
+        let (latest, sealed) = segments.split_last()?;
+
+        // The sealed segments are shared with the saved ones unless they changed
+        let unchanged = sealed.len() == saved_sealed.len()
+            && sealed
+                .iter()
+                .zip(saved_sealed)
+                .all(|(s, t)| Arc::ptr_eq(s, t))
+            && latest.get_time() == saved_latest.get_time();
+        let known = saved_latest.get_events();
+        let events = latest.get_events();
+        if !unchanged || events.get(..known.len())? != &known[..] {
+            return None;
+        }
+
+        Some(&events[known.len()..])
+    }
+
+    /// Saves the segments before an operation, holding back its changes until
+    /// it's persisted (see [`recover`](#method.recover))
+    ///
+    /// The saved segments are shared with the projector, so saving them is cheap.
+    fn save(&mut self) -> Vec<Arc<Segment<'static, T>>> {
+        self.projector.hold_changes();
+        self.projector.get_segments().clone()
+    }
+
+    /// Restores the segments saved before an operation if it (or persisting it)
+    /// failed, and notifies the subscribers of its changes otherwise
+    ///
+    /// Only the segments are restored, keeping the subscribers, indexes, custom
+    /// projections and the conflict resolver of the projector.
+    fn recover<R>(&mut self, result: Result<R>, saved: Vec<Arc<Segment<'static, T>>>) -> Result<R> {
+        if result.is_err() {
+            self.projector.restore_segments(saved);
+        }
+        self.projector.release_changes(result.is_ok());
+
+        result
+    }
+}
+
+impl<T, S> Deref for AsyncProjector<T, S>
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+    S: AsyncStorage<T>,
+{
+    type Target = Projector<'static, T>;
+
+    /// Returns a reference to the in-memory projector
+    fn deref(&self) -> &Self::Target {
+        &self.projector
+    }
+}
+
+/// An in-memory [`AsyncStorage`], mostly useful for testing
+///
+/// [`AsyncStorage`]: trait.AsyncStorage.html
+#[derive(Debug, Default)]
+pub struct MemoryStorage<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    segments: Mutex<Vec<Segment<'static, T>>>,
+}
+
+impl<T> MemoryStorage<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// Creates a new, empty storage
+    pub fn new() -> Self {
+        Self {
+            segments: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl<T> AsyncStorage<T> for MemoryStorage<T>
+where
+    T: Clone + PartialEq + Send + Sync + 'static,
+{
+    fn load(&self) -> BoxFuture<'_, Result<Vec<Segment<'static, T>>>> {
+        async move { Ok(self.segments.lock().await.clone()) }.boxed()
+    }
+
+    fn append_all<'b>(&'b self, events: &'b [Event<'static, T>]) -> BoxFuture<'b, Result<()>> {
+        async move {
+            self.segments
+                .lock()
+                .await
+                .last_mut()
+                .ok_or_else(|| anyhow!("Cannot append events to an empty storage"))?
+                .push_all(events.to_vec())
+        }
+        .boxed()
+    }
+
+    fn store<'b>(&'b self, segments: &'b [Arc<Segment<'static, T>>]) -> BoxFuture<'b, Result<()>> {
+        async move {
+            *self.segments.lock().await = segments.iter().map(|s| Segment::clone(s)).collect();
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/**
+An [`AsyncStorage`] persisting segments as JSON files in a directory.
+
+The segments are stored in `segments.json`, while events appended since
+then are stored in `events.jsonl`. Every line contains a single event, or an
+array of the events appended at once (e.g. by a transaction). Events contained
+in both files (e.g. if storing the segments was interrupted before clearing the
+appended events) are only loaded once.
+
+A final line which can't be read (e.g. if appending was interrupted) is
+discarded when loading the storage, along with all of its events.
+
+[`AsyncStorage`]: trait.AsyncStorage.html
+*/
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    /// The directory containing the files of this storage
+    directory: PathBuf,
+}
+
+impl FileStorage {
+    /// The name of the file containing the segments
+    const SEGMENTS: &'static str = "segments.json";
+
+    /// The name of the file containing the appended events
+    const EVENTS: &'static str = "events.jsonl";
+
+    /// Creates a new storage using a given directory
+    ///
+    /// The directory is created when the segments are stored the first time.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Syncs the entries of a directory (e.g. after renaming a file)
+    #[cfg(unix)]
+    async fn sync_directory(directory: &Path) -> Result<()> {
+        fs::File::open(directory).await?.sync_all().await?;
+        Ok(())
+    }
+
+    /// Syncs the entries of a directory (only supported on Unix)
+    #[cfg(not(unix))]
+    async fn sync_directory(_directory: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Parses a line of the appended events (a single event or an array of events)
+    fn parse_line<T>(line: &[u8]) -> Result<Vec<Event<'static, T>>>
+    where
+        T: Clone + PartialEq + DeserializeOwned,
+    {
+        if line.trim_ascii_start().starts_with(b"[") {
+            Ok(serde_json::from_slice(line)?)
+        } else {
+            Ok(vec![serde_json::from_slice(line)?])
+        }
+    }
+
+    /// Reads a file, returning `None` if it doesn't exist
+    async fn read(&self, name: &str) -> Result<Option<String>> {
+        match fs::read_to_string(self.directory.join(name)).await {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl<T> AsyncStorage<T> for FileStorage
+where
+    T: Clone + PartialEq + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    fn load(&self) -> BoxFuture<'_, Result<Vec<Segment<'static, T>>>> {
+        async move {
+            // Load the segments
+            let mut segments: Vec<Segment<'static, T>> = match self.read(Self::SEGMENTS).await? {
+                Some(content) => serde_json::from_str(&content)?,
+                None => return Ok(vec![]),
+            };
+
+            // Push the appended events onto the latest segment, reading them line by line
+            let file = match fs::File::open(self.directory.join(Self::EVENTS)).await {
+                Ok(file) => file,
+                Err(error) if error.kind() == ErrorKind::NotFound => return Ok(segments),
+                Err(error) => return Err(error.into()),
+            };
+            let mut reader = BufReader::new(file);
+            let mut line = vec![];
+            let mut offset = 0;
+            loop {
+                line.clear();
+                let length = reader.read_until(b'\n', &mut line).await?;
+                if length == 0 {
+                    break;
+                }
+                if line.trim_ascii().is_empty() {
+                    offset += length as u64;
+                    continue;
+                }
+
+                let events = match Self::parse_line::<T>(&line) {
+                    Ok(events) => events,
+                    // Discard a torn final line (e.g. left behind by an interrupted append),
+                    // so appending continues on a new line
+                    Err(_) if reader.fill_buf().await?.is_empty() => {
+                        let file = fs::OpenOptions::new()
+                            .write(true)
+                            .open(self.directory.join(Self::EVENTS))
+                            .await?;
+                        file.set_len(offset).await?;
+                        file.sync_data().await?;
+                        break;
+                    }
+                    Err(error) => return Err(error),
+                };
+                offset += length as u64;
+
+                // Skip events already stored in the segments
+                let events: Vec<_> = events
+                    .into_iter()
+                    .filter(|event| !segments.iter().any(|s| s.contains(event)))
+                    .collect();
+
+                segments
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("Cannot append events to an empty storage"))?
+                    .push_all(events)?;
+            }
+
+            Ok(segments)
+        }
+        .boxed()
+    }
+
+    fn append_all<'b>(&'b self, events: &'b [Event<'static, T>]) -> BoxFuture<'b, Result<()>> {
+        async move {
+            // Write all events as a single line, so they're loaded either all or none
+            let mut line = match events {
+                [] => return Ok(()),
+                [event] => serde_json::to_string(event)?,
+                events => serde_json::to_string(events)?,
+            };
+            line.push('\n');
+
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.directory.join(Self::EVENTS))
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.sync_data().await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn store<'b>(&'b self, segments: &'b [Arc<Segment<'static, T>>]) -> BoxFuture<'b, Result<()>> {
+        async move {
+            fs::create_dir_all(&self.directory).await?;
+
+            // Replace the segments atomically using a temporary file
+            // Both the file and the directory are synced, so the replacement is durable
+            let temporary = self.directory.join(format!("{}.tmp", Self::SEGMENTS));
+            let mut file = fs::File::create(&temporary).await?;
+            file.write_all(&serde_json::to_vec(segments)?).await?;
+            file.sync_all().await?;
+            fs::rename(&temporary, self.directory.join(Self::SEGMENTS)).await?;
+            Self::sync_directory(&self.directory).await?;
+
+            // The appended events are now part of the segments
+            fs::write(self.directory.join(Self::EVENTS), b"").await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,517 @@
+use crate::events::{
+    Change, Event, KeyFunction, Projection, Projector, Segment, Timestamp, Transaction,
+    TransactionId,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, Utc};
+use std::{
+    any::{type_name, Any, TypeId},
+    borrow::Cow,
+    collections::HashMap,
+    sync::mpsc::Receiver,
+};
+
+/// The position of an event in the global sequence of a [`Store`]
+///
+/// [`Store`]: struct.Store.html
+pub type Sequence = u64;
+
+/**
+A store hosts several projectors of different entity types.
+
+All projectors of a store share one clock and one global event sequence,
+so events concerning different entity types (e.g. creating an author and
+their book together) are ordered consistently across all collections.
+Events concerning several collections can be applied atomically using
+[`transaction`].
+
+[`transaction`]: #method.transaction
+*/
+#[derive(Default)]
+pub struct Store {
+    /// The projectors of this store, indexed by the type of their entities
+    collections: HashMap<TypeId, Box<dyn Collection>>,
+
+    /// The global event sequence of this store
+    log: Vec<LogEntry>,
+
+    /// The latest moment in time issued by the clock of this store
+    clock: Option<Timestamp>,
+
+    /// The identifier of the next transaction, shared by all collections
+    next_transaction: TransactionId,
+}
+
+/// An entry in the global event sequence of a [`Store`]
+///
+/// [`Store`]: struct.Store.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// The position of the event in the global sequence
+    sequence: Sequence,
+
+    /// The moment in time the event occurred
+    timestamp: Timestamp,
+
+    /// The name of the entity type of the event
+    collection: &'static str,
+
+    /// The entity type of the event
+    type_id: TypeId,
+
+    /// The transaction the event is part of (if any)
+    transaction: Option<TransactionId>,
+}
+
+/**
+A transaction collects several events concerning different entity types
+of a [`Store`] to be applied atomically.
+
+All events of a transaction share the same timestamp and transaction id,
+which is unique across all collections of the store. The events of every
+collection are validated against a copy of its current projection before
+any of them are committed; if any of them fails, none of them are applied.
+
+Transactions are created using [`Store::transaction`].
+
+[`Store`]: struct.Store.html
+[`Store::transaction`]: struct.Store.html#method.transaction
+*/
+pub struct StoreTransaction<'s> {
+    /// The store the transaction is applied to
+    store: &'s Store,
+
+    /// The moment in time all events of this transaction occur
+    timestamp: Timestamp,
+
+    /// The staged events of every collection, indexed by their entity types
+    staged: HashMap<TypeId, Staged>,
+
+    /// The entity types of all staged events, in the order they were added
+    order: Vec<(TypeId, &'static str)>,
+}
+
+/// The type-erased events of a [`StoreTransaction`] concerning a single collection
+///
+/// [`StoreTransaction`]: struct.StoreTransaction.html
+struct Staged {
+    /// The transaction of the collection
+    transaction: Box<dyn Any>,
+
+    /// Creates the collection starting at a given moment in time if it isn't registered yet
+    new_collection: fn(Timestamp) -> Box<dyn Collection>,
+}
+
+/// A type-erased projector held by a [`Store`]
+///
+/// [`Store`]: struct.Store.html
+trait Collection {
+    /// Returns the projector as a shared reference to `Any`
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns the projector as a mutable reference to `Any`
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Makes a new snapshot of the projector
+    fn make_snapshot(&mut self);
+
+    /// Validates a staged transaction of the projector (without committing it)
+    fn validate(&self, transaction: &dyn Any) -> Result<()>;
+
+    /// Validates and commits a staged transaction of the projector
+    fn commit(&mut self, transaction: Box<dyn Any>) -> Result<()>;
+}
+
+impl<T> Collection for Projector<'static, T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn make_snapshot(&mut self) {
+        Projector::make_snapshot(self)
+    }
+
+    fn validate(&self, transaction: &dyn Any) -> Result<()> {
+        let transaction = transaction
+            .downcast_ref::<Transaction<'static, T>>()
+            .ok_or_else(|| anyhow!("Cannot find the transaction of the entity type"))?;
+        self.validate_transaction(transaction)
+    }
+
+    fn commit(&mut self, transaction: Box<dyn Any>) -> Result<()> {
+        let transaction = transaction
+            .downcast::<Transaction<'static, T>>()
+            .map_err(|_| anyhow!("Cannot find the transaction of the entity type"))?;
+        self.commit_transaction(*transaction)?;
+
+        Ok(())
+    }
+}
+
+/// Creates a new (empty) type-erased projector of a given entity type
+/// starting at a given moment in time
+fn new_collection_at<T>(timestamp: Timestamp) -> Box<dyn Collection>
+where
+    T: Clone + PartialEq + 'static,
+{
+    let segment = Segment::from_parts(timestamp, vec![], vec![]);
+
+    // Unwraps safely because there's exactly one segment
+    Box::new(Projector::<'static, T>::from_segments(vec![segment]).unwrap())
+}
+
+impl Store {
+    /// Generates a new, empty store
+    pub fn new() -> Store {
+        Self::default()
+    }
+
+    /// Registers a new (empty) collection for a given entity type
+    ///
+    /// Does nothing if the collection already exists.
+    pub fn register<T>(&mut self)
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.collections
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Projector::<'static, T>::new()));
+    }
+
+    /// Returns the projector of a given entity type (if registered)
+    pub fn collection<T>(&self) -> Option<&Projector<'static, T>>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.collections
+            .get(&TypeId::of::<T>())
+            .and_then(|c| c.as_any().downcast_ref())
+    }
+
+    /// Returns the projector of a given entity type, registering it if needed
+    fn collection_mut<T>(&mut self) -> &mut Projector<'static, T>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.register::<T>();
+
+        // Unwraps safely because the collection was just registered for this entity type
+        self.collections
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|c| c.as_any_mut().downcast_mut())
+            .unwrap()
+    }
+
+    /// Subscribes to the changes of the collection of a given entity type
+    /// (registering it if needed)
+    ///
+    /// See [`Projector::subscribe`](struct.Projector.html#method.subscribe) for details.
+    pub fn subscribe<T>(&mut self) -> Receiver<Change<'static, T>>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.collection_mut::<T>().subscribe()
+    }
+
+    /// Adds (or replaces) a secondary index of the collection of a given entity type
+    /// (registering it if needed)
+    ///
+    /// See [`Projector::add_index`](struct.Projector.html#method.add_index) for details.
+    pub fn add_index<T>(&mut self, name: &str, key_function: KeyFunction<T>)
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.collection_mut::<T>().add_index(name, key_function);
+    }
+
+    /// Adds (or replaces) a custom projection of the collection of a given entity type
+    /// (registering it if needed)
+    ///
+    /// See [`Projector::add_projection`](struct.Projector.html#method.add_projection) for details.
+    pub fn add_projection<T, P>(&mut self, name: &str, initial: P)
+    where
+        T: Clone + PartialEq + 'static,
+        P: Projection<T>,
+    {
+        self.collection_mut::<T>().add_projection(name, initial);
+    }
+
+    /// Returns the next moment in time of the shared clock
+    ///
+    /// The returned timestamps are strictly increasing, even when
+    /// requested faster than the resolution of the system clock.
+    pub fn now(&mut self) -> Timestamp {
+        let now = Utc::now();
+
+        // Never go back in time, and never issue the same timestamp twice
+        let next = match self.clock {
+            Some(last) if now <= last => last + Duration::nanoseconds(1),
+            _ => now,
+        };
+
+        self.clock = Some(next);
+        next
+    }
+
+    /// Creates a new entity using the shared clock
+    pub fn create<T>(&mut self, data: T) -> Result<Sequence>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        let timestamp = self.now();
+        self.push(Event::create_at(Cow::<T>::Owned(data), timestamp))
+    }
+
+    /// Mutates an existing entity using the shared clock
+    pub fn update<T>(&mut self, data: T) -> Result<Sequence>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        let timestamp = self.now();
+        self.push(Event::update_at(Cow::<T>::Owned(data), timestamp))
+    }
+
+    /// Deletes an existing entity using the shared clock
+    pub fn delete<T>(&mut self, data: T) -> Result<Sequence>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        let timestamp = self.now();
+        self.push(Event::delete_at(Cow::<T>::Owned(data), timestamp))
+    }
+
+    /// Pushes an event onto the collection of its entity type,
+    /// returning its position in the global sequence
+    ///
+    /// The collection is registered if it doesn't exist yet (once the event was pushed).
+    /// Events predating the latest event of the store are rejected.
+    pub fn push<T>(&mut self, event: Event<'static, T>) -> Result<Sequence>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        let timestamp = *event.get_time();
+
+        // Check if the new event predates the latest event of any collection
+        if let Some(last_entry) = self.log.last() {
+            if timestamp < last_entry.timestamp {
+                bail!("Cannot accept events predating the latest event of the store")
+            }
+        }
+
+        // Get the collection of the entity type (creating it without registering it if needed)
+        let mut created = None;
+        let collection = match self.collections.get_mut(&TypeId::of::<T>()) {
+            Some(collection) => collection,
+            None => created.insert(new_collection_at::<T>(timestamp)),
+        };
+        let projector = collection
+            .as_any_mut()
+            .downcast_mut::<Projector<'static, T>>()
+            .ok_or_else(|| anyhow!("Cannot find the collection of the entity type"))?;
+
+        // Apply the event to the collection, only registering new ones if it succeeds
+        projector.push(event)?;
+        if let Some(collection) = created {
+            self.collections.insert(TypeId::of::<T>(), collection);
+        }
+
+        // Keep the clock ahead of externally timestamped events
+        if self.clock < Some(timestamp) {
+            self.clock = Some(timestamp);
+        }
+
+        // Append the event to the global sequence
+        let sequence = self.log.len() as Sequence;
+        self.log.push(LogEntry {
+            sequence,
+            timestamp,
+            collection: type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            transaction: None,
+        });
+
+        Ok(sequence)
+    }
+
+    /// Applies several events concerning any entity types atomically,
+    /// returning their positions in the global sequence
+    ///
+    /// The events are added to the transaction by the given closure, and all share
+    /// the same timestamp of the shared clock and the same transaction id. The
+    /// events of every collection are validated before any of them are committed.
+    /// If any of them fails, none of them are applied and all collections (as well
+    /// as the log) are left unchanged.
+    ///
+    /// A transaction without any events still consumes a transaction id, while a
+    /// failed one doesn't.
+    pub fn transaction<F>(&mut self, f: F) -> Result<Vec<Sequence>>
+    where
+        F: FnOnce(&mut StoreTransaction),
+    {
+        // Let the caller stage the events of the transaction
+        let timestamp = self.now();
+        let mut transaction = StoreTransaction {
+            store: self,
+            timestamp,
+            staged: HashMap::new(),
+            order: vec![],
+        };
+        f(&mut transaction);
+        let StoreTransaction { staged, order, .. } = transaction;
+
+        // Create the collections which aren't registered yet (without registering them)
+        let mut created: HashMap<TypeId, Box<dyn Collection>> = staged
+            .iter()
+            .filter(|(type_id, _)| !self.collections.contains_key(type_id))
+            .map(|(type_id, staged)| (*type_id, (staged.new_collection)(timestamp)))
+            .collect();
+
+        // Validate the events of every collection before committing any of them
+        for (type_id, staged) in &staged {
+            let collection = self
+                .collections
+                .get(type_id)
+                .or_else(|| created.get(type_id))
+                .ok_or_else(|| anyhow!("Cannot find the collection of the entity type"))?;
+            collection.validate(staged.transaction.as_ref())?;
+        }
+
+        // Commit the events of every collection
+        // Doesn't fail, as all events were validated before
+        for (type_id, staged) in staged {
+            let collection = match created.remove(&type_id) {
+                Some(collection) => self.collections.entry(type_id).or_insert(collection),
+                None => self
+                    .collections
+                    .get_mut(&type_id)
+                    .ok_or_else(|| anyhow!("Cannot find the collection of the entity type"))?,
+            };
+            collection.commit(staged.transaction)?;
+        }
+
+        // Only consume the id once the transaction was committed
+        let id = self.next_transaction;
+        self.next_transaction += 1;
+
+        // Append the events to the global sequence, in the order they were staged
+        Ok(order
+            .into_iter()
+            .map(|(type_id, collection)| {
+                let sequence = self.log.len() as Sequence;
+                self.log.push(LogEntry {
+                    sequence,
+                    timestamp,
+                    collection,
+                    type_id,
+                    transaction: Some(id),
+                });
+                sequence
+            })
+            .collect())
+    }
+
+    /// Makes a new snapshot of every collection of this store
+    pub fn make_snapshot(&mut self) {
+        self.collections
+            .values_mut()
+            .for_each(|collection| collection.make_snapshot());
+    }
+
+    /// Returns a reference to the global event sequence of this store
+    pub fn get_log(&self) -> &Vec<LogEntry> {
+        &self.log
+    }
+}
+
+impl<'s> StoreTransaction<'s> {
+    /// Adds a create event to the transaction
+    pub fn create<T>(&mut self, data: T) -> &mut Self
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.stage::<T>().create(Cow::Owned(data));
+        self
+    }
+
+    /// Adds an update event to the transaction
+    pub fn update<T>(&mut self, data: T) -> &mut Self
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.stage::<T>().update(Cow::Owned(data));
+        self
+    }
+
+    /// Adds a delete event to the transaction
+    pub fn delete<T>(&mut self, data: T) -> &mut Self
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        self.stage::<T>().delete(Cow::Owned(data));
+        self
+    }
+
+    /// Returns the identifier of this transaction
+    pub fn get_id(&self) -> TransactionId {
+        self.store.next_transaction
+    }
+
+    /// Returns a shared reference to the timestamp of this transaction
+    pub fn get_time(&self) -> &Timestamp {
+        &self.timestamp
+    }
+
+    /// Returns the transaction of the collection of an entity type for the next event,
+    /// beginning it if needed
+    fn stage<T>(&mut self) -> &mut Transaction<'static, T>
+    where
+        T: Clone + PartialEq + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        self.order.push((type_id, type_name::<T>()));
+
+        let (id, timestamp) = (self.get_id(), self.timestamp);
+        let staged = self.staged.entry(type_id).or_insert_with(|| Staged {
+            // Use the transaction id of the store in every collection
+            transaction: Box::new(Transaction::<'static, T>::new(id, timestamp)),
+            new_collection: new_collection_at::<T>,
+        });
+
+        // Unwraps safely because the transaction was staged for this entity type
+        staged.transaction.downcast_mut().unwrap()
+    }
+}
+
+impl LogEntry {
+    /// Returns the position of the event in the global sequence
+    pub fn get_sequence(&self) -> Sequence {
+        self.sequence
+    }
+
+    /// Returns a shared reference to the timestamp of the event
+    pub fn get_time(&self) -> &Timestamp {
+        &self.timestamp
+    }
+
+    /// Returns the name of the entity type of the event
+    pub fn get_collection(&self) -> &'static str {
+        self.collection
+    }
+
+    /// Returns the transaction the event is part of (if any)
+    pub fn get_transaction(&self) -> Option<TransactionId> {
+        self.transaction
+    }
+
+    /// Checks if the event concerns a given entity type
+    pub fn is<T: 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+}
@@ -0,0 +1,90 @@
+use crate::events::{Event, Timestamp};
+use std::borrow::Cow;
+
+/// The identifier shared by all events of a [`Transaction`]
+///
+/// [`Transaction`]: struct.Transaction.html
+pub type TransactionId = u64;
+
+/**
+A transaction collects several events to be applied atomically.
+
+All events of a transaction share the same timestamp and transaction id,
+and are told apart by their positions within the transaction.
+They are validated against a copy of the current projection before being
+committed; if any of them fails, none of them are applied.
+
+Transactions are created using [`Projector::transaction`].
+
+[`Projector::transaction`]: struct.Projector.html#method.transaction
+*/
+#[derive(Debug, Clone)]
+pub struct Transaction<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The identifier shared by all events of this transaction
+    id: TransactionId,
+
+    /// The moment in time all events of this transaction occur
+    timestamp: Timestamp,
+
+    /// The events to be committed
+    events: Vec<Event<'a, T>>,
+}
+
+impl<'a, T> Transaction<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Begins a new, empty transaction
+    pub(super) fn new(id: TransactionId, timestamp: Timestamp) -> Self {
+        Self {
+            id,
+            timestamp,
+            events: vec![],
+        }
+    }
+
+    /// Adds a create event to the transaction
+    pub fn create(&mut self, data: Cow<'a, T>) -> &mut Self {
+        self.add(Event::create_at(data, self.timestamp))
+    }
+
+    /// Adds an update event to the transaction
+    pub fn update(&mut self, data: Cow<'a, T>) -> &mut Self {
+        self.add(Event::update_at(data, self.timestamp))
+    }
+
+    /// Adds a delete event to the transaction
+    pub fn delete(&mut self, data: Cow<'a, T>) -> &mut Self {
+        self.add(Event::delete_at(data, self.timestamp))
+    }
+
+    /// Returns the identifier of this transaction
+    pub fn get_id(&self) -> TransactionId {
+        self.id
+    }
+
+    /// Returns a shared reference to the timestamp of this transaction
+    pub fn get_time(&self) -> &Timestamp {
+        &self.timestamp
+    }
+
+    /// Returns a reference to the events added to this transaction so far
+    pub fn get_events(&self) -> &Vec<Event<'a, T>> {
+        &self.events
+    }
+
+    /// Consumes the transaction, returning its events
+    pub(super) fn take(self) -> Vec<Event<'a, T>> {
+        self.events
+    }
+
+    /// Marks an event as part of this transaction and adds it
+    fn add(&mut self, mut event: Event<'a, T>) -> &mut Self {
+        event.set_transaction(self.id, self.events.len());
+        self.events.push(event);
+        self
+    }
+}
@@ -0,0 +1,138 @@
+use crate::events::Event;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{cmp::Ordering, collections::BTreeMap};
+
+/// The identifier of a replica (e.g. a device or a server)
+pub type ReplicaId = String;
+
+/**
+A version vector, counting the events of every replica known at some point.
+
+Version vectors capture causality between replicas, which wall-clock
+timestamps can't: An event happened before another one if the version of
+the former is less than the version of the latter, while two events are
+concurrent if neither version is less than or equal to the other one.
+
+They're only used to detect concurrent events (see [`Projector::get_conflicts`])
+and to hold back events delivered before their causal predecessors (see
+[`Projector::deliver`]). Projections still apply events in the order of their
+timestamps, so concurrent events aren't reordered.
+
+[`Projector::get_conflicts`]: struct.Projector.html#method.get_conflicts
+[`Projector::deliver`]: struct.Projector.html#method.deliver
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct VersionVector {
+    /// The number of events of every replica (replicas without any events are omitted)
+    #[serde(deserialize_with = "deserialize_counters")]
+    counters: BTreeMap<ReplicaId, u64>,
+}
+
+impl VersionVector {
+    /// Creates a new, empty version vector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of events of a replica
+    pub fn get(&self, replica: &str) -> u64 {
+        self.counters.get(replica).copied().unwrap_or(0)
+    }
+
+    /// Returns a shared reference to the counters of all replicas
+    pub fn get_counters(&self) -> &BTreeMap<ReplicaId, u64> {
+        &self.counters
+    }
+
+    /// Counts a new event of a replica, returning the new counter
+    pub fn increment(&mut self, replica: &str) -> u64 {
+        let counter = self.counters.entry(replica.to_owned()).or_insert(0);
+        *counter += 1;
+        *counter
+    }
+
+    /// Merges another version vector into this one (using the maximum of every counter)
+    pub fn merge(&mut self, other: &Self) {
+        for (replica, &counter) in &other.counters {
+            let own = self.counters.entry(replica.clone()).or_insert(0);
+            *own = (*own).max(counter);
+        }
+    }
+
+    /// Checks if neither version vector is less than or equal to the other one
+    pub fn is_concurrent_with(&self, other: &Self) -> bool {
+        self.partial_cmp(other).is_none()
+    }
+}
+
+/// Deserializes the counters, omitting replicas without any events (so that
+/// equal versions are equal counters, like [`VersionVector::partial_cmp`] expects)
+fn deserialize_counters<'de, D>(deserializer: D) -> Result<BTreeMap<ReplicaId, u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut counters = BTreeMap::<ReplicaId, u64>::deserialize(deserializer)?;
+    counters.retain(|_, counter| *counter > 0);
+    Ok(counters)
+}
+
+impl PartialOrd for VersionVector {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let mut less = false;
+        let mut greater = false;
+
+        // Compare the counters of all replicas known to either vector
+        for replica in self.counters.keys().chain(other.counters.keys()) {
+            match self.get(replica).cmp(&other.get(replica)) {
+                Ordering::Less => less = true,
+                Ordering::Greater => greater = true,
+                Ordering::Equal => {}
+            }
+        }
+
+        match (less, greater) {
+            (false, false) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (true, true) => None,
+        }
+    }
+}
+
+/**
+A conflict between concurrent events concerning the same entity.
+
+A conflict exists while no event concerning the entity causally follows all
+of the concurrent events. Resolve it by pushing an event stamped using
+[`Projector::stamp`], which follows all events known to the projector.
+
+Conflicts are also reported by [`Projector::merge_branch`], containing the
+events of both lines concerning an entity modified by both of them.
+
+[`Projector::stamp`]: struct.Projector.html#method.stamp
+[`Projector::merge_branch`]: struct.Projector.html#method.merge_branch
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The concurrent events, ordered by their timestamps
+    events: Vec<Event<'a, T>>,
+}
+
+impl<'a, T> Conflict<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Constructs a new conflict from at least two concurrent events
+    pub(super) fn new(events: Vec<Event<'a, T>>) -> Self {
+        Self { events }
+    }
+
+    /// Returns a shared reference to the concurrent events (ordered by their timestamps)
+    pub fn get_events(&self) -> &Vec<Event<'a, T>> {
+        &self.events
+    }
+}
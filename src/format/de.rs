@@ -0,0 +1,373 @@
+use super::{Error, Result, BYTES, FALSE, FLOAT, MAP, NINT, NULL, SEQ, SOME, STRING, TRUE, UINT};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+/// The maximum nesting depth of sequences, maps, options and enums
+///
+/// Deserializing is recursive, so deeper input would overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Deserializes a value from its canonical encoding, rejecting trailing bytes
+pub fn from_slice<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+    let mut deserializer = Deserializer::from_slice(input);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Reads a varint (LEB128), rejecting over-long encodings and advancing the slice
+pub(crate) fn read_varint(input: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+
+    loop {
+        let (&byte, rest) = input
+            .split_first()
+            .ok_or_else(|| Error::new("Unexpected end of input"))?;
+        *input = rest;
+        let bits = u64::from(byte & 0x7f);
+
+        // The bits must fit into 64 bits
+        if shift == 63 && bits > 1 || shift > 63 {
+            return Err(Error::new("Integer out of range"));
+        }
+        value |= bits << shift;
+
+        if byte & 0x80 == 0 {
+            // The shortest form never ends with a zero byte (except for zero itself)
+            if byte == 0 && shift != 0 {
+                return Err(Error::new("Non-canonical integer"));
+            }
+
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+/**
+A deserializer reading the canonical encoding of a value.
+
+Use [`from_slice`] unless you need to deserialize multiple values from one buffer.
+
+[`from_slice`]: fn.from_slice.html
+*/
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    /// The remaining input
+    input: &'de [u8],
+
+    /// The nesting depth of the value being deserialized
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a new deserializer reading from a slice
+    pub fn from_slice(input: &'de [u8]) -> Self {
+        Self { input, depth: 0 }
+    }
+
+    /// Checks if all input was consumed
+    pub fn end(&self) -> Result<()> {
+        if !self.input.is_empty() {
+            return Err(Error::new(format!(
+                "Found {} trailing bytes",
+                self.input.len()
+            )));
+        }
+
+        // Return Ok
+        Ok(())
+    }
+
+    /// Deserializes a nested value, failing if the input is nested too deeply
+    fn nested<R, F>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Self) -> Result<R>,
+    {
+        if self.depth == MAX_DEPTH {
+            return Err(Error::new(format!(
+                "Exceeded the maximum nesting depth of {}",
+                MAX_DEPTH
+            )));
+        }
+
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// Returns the next byte without consuming it
+    fn peek(&self) -> Result<u8> {
+        self.input
+            .first()
+            .copied()
+            .ok_or_else(|| Error::new("Unexpected end of input"))
+    }
+
+    /// Consumes the next byte
+    fn next(&mut self) -> Result<u8> {
+        let byte = self.peek()?;
+        self.input = &self.input[1..];
+        Ok(byte)
+    }
+
+    /// Consumes a given number of bytes
+    fn take(&mut self, length: usize) -> Result<&'de [u8]> {
+        if length > self.input.len() {
+            return Err(Error::new("Unexpected end of input"));
+        }
+
+        let (bytes, rest) = self.input.split_at(length);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    /// Reads a varint (LEB128), rejecting over-long encodings
+    fn read_varint(&mut self) -> Result<u64> {
+        read_varint(&mut self.input)
+    }
+
+    /// Reads a length
+    fn read_length(&mut self) -> Result<usize> {
+        let length = self.read_varint()?;
+
+        // Every element takes at least one byte, so longer lengths are invalid
+        if length > self.input.len() as u64 {
+            return Err(Error::new("Length exceeds the input"));
+        }
+
+        Ok(length as usize)
+    }
+
+    /// Reads a string after its tag
+    fn read_str(&mut self) -> Result<&'de str> {
+        let length = self.read_length()?;
+        std::str::from_utf8(self.take(length)?).map_err(Error::new)
+    }
+}
+
+/// Provides the elements of a sequence
+struct SeqAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+/// Provides the entries of a map, checking their order
+struct MapAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+
+    /// The encoding of the previous key
+    previous: Option<&'de [u8]>,
+}
+
+/// Provides the variant of an enum encoded as a map containing a single entry
+struct EnumAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.next()? {
+            NULL => visitor.visit_unit(),
+            FALSE => visitor.visit_bool(false),
+            TRUE => visitor.visit_bool(true),
+            SOME => self.nested(|de| visitor.visit_some(de)),
+            UINT => visitor.visit_u64(self.read_varint()?),
+            NINT => {
+                let value = self.read_varint()?;
+                if value > i64::MAX as u64 {
+                    return Err(Error::new("Integer out of range"));
+                }
+
+                // Decode -1 - value
+                visitor.visit_i64(!(value as i64))
+            }
+            FLOAT => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                let value = f64::from_be_bytes(bytes);
+
+                if value.is_nan() && value.to_bits() != f64::NAN.to_bits() {
+                    return Err(Error::new("Non-canonical NaN"));
+                }
+                visitor.visit_f64(value)
+            }
+            STRING => visitor.visit_borrowed_str(self.read_str()?),
+            BYTES => {
+                let length = self.read_length()?;
+                visitor.visit_borrowed_bytes(self.take(length)?)
+            }
+            SEQ => {
+                let remaining = self.read_length()?;
+                self.nested(|de| {
+                    let mut access = SeqAccess { remaining, de };
+                    let value = visitor.visit_seq(&mut access)?;
+
+                    if access.remaining != 0 {
+                        return Err(Error::new("Not all elements of a sequence were read"));
+                    }
+                    Ok(value)
+                })
+            }
+            MAP => {
+                let remaining = self.read_length()?;
+                self.nested(|de| {
+                    let mut access = MapAccess {
+                        remaining,
+                        de,
+                        previous: None,
+                    };
+                    let value = visitor.visit_map(&mut access)?;
+
+                    if access.remaining != 0 {
+                        return Err(Error::new("Not all entries of a map were read"));
+                    }
+                    Ok(value)
+                })
+            }
+            tag => Err(Error::new(format!("Unknown tag {:#04x}", tag))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.peek()? {
+            NULL => {
+                self.next()?;
+                visitor.visit_none()
+            }
+            SOME => {
+                self.next()?;
+                self.nested(|de| visitor.visit_some(de))
+            }
+            _ => Err(Error::new("Expected an option")),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.next()? {
+            // A unit variant
+            STRING => visitor.visit_enum(self.read_str()?.into_deserializer()),
+
+            // Any other variant
+            MAP => {
+                if self.read_varint()? != 1 {
+                    return Err(Error::new("Expected a map containing a single variant"));
+                }
+                self.nested(|de| visitor.visit_enum(EnumAccess { de }))
+            }
+            _ => Err(Error::new("Expected an enum")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        // Remember the encoding of the key
+        let start = self.de.input;
+        let key = seed.deserialize(&mut *self.de)?;
+        let encoded = &start[..start.len() - self.de.input.len()];
+
+        // The keys must be unique and sorted
+        if let Some(previous) = self.previous {
+            if previous >= encoded {
+                return Err(Error::new("Map keys must be unique and sorted"));
+            }
+        }
+        self.previous = Some(encoded);
+
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EnumAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        // Unit variants are encoded as strings
+        Err(Error::new(
+            "Expected a unit variant to be encoded as a string",
+        ))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
@@ -0,0 +1,106 @@
+/*!
+This module implements a compact, self-describing and canonical data format for Serde.
+
+It's an alternative to JSON suitable for both storage and hashing (e.g. using
+multihash) of [`Event`]s, as encoding the same data always yields the same bytes:
+
+- Every value starts with a one-byte tag describing its type
+- Integers are encoded as variable-length integers (LEB128) using the shortest
+  form, and regardless of their Rust type (e.g. `5u8` and `5i64` are equal)
+- Floats are always encoded as 64-bit big-endian floats (with a single NaN)
+- Maps and structs are encoded as maps, whose entries are sorted by their
+  encoded keys (so the order of e.g. a `HashMap` doesn't matter)
+- Enum variants are encoded like JSON does by default (unit variants as strings,
+  all others as maps containing a single entry)
+
+The deserializer accepts only canonical encodings, rejecting e.g. unsorted or
+duplicate keys, over-long integers or trailing bytes. Re-encoding a decoded value
+yields the original bytes as long as its type keeps everything it reads (e.g. an
+[`Event`] decoded from an encoded `Event`), but not if it ignores unknown fields
+or converts between types. It also rejects values nested more than 128 levels deep.
+
+[`Event`]: ../events/enum.Event.html
+*/
+
+mod de;
+mod ser;
+
+pub use de::{from_slice, Deserializer};
+pub use ser::{to_vec, Serializer};
+
+pub(crate) use de::read_varint;
+pub(crate) use ser::write_varint;
+
+use std::fmt::{self, Display};
+
+/// The result type used by the serializer and deserializer
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The unit value, `None` or a unit struct
+const NULL: u8 = 0x00;
+
+/// The boolean `false`
+const FALSE: u8 = 0x01;
+
+/// The boolean `true`
+const TRUE: u8 = 0x02;
+
+/// `Some`, followed by the contained value
+const SOME: u8 = 0x03;
+
+/// A non-negative integer `n`, followed by `n` as a varint
+const UINT: u8 = 0x10;
+
+/// A negative integer `n`, followed by `-1 - n` as a varint
+const NINT: u8 = 0x11;
+
+/// A float, followed by its 8 big-endian bytes
+const FLOAT: u8 = 0x12;
+
+/// A string, followed by its length (as a varint) and its UTF-8 bytes
+const STRING: u8 = 0x20;
+
+/// A byte array, followed by its length (as a varint) and its bytes
+const BYTES: u8 = 0x21;
+
+/// A sequence or tuple, followed by its length (as a varint) and its elements
+const SEQ: u8 = 0x30;
+
+/// A map or struct, followed by its length (as a varint) and its sorted entries
+const MAP: u8 = 0x31;
+
+/// An error occurring while serializing or deserializing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    /// The description of the error
+    message: String,
+}
+
+impl Error {
+    /// Creates a new error with a given description
+    fn new<M: Display>(message: M) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::ser::Error for Error {
+    fn custom<M: Display>(message: M) -> Self {
+        Self::new(message)
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<M: Display>(message: M) -> Self {
+        Self::new(message)
+    }
+}
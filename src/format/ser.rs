@@ -0,0 +1,448 @@
+use super::{Error, Result, BYTES, FALSE, FLOAT, MAP, NINT, NULL, SEQ, SOME, STRING, TRUE, UINT};
+use serde::{ser, Serialize};
+
+/// Serializes a value into its canonical encoding
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// Writes an unsigned integer as a varint (LEB128) using the shortest form
+pub(crate) fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            output.push(byte);
+            return;
+        }
+
+        // Set the continuation bit
+        output.push(byte | 0x80);
+    }
+}
+
+/**
+A serializer producing the canonical encoding of a value.
+
+Use [`to_vec`] unless you need to serialize multiple values into one buffer.
+
+[`to_vec`]: fn.to_vec.html
+*/
+#[derive(Debug, Default)]
+pub struct Serializer {
+    /// The encoded bytes
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    /// Creates a new serializer with an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the encoded bytes
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+
+    /// Writes an unsigned integer as a varint (LEB128)
+    fn write_varint(&mut self, value: u64) {
+        write_varint(&mut self.output, value);
+    }
+
+    /// Writes a tag followed by a length
+    fn write_header(&mut self, tag: u8, length: usize) {
+        self.output.push(tag);
+        self.write_varint(length as u64);
+    }
+
+    /// Writes a map from its encoded entries, sorting them by their keys
+    fn write_map(&mut self, mut entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Keys must be unique
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::new("Map keys must be unique"));
+        }
+
+        self.write_header(MAP, entries.len());
+        for (key, value) in entries {
+            self.output.extend(key);
+            self.output.extend(value);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the start of a map containing a single entry keyed by a variant name
+    fn write_variant(&mut self, variant: &str) {
+        self.write_header(MAP, 1);
+        self.write_str(variant);
+    }
+
+    /// Writes a string
+    fn write_str(&mut self, value: &str) {
+        self.write_header(STRING, value.len());
+        self.output.extend_from_slice(value.as_bytes());
+    }
+}
+
+/// Serializes the elements of a sequence, tuple or tuple struct
+#[derive(Debug)]
+pub struct SeqSerializer<'a> {
+    /// The serializer to write the sequence to
+    parent: &'a mut Serializer,
+
+    /// The encoded elements
+    elements: Serializer,
+
+    /// The number of elements
+    length: usize,
+}
+
+/// Serializes the entries of a map or struct
+#[derive(Debug)]
+pub struct MapSerializer<'a> {
+    /// The serializer to write the map to
+    parent: &'a mut Serializer,
+
+    /// The encoded entries
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+
+    /// The encoded key of the entry being serialized
+    key: Option<Vec<u8>>,
+}
+
+impl<'a> SeqSerializer<'a> {
+    fn new(parent: &'a mut Serializer) -> Self {
+        Self {
+            parent,
+            elements: Serializer::new(),
+            length: 0,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.length += 1;
+        value.serialize(&mut self.elements)
+    }
+
+    fn finish(self) -> Result<()> {
+        self.parent.write_header(SEQ, self.length);
+        self.parent.output.extend(self.elements.output);
+        Ok(())
+    }
+}
+
+impl<'a> MapSerializer<'a> {
+    fn new(parent: &'a mut Serializer) -> Self {
+        Self {
+            parent,
+            entries: vec![],
+            key: None,
+        }
+    }
+
+    fn push_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let mut encoded = Serializer::new();
+        encoded.write_str(key);
+        self.entries.push((encoded.output, to_vec(value)?));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        self.parent.write_map(self.entries)
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = SeqSerializer<'a>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = MapSerializer<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.output.push(if v { TRUE } else { FALSE });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        if v >= 0 {
+            return self.serialize_u64(v as u64);
+        }
+
+        // Encode -1 - v, which is non-negative
+        self.output.push(NINT);
+        self.write_varint(!(v as u64));
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.output.push(UINT);
+        self.write_varint(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        // There's only one canonical NaN
+        let v = if v.is_nan() { f64::NAN } else { v };
+
+        self.output.push(FLOAT);
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_str(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_str(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_header(BYTES, v.len());
+        self.output.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.output.push(SOME);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.output.push(NULL);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.write_variant(variant);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SeqSerializer::new(self))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(SeqSerializer::new(self))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(SeqSerializer::new(self))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.write_variant(variant);
+        Ok(SeqSerializer::new(self))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapSerializer::new(self))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(MapSerializer::new(self))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.write_variant(variant);
+        Ok(MapSerializer::new(self))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key = Some(to_vec(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::new("Cannot serialize a map value without a key"))?;
+        self.entries.push((key, to_vec(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
@@ -0,0 +1,196 @@
+/*!
+This module converts event logs from and to the JSON shapes used by [libocc-ts].
+
+An event of libocc-ts looks like this:
+
+```json
+{ "date": "2021-03-14T15:09:26.535Z", "operation": "update", "data": { ... } }
+```
+
+A projector of libocc-ts contains its event log and its projection:
+
+```json
+{ "eventLog": [ ... ], "projection": [ ... ] }
+```
+
+Use [`TsEvent`] and [`TsProjector`] with any Serde data format (usually JSON)
+to exchange event logs with libocc-ts clients.
+
+Note that libocc-ts only knows milliseconds and neither segments nor transactions:
+Converting to the libocc-ts shapes truncates timestamps to milliseconds, flattens
+all segments into a single event log and drops transaction ids.
+
+[libocc-ts]: https://github.com/Bernd-L/libocc-ts
+[`TsEvent`]: struct.TsEvent.html
+[`TsProjector`]: struct.TsProjector.html
+*/
+
+use crate::{
+    events::{Event, Projector, Timestamp},
+    format,
+};
+use anyhow::{bail, Result};
+use chrono::{DurationRound, SecondsFormat};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+
+/// The operation of a libocc-ts event
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// The entity was created
+    Create,
+
+    /// The entity was updated
+    Update,
+
+    /// The entity was deleted
+    Delete,
+}
+
+/// An event in the shape used by libocc-ts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TsEvent<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// The moment in time the event occurred (with millisecond precision)
+    #[serde(serialize_with = "serialize_date")]
+    date: Timestamp,
+
+    /// The operation of the event
+    operation: Operation,
+
+    /// The entity after the occurrence of this event
+    data: Cow<'a, T>,
+}
+
+/// A projector in the shape used by libocc-ts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TsProjector<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// All events of the projector in order
+    event_log: Vec<TsEvent<'a, T>>,
+
+    /// The current projection
+    projection: Vec<Cow<'a, T>>,
+}
+
+/// Serializes a date like `Date.prototype.toJSON` does
+fn serialize_date<S: Serializer>(date: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&date.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+impl<'a, T> TsEvent<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Returns a shared reference to the date of the event
+    pub fn get_date(&self) -> &Timestamp {
+        &self.date
+    }
+
+    /// Returns the operation of the event
+    pub fn get_operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Returns a shared reference to the data of the event
+    pub fn get_data(&self) -> &T {
+        &self.data
+    }
+}
+
+impl<'a, T> From<Event<'a, T>> for TsEvent<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    fn from(event: Event<'a, T>) -> Self {
+        let operation = match &event {
+            Event::Create(_) => Operation::Create,
+            Event::Update(_) => Operation::Update,
+            Event::Delete(_) => Operation::Delete,
+        };
+
+        // libocc-ts only knows milliseconds
+        // Unwraps safely because truncating to milliseconds never overflows
+        let date = event
+            .get_time()
+            .duration_trunc(chrono::Duration::milliseconds(1))
+            .unwrap();
+
+        Self {
+            date,
+            operation,
+            data: event.take(),
+        }
+    }
+}
+
+impl<'a, T> From<TsEvent<'a, T>> for Event<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    fn from(event: TsEvent<'a, T>) -> Self {
+        match event.operation {
+            Operation::Create => Event::create_at(event.data, event.date),
+            Operation::Update => Event::update_at(event.data, event.date),
+            Operation::Delete => Event::delete_at(event.data, event.date),
+        }
+    }
+}
+
+impl<'a, T> TsProjector<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    /// Returns a shared reference to the event log
+    pub fn get_event_log(&self) -> &Vec<TsEvent<'a, T>> {
+        &self.event_log
+    }
+
+    /// Returns a shared reference to the projection
+    pub fn get_projection(&self) -> &Vec<Cow<'a, T>> {
+        &self.projection
+    }
+
+    /// Converts a projector into the libocc-ts shape, flattening its segments
+    pub fn from_projector(projector: &Projector<'a, T>) -> Self {
+        Self {
+            event_log: projector
+                .iter()
+                .flat_map(|s| s.get_events())
+                .cloned()
+                .map(TsEvent::from)
+                .collect(),
+            projection: projector.get_projection().clone(),
+        }
+    }
+}
+
+impl<'a, T> TsProjector<'a, T>
+where
+    T: Clone + PartialEq + Serialize,
+{
+    /// Replays the event log into a new projector
+    ///
+    /// Fails if the event log is invalid, or if the replayed projection
+    /// doesn't match the projection of libocc-ts. The projections are compared
+    /// using their canonical encodings (see [`libocc::format`]), as the entities
+    /// may only compare their identities using `PartialEq`.
+    ///
+    /// [`libocc::format`]: ../format/index.html
+    pub fn into_projector(self) -> Result<Projector<'a, T>> {
+        let mut projector = Projector::new();
+        projector.merge(self.event_log.into_iter().map(Event::from).collect())?;
+
+        if format::to_vec(projector.get_projection())? != format::to_vec(&self.projection)? {
+            bail!("The replayed projection doesn't match the one of libocc-ts")
+        }
+
+        Ok(projector)
+    }
+}
@@ -16,5 +16,11 @@ or use the [`Projector`] struct to access the lower-level operations of event pr
 #[cfg(test)]
 mod test;
 
+pub mod crdt;
 pub mod events;
+pub mod format;
+pub mod interop;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod sync;
 pub mod tree;
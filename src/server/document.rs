@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/**
+A JSON document hosted by the sync server.
+
+Documents are identified by their `uuid` field, just like the entities of
+the [`Projector`]s they're pushed from. Documents lacking such a field are
+identified by their entire content, so updating them would create a different
+document instead. The server rejects them.
+
+[`Projector`]: ../events/struct.Projector.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct Document(Value);
+
+impl Document {
+    /// The name of the field identifying a document
+    pub const KEY: &'static str = "uuid";
+
+    /// Returns the value identifying this document
+    pub fn get_key(&self) -> Option<&Value> {
+        self.0.get(Self::KEY)
+    }
+
+    /// Returns a reference to the content of this document
+    pub fn get_value(&self) -> &Value {
+        &self.0
+    }
+
+    /// Consumes the document, returning its content
+    pub fn take(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Value> for Document {
+    fn from(value: Value) -> Self {
+        Self(value)
+    }
+}
+
+// Implemented manually to distinguish between documents based on their keys
+impl PartialEq for Document {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.get_key(), other.get_key()) {
+            (Some(key), Some(other_key)) => key == other_key,
+            (None, None) => self.0 == other.0,
+            _ => false,
+        }
+    }
+}
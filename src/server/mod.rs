@@ -0,0 +1,491 @@
+/*!
+A reference sync server hosting named projectors over a RESTful HTTP API.
+
+Every collection is a [`Projector`] of JSON [`Document`]s, which is created
+as soon as the first events are pushed to it. All request and response
+bodies are JSON, and all timestamps are formatted according to RFC 3339.
+Events of documents without a `uuid` field are rejected.
+
+| Method | Path                                  | Description                                                  |
+| ------ | ------------------------------------- | ------------------------------------------------------------ |
+| `GET`  | `/collections`                        | Lists the names of all collections                           |
+| `POST` | `/collections/{name}/events`          | Merges a list of (possibly out-of-order) events              |
+| `GET`  | `/collections/{name}/events?from=`    | Lists all events at or after `from` (or all events)          |
+| `GET`  | `/collections/{name}/arrivals?from=`  | Lists all events in order of arrival, from position `from`   |
+| `GET`  | `/collections/{name}/projection?at=`  | Returns the projection at `at` (or the current projection)   |
+| `GET`  | `/collections/{name}/snapshots`       | Lists the snapshots of all segments                          |
+| `POST` | `/collections/{name}/snapshots`       | Makes a new snapshot                                         |
+| `GET`  | `/collections/{name}/live?from=`      | Opens a WebSocket for live replication, from position `from` |
+
+The live replication WebSocket exchanges [`LiveMessage`]s as JSON text messages.
+After connecting, the server sends all events which arrived at or after position
+`from` (or all events), followed by all events arriving from then on, along with
+the position after them. Clients may push events by sending them to the server,
+which replies with an error message if merging them fails. Reconnecting clients
+resume by passing the position after the latest event merged as `from`, like
+pulling arrivals; duplicate events are merged idempotently.
+
+The arrivals of a collection list its events in the order they were merged,
+regardless of their timestamps. Clients resuming from the position returned
+by their previous request (see [`Arrivals`]) thereby also receive events
+created offline by other clients, which `events?from=` would miss.
+
+[`Projector`]: ../events/struct.Projector.html
+[`Document`]: struct.Document.html
+[`Arrivals`]: ../sync/struct.Arrivals.html
+[`LiveMessage`]: ../sync/enum.LiveMessage.html
+*/
+
+mod document;
+
+pub use document::Document;
+
+use crate::{
+    events::{Event, Projector, Timestamp},
+    sync::{merge_arrivals, Arrivals, LiveMessage, Position},
+};
+use anyhow::{bail, Result};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap, future::Future, sync::Arc};
+use tokio::{
+    net::TcpListener,
+    sync::{
+        broadcast::{self, error::RecvError},
+        RwLock,
+    },
+};
+
+/// A collection of documents hosted by the server
+pub type Collection = Projector<'static, Document>;
+
+/// The number of pushes buffered for slow live replication clients
+const LIVE_CAPACITY: usize = 1024;
+
+/// The state of the sync server, holding all collections
+#[derive(Debug, Default)]
+pub struct Server {
+    collections: RwLock<HashMap<String, Hosted>>,
+
+    /// The channels broadcasting the events arriving at the collections
+    channels: std::sync::Mutex<HashMap<String, broadcast::Sender<Arrivals<Document>>>>,
+}
+
+/// A collection hosted by the server, along with the order its events arrived in
+#[derive(Debug, Default)]
+struct Hosted {
+    collection: Collection,
+
+    /// The events merged into the collection, in the order they arrived
+    arrivals: Vec<Event<'static, Document>>,
+}
+
+/// The snapshot of a segment, as returned by the server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotInfo {
+    /// The earliest data captured by the segment
+    pub timestamp: Timestamp,
+
+    /// The latest projection from the segment
+    pub snapshot: Vec<Document>,
+}
+
+/// The query of a request for events
+#[derive(Deserialize, Debug)]
+struct EventsQuery {
+    from: Option<Timestamp>,
+}
+
+/// The query of a request for arrivals (or live replication)
+#[derive(Deserialize, Debug)]
+struct ArrivalsQuery {
+    #[serde(default)]
+    from: Position,
+}
+
+/// The query of a request for a projection
+#[derive(Deserialize, Debug)]
+struct ProjectionQuery {
+    at: Option<Timestamp>,
+}
+
+/// An error returned by the server
+#[derive(Serialize, Debug)]
+struct ApiError {
+    #[serde(skip)]
+    status: StatusCode,
+    error: String,
+}
+
+impl Server {
+    /// Creates a new server without any collections
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the names of all collections
+    pub async fn get_collections(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.collections.read().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Merges events into a collection, creating it if it doesn't exist yet
+    ///
+    /// Fails if any of the documents lacks a key (see [`Document`]).
+    ///
+    /// [`Document`]: struct.Document.html
+    pub async fn push(&self, name: &str, events: Vec<Event<'static, Document>>) -> Result<()> {
+        // Documents without keys couldn't be told apart from their updates
+        if events.iter().any(|e| e.get_key().is_none()) {
+            bail!("Cannot merge documents without a {:?} field", Document::KEY)
+        }
+
+        let mut collections = self.collections.write().await;
+
+        // Don't create collections for failed merges
+        let mut created = Hosted::default();
+        let is_new = !collections.contains_key(name);
+        let hosted = collections.get_mut(name).unwrap_or(&mut created);
+
+        let start = hosted.arrivals.len();
+        merge_arrivals(&mut hosted.collection, &mut hosted.arrivals, events)?;
+
+        // Broadcast the new arrivals to the live replication clients (while still holding the lock)
+        // Sending only fails if there are no clients at the moment
+        if hosted.arrivals.len() > start {
+            let _ = self.channel(name).send(Arrivals {
+                events: hosted.arrivals[start..].to_vec(),
+                next: hosted.arrivals.len() as Position,
+            });
+        }
+
+        if is_new {
+            collections.insert(name.to_owned(), created);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the channel broadcasting the events arriving at a collection
+    fn channel(&self, name: &str) -> broadcast::Sender<Arrivals<Document>> {
+        // Unwraps safely because the lock is never held while panicking
+        self.channels
+            .lock()
+            .unwrap()
+            .entry(name.to_owned())
+            .or_insert_with(|| broadcast::channel(LIVE_CAPACITY).0)
+            .clone()
+    }
+
+    /// Replicates a collection over a WebSocket until either side closes it
+    async fn replicate(self: Arc<Self>, name: String, from: Position, mut socket: WebSocket) {
+        // Subscribe to new events and read the history at once, so no events are missed
+        let (history, mut pushes) = {
+            let collections = self.collections.read().await;
+            let pushes = self.channel(&name).subscribe();
+
+            let history = match collections.get(&name) {
+                Some(Hosted { arrivals, .. }) => Arrivals {
+                    events: arrivals.iter().skip(from as usize).cloned().collect(),
+                    next: arrivals.len() as Position,
+                },
+                None => Arrivals {
+                    events: vec![],
+                    next: 0,
+                },
+            };
+
+            (history, pushes)
+        };
+
+        // Send the history
+        if Self::send(&mut socket, LiveMessage::Arrivals(history))
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                // Merge the events pushed by the client
+                message = socket.recv() => {
+                    let reply = match message {
+                        Some(Ok(Message::Text(text))) => {
+                            match serde_json::from_str::<LiveMessage<Document>>(&text) {
+                                Ok(LiveMessage::Events(events))
+                                | Ok(LiveMessage::Arrivals(Arrivals { events, .. })) => {
+                                    self.push(&name, events).await.err().map(|e| e.to_string())
+                                }
+                                Ok(LiveMessage::Error(_)) => None,
+                                Err(error) => Some(error.to_string()),
+                            }
+                        }
+                        // Ignore pings, pongs and binary messages
+                        Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) | Some(Ok(Message::Binary(_))) => None,
+                        // The client closed the connection
+                        _ => return,
+                    };
+
+                    if let Some(error) = reply {
+                        if Self::send(&mut socket, LiveMessage::Error(error)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+
+                // Forward the events arriving at the collection
+                arrivals = pushes.recv() => match arrivals {
+                    Ok(arrivals) => {
+                        if Self::send(&mut socket, LiveMessage::Arrivals(arrivals)).await.is_err() {
+                            return;
+                        }
+                    }
+                    // Close the connection of lagging clients, so they resume after reconnecting
+                    Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return,
+                },
+            }
+        }
+    }
+
+    /// Sends a message over a WebSocket
+    async fn send(socket: &mut WebSocket, message: LiveMessage<Document>) -> Result<()> {
+        let text = serde_json::to_string(&message)?;
+        socket.send(Message::Text(text.into())).await?;
+        Ok(())
+    }
+
+    /// Returns all events of a collection at or after a given timestamp (or all of them)
+    pub async fn get_events_from(
+        &self,
+        name: &str,
+        starting_date: Option<&Timestamp>,
+    ) -> Option<Vec<Event<'static, Document>>> {
+        let collections = self.collections.read().await;
+        let collection = &collections.get(name)?.collection;
+
+        let events = match starting_date {
+            Some(starting_date) => collection.get_events_from(starting_date),
+            None => collection.iter().flat_map(|s| s.get_events()).collect(),
+        };
+
+        Some(events.into_iter().cloned().collect())
+    }
+
+    /// Returns all events of a collection which arrived at or after a given position
+    pub async fn get_arrivals_from(
+        &self,
+        name: &str,
+        from: Position,
+    ) -> Option<Arrivals<Document>> {
+        let collections = self.collections.read().await;
+        let arrivals = &collections.get(name)?.arrivals;
+
+        Some(Arrivals {
+            events: arrivals.iter().skip(from as usize).cloned().collect(),
+            next: arrivals.len() as Position,
+        })
+    }
+
+    /// Returns the projection of a collection at a given timestamp (or the current one)
+    pub async fn project_at(
+        &self,
+        name: &str,
+        timestamp: Option<&Timestamp>,
+    ) -> Option<Vec<Document>> {
+        let collections = self.collections.read().await;
+        let collection = &collections.get(name)?.collection;
+
+        let projection = match timestamp {
+            // Nothing existed before the collection
+            Some(timestamp) => collection.project_at(timestamp).unwrap_or_default(),
+            None => collection.get_projection().clone(),
+        };
+
+        Some(projection.into_iter().map(Cow::into_owned).collect())
+    }
+
+    /// Returns the snapshots of all segments of a collection
+    pub async fn get_snapshots(&self, name: &str) -> Option<Vec<SnapshotInfo>> {
+        let collections = self.collections.read().await;
+        let collection = &collections.get(name)?.collection;
+
+        Some(
+            collection
+                .iter()
+                .map(|segment| SnapshotInfo {
+                    timestamp: *segment.get_time(),
+                    snapshot: segment
+                        .get_projection()
+                        .iter()
+                        .map(|d| d.clone().into_owned())
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Makes a new snapshot of a collection
+    pub async fn make_snapshot(&self, name: &str) -> Option<()> {
+        self.collections
+            .write()
+            .await
+            .get_mut(name)?
+            .collection
+            .make_snapshot();
+        Some(())
+    }
+
+    /// Returns the router handling the requests to this server
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/collections", get(list_collections))
+            .route(
+                "/collections/{name}/events",
+                get(get_events).post(push_events),
+            )
+            .route("/collections/{name}/arrivals", get(get_arrivals))
+            .route("/collections/{name}/projection", get(get_projection))
+            .route(
+                "/collections/{name}/snapshots",
+                get(get_snapshots).post(make_snapshot),
+            )
+            .route("/collections/{name}/live", get(replicate))
+            .with_state(self)
+    }
+
+    /// Serves requests using a given listener until the shutdown signal completes
+    pub async fn serve<F>(self: Arc<Self>, listener: TcpListener, shutdown: F) -> Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        axum::serve(listener, self.router())
+            .with_graceful_shutdown(shutdown)
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl ApiError {
+    /// Constructs the error of a request for an unknown collection
+    fn not_found(name: &str) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            error: format!("Cannot find the collection \"{}\"", name),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Handles `GET /collections`
+async fn list_collections(State(server): State<Arc<Server>>) -> Json<Vec<String>> {
+    Json(server.get_collections().await)
+}
+
+/// Handles `POST /collections/{name}/events`
+async fn push_events(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+    Json(events): Json<Vec<Event<'static, Document>>>,
+) -> Result<StatusCode, ApiError> {
+    server.push(&name, events).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handles `GET /collections/{name}/events`
+async fn get_events(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Json<Vec<Event<'static, Document>>>, ApiError> {
+    server
+        .get_events_from(&name, query.from.as_ref())
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(&name))
+}
+
+/// Handles `GET /collections/{name}/arrivals`
+async fn get_arrivals(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+    Query(query): Query<ArrivalsQuery>,
+) -> Result<Json<Arrivals<Document>>, ApiError> {
+    server
+        .get_arrivals_from(&name, query.from)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(&name))
+}
+
+/// Handles `GET /collections/{name}/projection`
+async fn get_projection(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+    Query(query): Query<ProjectionQuery>,
+) -> Result<Json<Vec<Document>>, ApiError> {
+    server
+        .project_at(&name, query.at.as_ref())
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(&name))
+}
+
+/// Handles `GET /collections/{name}/snapshots`
+async fn get_snapshots(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<SnapshotInfo>>, ApiError> {
+    server
+        .get_snapshots(&name)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(&name))
+}
+
+/// Handles `POST /collections/{name}/snapshots`
+async fn make_snapshot(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    server
+        .make_snapshot(&name)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .ok_or_else(|| ApiError::not_found(&name))
+}
+
+/// Handles `GET /collections/{name}/live`
+async fn replicate(
+    State(server): State<Arc<Server>>,
+    Path(name): Path<String>,
+    Query(query): Query<ArrivalsQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| server.replicate(name, query.from, socket))
+}
@@ -0,0 +1,74 @@
+use crate::{
+    events::Event,
+    sync::{Arrivals, Position, Transport},
+};
+use anyhow::Result;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{de::DeserializeOwned, Serialize};
+
+/**
+A [`Transport`] connecting to a collection of the sync server over HTTP.
+
+See the [`server`] module for the API used by this transport.
+
+[`Transport`]: trait.Transport.html
+[`server`]: ../server/index.html
+*/
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    /// The URL of the collection
+    url: String,
+
+    /// The HTTP client used for all requests
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    /// Creates a new transport connecting to a collection of the server at a given base URL
+    pub fn new(base_url: &str, collection: &str) -> Self {
+        Self {
+            url: collection_url(base_url, collection),
+            agent: ureq::Agent::new(),
+        }
+    }
+}
+
+/// Returns the URL of a collection of the server at a given base URL
+///
+/// The name of the collection is percent-encoded, so it's always a single path segment.
+pub(super) fn collection_url(base_url: &str, collection: &str) -> String {
+    format!(
+        "{}/collections/{}",
+        base_url.trim_end_matches('/'),
+        utf8_percent_encode(collection, NON_ALPHANUMERIC)
+    )
+}
+
+impl<T> Transport<T> for HttpTransport
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned + 'static,
+{
+    fn push(&mut self, events: &[&Event<'_, T>]) -> Result<()> {
+        self.agent
+            .post(&format!("{}/events", self.url))
+            .send_json(events)?;
+        Ok(())
+    }
+
+    fn pull(&mut self, from: Position) -> Result<Arrivals<T>> {
+        let request = self
+            .agent
+            .get(&format!("{}/arrivals", self.url))
+            .query("from", &from.to_string());
+
+        match request.call() {
+            Ok(response) => Ok(response.into_json()?),
+            // The collection doesn't exist on the server yet
+            Err(ureq::Error::Status(404, _)) => Ok(Arrivals {
+                events: vec![],
+                next: 0,
+            }),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
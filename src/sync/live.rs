@@ -0,0 +1,355 @@
+use crate::{
+    events::{Change, Event, Projector},
+    sync::{http::collection_url, LiveMessage, Position},
+};
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::ErrorKind,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+/// How long the connection thread waits for incoming messages before sending outgoing ones
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The initial delay before reconnecting
+const MIN_BACKOFF: Duration = Duration::from_millis(50);
+
+/// The maximum delay before reconnecting
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/**
+A client replicating a collection of the sync server over a WebSocket.
+
+A background thread maintains the connection, reconnecting whenever it's lost.
+After reconnecting, it resumes from the arrival position after the latest event
+merged successfully (rather than the latest event received), like pulling the
+arrivals of the server. It thereby also receives events created offline by other
+clients, even if they predate the events merged before.
+Events sent while disconnected are buffered and sent after reconnecting.
+
+Received events are merged into a projector using [`apply`], while local
+events are sent using [`send`] or [`forward`] (e.g. forwarding the changes
+of a [`Projector::subscribe`] receiver). Received events which can't be merged
+are kept, and merged again along with the events received later.
+
+[`apply`]: #method.apply
+[`send`]: #method.send
+[`forward`]: #method.forward
+[`Projector::subscribe`]: ../events/struct.Projector.html#method.subscribe
+*/
+#[derive(Debug)]
+pub struct LiveClient<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// The messages received from the server
+    incoming: Receiver<LiveMessage<T>>,
+
+    /// The events to be sent to the server
+    outgoing: Sender<Vec<Event<'static, T>>>,
+
+    /// The events received, but not merged yet
+    unmerged: Vec<Event<'static, T>>,
+
+    /// The arrival position after the latest event received
+    received: Position,
+
+    /// The errors reported by the server
+    errors: Vec<String>,
+
+    /// Stops the connection thread when dropped
+    stop: Option<Sender<()>>,
+
+    /// Whether the connection thread is currently connected
+    connected: Arc<AtomicBool>,
+
+    /// The arrival position after the latest event merged (shared with the connection thread)
+    acknowledged: Arc<Mutex<Position>>,
+
+    /// The connection thread
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The state of the connection thread of a [`LiveClient`]
+///
+/// [`LiveClient`]: struct.LiveClient.html
+struct Connection<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// The URL of the live replication endpoint (without the `from` parameter)
+    url: String,
+
+    /// The arrival position after the latest event merged by the client
+    acknowledged: Arc<Mutex<Position>>,
+
+    /// The events which haven't been sent yet
+    pending: Vec<Vec<Event<'static, T>>>,
+
+    incoming: Sender<LiveMessage<T>>,
+    outgoing: Receiver<Vec<Event<'static, T>>>,
+    stop: Receiver<()>,
+    connected: Arc<AtomicBool>,
+}
+
+impl<T> LiveClient<T>
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Connects to a collection of the server at a given base URL, receiving
+    /// all events which arrived at or after a given position (`0` for all of them)
+    ///
+    /// The connection is established in the background; failing to connect
+    /// isn't an error, as the client keeps trying to reconnect.
+    pub fn connect(base_url: &str, collection: &str, from: Position) -> Self {
+        let (incoming_sender, incoming) = mpsc::channel();
+        let (outgoing, outgoing_receiver) = mpsc::channel();
+        let (stop, stop_receiver) = mpsc::channel();
+        let connected = Arc::new(AtomicBool::new(false));
+        let acknowledged = Arc::new(Mutex::new(from));
+
+        let connection = Connection {
+            url: format!(
+                "{}/live",
+                collection_url(&base_url.replacen("http", "ws", 1), collection)
+            ),
+            acknowledged: acknowledged.clone(),
+            pending: vec![],
+            incoming: incoming_sender,
+            outgoing: outgoing_receiver,
+            stop: stop_receiver,
+            connected: connected.clone(),
+        };
+
+        Self {
+            incoming,
+            outgoing,
+            unmerged: vec![],
+            received: from,
+            errors: vec![],
+            stop: Some(stop),
+            connected,
+            acknowledged,
+            thread: Some(thread::spawn(move || connection.run())),
+        }
+    }
+
+    /// Returns the arrival position after the latest event merged
+    ///
+    /// Pass it to [`connect`](#method.connect) to resume replicating later on.
+    pub fn get_acknowledged(&self) -> Position {
+        self.acknowledged
+            .lock()
+            .map_or(0, |acknowledged| *acknowledged)
+    }
+
+    /// Checks if the client is currently connected to the server
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Sends events to the server, which merges them
+    pub fn send(&self, events: Vec<Event<'static, T>>) -> Result<()> {
+        self.outgoing
+            .send(events)
+            .map_err(|_| anyhow!("Cannot send events after the connection thread stopped"))
+    }
+
+    /// Sends the events of all pending changes of a subscription to the server
+    pub fn forward(&self, changes: &Receiver<Change<'_, T>>) -> Result<()> {
+        let events: Vec<_> = changes
+            .try_iter()
+            .map(|change| change.take().0.into_owned())
+            .collect();
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        self.send(events)
+    }
+
+    /// Merges all events received so far into a projector,
+    /// returning the resulting changes of the current projection
+    ///
+    /// If merging fails, the events are kept and merged again on the next call.
+    pub fn apply<'a>(&mut self, projector: &mut Projector<'a, T>) -> Result<Vec<Change<'a, T>>> {
+        let messages: Vec<_> = self.incoming.try_iter().collect();
+        self.merge(projector, messages)
+    }
+
+    /// Waits up to a given duration for events to be received, and merges them
+    /// (along with all other events received so far) into a projector
+    pub fn apply_timeout<'a>(
+        &mut self,
+        projector: &mut Projector<'a, T>,
+        timeout: Duration,
+    ) -> Result<Vec<Change<'a, T>>> {
+        let mut messages = match self.incoming.recv_timeout(timeout) {
+            Ok(message) => vec![message],
+            Err(RecvTimeoutError::Timeout) => vec![],
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow!(
+                    "Cannot receive events after the connection thread stopped"
+                ))
+            }
+        };
+        messages.extend(self.incoming.try_iter());
+
+        self.merge(projector, messages)
+    }
+
+    /// Takes the errors reported by the server so far
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Merges the events of some messages (and the ones not merged before) into
+    /// a projector, remembering the errors and keeping the events if merging fails
+    fn merge<'a>(
+        &mut self,
+        projector: &mut Projector<'a, T>,
+        messages: Vec<LiveMessage<T>>,
+    ) -> Result<Vec<Change<'a, T>>> {
+        for message in messages {
+            match message {
+                LiveMessage::Events(received) => self.unmerged.extend(received),
+                LiveMessage::Arrivals(arrivals) => {
+                    self.unmerged.extend(arrivals.events);
+                    self.received = self.received.max(arrivals.next);
+                }
+                LiveMessage::Error(error) => self.errors.push(error),
+            }
+        }
+
+        if self.unmerged.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let changes = projector.merge(self.unmerged.clone())?;
+
+        // Acknowledge the merged events, so reconnecting resumes after them
+        if let Ok(mut acknowledged) = self.acknowledged.lock() {
+            *acknowledged = (*acknowledged).max(self.received);
+        }
+        self.unmerged.clear();
+
+        Ok(changes)
+    }
+}
+
+impl<T> Drop for LiveClient<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    fn drop(&mut self) {
+        // Stop the connection thread (even while waiting to reconnect) and wait for it
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<T> Connection<T>
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned + 'static,
+{
+    /// Checks if the client is still running (i.e. it wasn't dropped)
+    fn is_running(&self) -> bool {
+        matches!(self.stop.try_recv(), Err(TryRecvError::Empty))
+    }
+
+    /// Keeps (re)connecting to the server until the client is dropped
+    fn run(mut self) {
+        let mut backoff = MIN_BACKOFF;
+
+        while self.is_running() {
+            if let Ok(mut socket) = self.connect() {
+                self.connected.store(true, Ordering::SeqCst);
+                backoff = MIN_BACKOFF;
+
+                let result = self.replicate(&mut socket);
+
+                self.connected.store(false, Ordering::SeqCst);
+                let _ = socket.close(None);
+
+                // The client was dropped
+                if result.is_ok() {
+                    return;
+                }
+            }
+
+            // Wait before reconnecting, increasing the delay every time
+            // Stops waiting as soon as the client is dropped
+            if let Err(RecvTimeoutError::Disconnected) = self.stop.recv_timeout(backoff) {
+                return;
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Connects to the server, resuming from the latest event merged by the client
+    fn connect(&self) -> Result<WebSocket<MaybeTlsStream<TcpStream>>> {
+        let acknowledged = *self
+            .acknowledged
+            .lock()
+            .map_err(|_| anyhow!("Cannot read the position of the latest event merged"))?;
+        let url = format!("{}?from={}", self.url, acknowledged);
+
+        let (mut socket, _) = tungstenite::connect(url)?;
+
+        // Poll for incoming messages, so outgoing ones can be sent in between
+        if let MaybeTlsStream::Plain(stream) = socket.get_mut() {
+            stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        }
+
+        Ok(socket)
+    }
+
+    /// Exchanges messages with the server until the connection is lost (`Err`)
+    /// or the client is dropped (`Ok`)
+    fn replicate(&mut self, socket: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> Result<()> {
+        while self.is_running() {
+            // Send all pending events, keeping them if sending fails
+            self.pending.extend(self.outgoing.try_iter());
+            while let Some(events) = self.pending.first() {
+                let text = serde_json::to_string(&LiveMessage::Events(events.clone()))?;
+                socket.send(Message::text(text))?;
+                self.pending.remove(0);
+            }
+
+            // Receive the next message (if any)
+            let text = match socket.read() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return Err(anyhow!("The server closed the connection")),
+                Ok(_) => continue,
+                Err(tungstenite::Error::Io(error))
+                    if error.kind() == ErrorKind::WouldBlock
+                        || error.kind() == ErrorKind::TimedOut =>
+                {
+                    continue
+                }
+                Err(error) => return Err(error.into()),
+            };
+
+            let message: LiveMessage<T> = serde_json::from_str(&text)?;
+
+            // Stop if the client was dropped
+            if self.incoming.send(message).is_err() {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+}
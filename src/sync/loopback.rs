@@ -0,0 +1,77 @@
+use crate::{
+    events::{Event, Projector},
+    sync::{merge_arrivals, Arrivals, Position, Transport},
+};
+use anyhow::{anyhow, Result};
+use std::sync::{Arc, Mutex};
+
+/**
+An in-memory [`Transport`] connecting to a shared projector.
+
+All clones of a loopback transport share the same remote projector,
+which makes it useful for testing the sync logic without any wire.
+Only events pushed using a transport are pulled, as the transport
+remembers the order they arrived in.
+
+[`Transport`]: trait.Transport.html
+*/
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackTransport<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    remote: Arc<Mutex<Projector<'static, T>>>,
+
+    /// The events pushed to the remote projector, in the order they arrived
+    arrivals: Arc<Mutex<Vec<Event<'static, T>>>>,
+}
+
+impl<T> LoopbackTransport<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// Creates a new transport connected to a new, empty projector
+    pub fn new() -> Self {
+        Self {
+            remote: Arc::new(Mutex::new(Projector::new())),
+            arrivals: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Returns the remote projector shared by all clones of this transport
+    pub fn get_remote(&self) -> &Arc<Mutex<Projector<'static, T>>> {
+        &self.remote
+    }
+}
+
+impl<T> Transport<T> for LoopbackTransport<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    fn push(&mut self, events: &[&Event<'_, T>]) -> Result<()> {
+        let events = events.iter().map(|e| (*e).clone().into_owned()).collect();
+
+        let mut remote = self
+            .remote
+            .lock()
+            .map_err(|_| anyhow!("Cannot access the poisoned remote projector"))?;
+        let mut arrivals = self
+            .arrivals
+            .lock()
+            .map_err(|_| anyhow!("Cannot access the poisoned remote projector"))?;
+
+        merge_arrivals(&mut remote, &mut arrivals, events)
+    }
+
+    fn pull(&mut self, from: Position) -> Result<Arrivals<T>> {
+        let arrivals = self
+            .arrivals
+            .lock()
+            .map_err(|_| anyhow!("Cannot access the poisoned remote projector"))?;
+
+        Ok(Arrivals {
+            events: arrivals.iter().skip(from as usize).cloned().collect(),
+            next: arrivals.len() as Position,
+        })
+    }
+}
@@ -0,0 +1,288 @@
+/*!
+This module synchronizes projectors with remote replicas.
+
+A [`SyncClient`] tracks the last-synced frontier of a [`Projector`], pushes
+local events and pulls (and merges) remote ones. Remote events are pulled
+in the order they arrived at the remote replica (rather than by their
+timestamps), so events created offline by other clients are pulled as
+well. The wire is abstracted by the [`Transport`] trait, so the sync logic
+is independent of it.
+
+For push-based replication, a [`LiveClient`] streams events over a WebSocket
+(requires the `client` feature).
+
+[`SyncClient`]: struct.SyncClient.html
+[`Projector`]: ../events/struct.Projector.html
+[`Transport`]: trait.Transport.html
+[`LiveClient`]: struct.LiveClient.html
+*/
+
+#[cfg(feature = "client")]
+mod http;
+#[cfg(feature = "client")]
+mod live;
+mod loopback;
+
+#[cfg(feature = "client")]
+pub use http::HttpTransport;
+#[cfg(feature = "client")]
+pub use live::LiveClient;
+pub use loopback::LoopbackTransport;
+
+use crate::events::{Change, Event, Projector, Segment, Timestamp};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The connection to a remote replica of a projector
+pub trait Transport<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// Pushes events to the remote replica, which merges them
+    fn push(&mut self, events: &[&Event<'_, T>]) -> Result<()>;
+
+    /// Pulls all remote events which arrived at the remote replica at or after a given position
+    fn pull(&mut self, from: Position) -> Result<Arrivals<T>>;
+}
+
+/// The position of an event in the order events arrived at a replica
+pub type Position = u64;
+
+/// The events which arrived at a replica since a given position
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Arrivals<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// The events in the order they arrived at the replica
+    pub events: Vec<Event<'static, T>>,
+
+    /// The position after the latest event
+    pub next: Position,
+}
+
+/// A message sent over a live replication channel (e.g. a WebSocket)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveMessage<T>
+where
+    T: Clone + PartialEq + 'static,
+{
+    /// Events to be merged by the receiver
+    Events(Vec<Event<'static, T>>),
+
+    /// Events which arrived at the sender, to be merged by the receiver
+    Arrivals(Arrivals<T>),
+
+    /// An error caused by the previous message of the receiver
+    Error(String),
+}
+
+/// The last-synced frontier of a projector
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Frontier {
+    /// The timestamp of the latest event pushed to the remote replica (the high-water mark)
+    pub pushed: Option<Timestamp>,
+
+    /// The number of events at or before the mark of each segment (by its timestamp)
+    /// as of the last sync
+    ///
+    /// Merging may insert local events before the mark, which changes the number
+    /// of events of their segments. Segments which changed (or are unknown) are
+    /// pushed again, rather than only the events after the mark.
+    pub synced: BTreeMap<Timestamp, u64>,
+
+    /// The position after the latest event pulled from the remote replica
+    pub pulled: Position,
+}
+
+/**
+Synchronizes a projector with a remote replica using a [`Transport`].
+
+Syncing pushes all local events which weren't pushed before and merges all
+remote events which arrived at the remote replica since the last pull, both
+regardless of their timestamps. As both replicas merge idempotently, events
+may be sent more than once (e.g. when pushing them fails halfway, or when
+local events were merged before the latest event pushed).
+
+[`Transport`]: trait.Transport.html
+*/
+#[derive(Debug, Clone)]
+pub struct SyncClient<X> {
+    /// The connection to the remote replica
+    transport: X,
+
+    /// The last-synced frontier
+    frontier: Frontier,
+}
+
+/// Merges events into a projector, appending the ones it didn't contain before
+/// (in the order they arrived) to a log of arrivals
+pub(crate) fn merge_arrivals<'a, T>(
+    projector: &mut Projector<'a, T>,
+    arrivals: &mut Vec<Event<'a, T>>,
+    events: Vec<Event<'a, T>>,
+) -> Result<()>
+where
+    T: Clone + PartialEq,
+{
+    // Find the new events before merging them (ignoring duplicates within the batch)
+    let mut arrived: Vec<Event<'a, T>> = vec![];
+    for event in &events {
+        if !projector.contains(event) && !arrived.contains(event) {
+            arrived.push(event.clone());
+        }
+    }
+
+    // Only remember them if merging succeeds
+    projector.merge(events)?;
+    arrivals.extend(arrived);
+
+    Ok(())
+}
+
+impl<X> SyncClient<X> {
+    /// Creates a new client which hasn't synced anything yet
+    pub fn new(transport: X) -> Self {
+        Self::with_frontier(transport, Frontier::default())
+    }
+
+    /// Creates a new client resuming from a previously synced frontier
+    pub fn with_frontier(transport: X, frontier: Frontier) -> Self {
+        Self {
+            transport,
+            frontier,
+        }
+    }
+
+    /// Returns the last-synced frontier
+    pub fn get_frontier(&self) -> &Frontier {
+        &self.frontier
+    }
+
+    /// Returns a reference to the transport of this client
+    pub fn get_transport(&self) -> &X {
+        &self.transport
+    }
+
+    /// Pushes all local events which weren't pushed before
+    ///
+    /// Only the events after the latest one pushed are sent, unless events were
+    /// merged before it since the last sync: The events of their segments are
+    /// sent again then, as they can't be told apart from the ones pushed before.
+    pub fn push<'a, T>(&mut self, projector: &Projector<'a, T>) -> Result<()>
+    where
+        T: Clone + PartialEq + 'static,
+        X: Transport<T>,
+    {
+        // Find the local events which weren't pushed before (wherever they were inserted)
+        let mut events = vec![];
+        for segment in projector.iter() {
+            let synced = self.count_synced(segment);
+            if self.frontier.synced.get(segment.get_time()) != Some(&(synced as u64)) {
+                events.extend(&segment.get_events()[..synced]);
+            }
+            events.extend(&segment.get_events()[synced..]);
+        }
+
+        // Push the events (if any), moving the frontier afterwards
+        if !events.is_empty() {
+            self.transport.push(&events)?;
+        }
+        self.mark_synced(projector);
+
+        Ok(())
+    }
+
+    /// Counts the events of a segment at or before the latest event pushed
+    fn count_synced<T>(&self, segment: &Segment<'_, T>) -> usize
+    where
+        T: Clone + PartialEq,
+    {
+        match &self.frontier.pushed {
+            Some(pushed) => segment
+                .get_events()
+                .partition_point(|e| e.get_time() <= pushed),
+            None => 0,
+        }
+    }
+
+    /// Checks if all events of a projector were synced (i.e. there's nothing to push)
+    fn is_synced<T>(&self, projector: &Projector<'_, T>) -> bool
+    where
+        T: Clone + PartialEq,
+    {
+        projector.iter().all(|segment| {
+            let synced = self.count_synced(segment);
+            synced == segment.get_events().len()
+                && self.frontier.synced.get(segment.get_time()) == Some(&(synced as u64))
+        })
+    }
+
+    /// Moves the mark after all events of a projector, as they're known to the remote replica
+    fn mark_synced<T>(&mut self, projector: &Projector<'_, T>)
+    where
+        T: Clone + PartialEq,
+    {
+        if let Some(latest) = projector.iter().rev().find_map(|s| s.get_events().last()) {
+            self.frontier.pushed = Some(*latest.get_time());
+        }
+        self.frontier.synced = projector
+            .iter()
+            .map(|s| (*s.get_time(), s.get_events().len() as u64))
+            .collect();
+    }
+
+    /// Pulls and merges all remote events since the last pull,
+    /// returning the resulting changes of the current projection
+    pub fn pull<'a, T>(&mut self, projector: &mut Projector<'a, T>) -> Result<Vec<Change<'a, T>>>
+    where
+        T: Clone + PartialEq + 'static,
+        X: Transport<T>,
+    {
+        // Pull the remote events since the last pull
+        let arrivals = self.transport.pull(self.frontier.pulled)?;
+
+        // Nothing to do
+        if arrivals.events.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Merge the events, moving the frontier afterwards
+        // The pulled events needn't be pushed, unless there are local events to push anyway
+        let synced = self.is_synced(projector);
+        let changes = projector.merge(arrivals.events)?;
+        self.frontier.pulled = arrivals.next;
+        if synced {
+            self.mark_synced(projector);
+        }
+
+        Ok(changes)
+    }
+
+    /// Pulls and merges all remote events regardless of the frontier,
+    /// returning the resulting changes of the current projection
+    pub fn pull_all<'a, T>(
+        &mut self,
+        projector: &mut Projector<'a, T>,
+    ) -> Result<Vec<Change<'a, T>>>
+    where
+        T: Clone + PartialEq + 'static,
+        X: Transport<T>,
+    {
+        self.frontier.pulled = 0;
+        self.pull(projector)
+    }
+
+    /// Pushes all local events and pulls all remote ones,
+    /// returning the resulting changes of the current projection
+    pub fn sync<'a, T>(&mut self, projector: &mut Projector<'a, T>) -> Result<Vec<Change<'a, T>>>
+    where
+        T: Clone + PartialEq + 'static,
+        X: Transport<T>,
+    {
+        self.push(projector)?;
+        self.pull(projector)
+    }
+}
@@ -0,0 +1,103 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projector, Timestamp, MAX_STEPS};
+use chrono::{Duration, Utc};
+use std::{borrow::Cow, thread, time};
+
+/// Waits until a moment in time, so events may be pushed at that moment
+fn wait_until(timestamp: Timestamp) {
+    while Utc::now() < timestamp {
+        thread::sleep(time::Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn test_temporal_aggregates() {
+    let mut books = Projector::<book::Book>::new();
+    let start = Utc::now();
+    let step = Duration::milliseconds(20);
+    let offset = Duration::milliseconds(5);
+
+    // Push the events a little after the start of every step
+    let at = |steps: i32| {
+        let timestamp = start + step * steps + offset;
+        wait_until(timestamp);
+        timestamp
+    };
+
+    // Create a book every step, update the first one twice and delete it again
+    let mut first = new_book(1);
+    let create = Event::create_at(Cow::Owned(first.clone()), at(0));
+    books.push(create).unwrap();
+    let create = Event::create_at(Cow::Owned(new_book(2)), at(1));
+    books.push(create).unwrap();
+    first.some_number = 10;
+    let update = Event::update_at(Cow::Owned(first.clone()), at(1));
+    books.push(update).unwrap();
+    books.make_snapshot();
+    let create = Event::create_at(Cow::Owned(new_book(3)), at(2));
+    books.push(create).unwrap();
+    first.some_number = 20;
+    let update = Event::update_at(Cow::Owned(first.clone()), at(2));
+    books.push(update).unwrap();
+    let delete = Event::delete_at(Cow::Owned(first.clone()), at(3));
+    books.push(delete).unwrap();
+
+    // Count the books existing at every step
+    let counts = books
+        .count_every(&start, &(start + step * 4), step)
+        .unwrap()
+        .unwrap();
+    let counts: Vec<usize> = counts.into_iter().map(|(_, count)| count).collect();
+    assert_eq!(counts, vec![0, 1, 2, 3, 2]);
+
+    // Samples agree with projections
+    let numbers = books
+        .sample_every(&start, &(start + step * 3), step, |projection| {
+            projection.iter().map(|b| b.some_number).sum::<usize>()
+        })
+        .unwrap()
+        .unwrap();
+    assert_eq!(numbers[2].0, start + step * 2);
+    assert_eq!(numbers[2].1, 12);
+    assert_eq!(numbers[3].1, 25);
+
+    // Count the updates of the first book in every window of two steps
+    let updates = books
+        .count_events_every(&start, &(start + step * 4), step * 2, |event| {
+            matches!(event, Event::Update(_)) && **event == first
+        })
+        .unwrap();
+    assert_eq!(updates, vec![(start, 1), (start + step * 2, 1)]);
+
+    // Aggregate arbitrary values
+    let created = books
+        .aggregate_every(
+            &start,
+            &(start + step * 3),
+            step,
+            |sum: &mut usize, event| {
+                if let Event::Create(_) = event {
+                    *sum += event.some_number;
+                }
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        created,
+        vec![(start, 1), (start + step, 2), (start + step * 2, 3)]
+    );
+
+    // Invalid steps or timestamps are rejected
+    assert!(books.count_every(&start, &start, Duration::zero()).is_err());
+    let past = start - Duration::hours(1);
+    assert!(books.count_every(&past, &start, step).unwrap().is_none());
+
+    // Too many steps are rejected before sampling or allocating any windows
+    let tiny = Duration::nanoseconds(1);
+    let end = start + tiny * MAX_STEPS;
+    assert!(books.count_every(&start, &end, tiny).is_ok());
+    assert!(books.count_every(&start, &(end + step), tiny).is_err());
+    assert!(books
+        .count_events_every(&past, &start, tiny, |_| true)
+        .is_err());
+}
@@ -17,3 +17,16 @@ impl PartialEq for Book {
         self.uuid == other.uuid
     }
 }
+
+/// Creates a new book with a random UUID
+pub(crate) fn new_book(some_number: usize) -> Book {
+    Book {
+        uuid: Uuid::new_v4(),
+        some_number,
+        author: Person {
+            uuid: Uuid::new_v4(),
+            first_name: String::from("Alex"),
+            last_name: String::from("Example"),
+        },
+    }
+}
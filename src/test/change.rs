@@ -0,0 +1,91 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projector};
+use chrono::{Duration, Utc};
+use std::borrow::Cow;
+
+#[test]
+fn test_subscribe() {
+    let mut my_book = new_book(42);
+
+    // Create a new projector of type `Book` and subscribe to it
+    let mut books = Projector::<book::Book>::new();
+    let changes = books.subscribe();
+
+    // Create and modify a book
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    my_book.some_number = 123;
+    books
+        .transaction(|tx| {
+            tx.update(Cow::Owned(my_book.clone()))
+                .delete(Cow::Owned(my_book.clone()));
+        })
+        .unwrap();
+
+    // Every event was reported along with the old and the new entity
+    let received: Vec<_> = changes.try_iter().collect();
+    assert_eq!(received.len(), 3);
+
+    assert!(matches!(received[0].get_event(), Event::Create(_)));
+    assert!(received[0].get_old().is_none());
+    assert_eq!(received[0].get_new().unwrap().some_number, 42);
+
+    assert!(matches!(received[1].get_event(), Event::Update(_)));
+    assert_eq!(received[1].get_old().unwrap().some_number, 42);
+    assert_eq!(received[1].get_new().unwrap().some_number, 123);
+
+    assert!(matches!(received[2].get_event(), Event::Delete(_)));
+    assert_eq!(received[2].get_old().unwrap().some_number, 123);
+    assert!(received[2].get_new().is_none());
+
+    // Failed events aren't reported
+    assert!(books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .is_err());
+    assert!(changes.try_recv().is_err());
+}
+
+#[test]
+fn test_merge_out_of_order() {
+    let mut my_book = new_book(42);
+    let start = Utc::now();
+
+    // Create a new projector of type `Book` containing a book
+    let mut books = Projector::<book::Book>::new();
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    books.make_snapshot();
+    let changes = books.subscribe();
+
+    // A remote replica created another book and modified ours earlier
+    let other_book = new_book(7);
+    my_book.some_number = 123;
+    let remote_events = vec![
+        Event::update_at(Cow::Owned(my_book.clone()), Utc::now()),
+        Event::create_at(Cow::Owned(other_book.clone()), start - Duration::seconds(1)),
+    ];
+
+    // Merge the remote events, reporting the diff of the projection
+    let diff = books.merge(remote_events.clone()).unwrap();
+    assert_eq!(diff.len(), 2);
+    assert!(diff[0].get_old().is_none());
+    assert_eq!(diff[0].get_new().unwrap().uuid, other_book.uuid);
+    assert_eq!(diff[1].get_old().unwrap().some_number, 42);
+    assert_eq!(diff[1].get_new().unwrap().some_number, 123);
+    assert_eq!(changes.try_iter().count(), 2);
+
+    // The history reflects the out-of-order events
+    assert_eq!(books.get_projection().len(), 2);
+    assert_eq!(books.project_at(&start).unwrap().len(), 1);
+
+    // Merging the same events again changes nothing
+    assert!(books.merge(remote_events).unwrap().is_empty());
+
+    // Merges leading to invalid histories are rejected entirely
+    assert!(books
+        .insert(Event::create_at(Cow::Owned(other_book), Utc::now()))
+        .is_err());
+    assert_eq!(books.get_projection().len(), 2);
+}
@@ -0,0 +1,184 @@
+use crate::{
+    crdt::{Crdt, LwwRegister, MvRegister, OrSet, PnCounter},
+    events::{Event, Projector},
+    format,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Note {
+    uuid: Uuid,
+    title: LwwRegister<String>,
+    likes: PnCounter,
+    tags: OrSet<String>,
+}
+
+impl PartialEq for Note {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+impl Crdt for Note {
+    fn merge(&mut self, other: &Self) {
+        self.title.merge(&other.title);
+        self.likes.merge(&other.likes);
+        self.tags.merge(&other.tags);
+    }
+}
+
+#[test]
+fn test_crdt_types() {
+    // Registers keep the latest (or all concurrent) values
+    let mut first = LwwRegister::new(1, "a");
+    let mut second = first.clone();
+    second.set(2, "b");
+    first.merge(&second);
+    assert_eq!(*first.get(), 2);
+
+    let mut first = MvRegister::new(1, "a");
+    let mut second = first.clone();
+    first.set(2, "a");
+    second.set(3, "b");
+    first.merge(&second);
+    second.merge(&first);
+    assert_eq!(first, second);
+    assert!(first.is_conflicting());
+    assert!(first.get().contains(&&2) && first.get().contains(&&3));
+    first.set(4, "a");
+    second.merge(&first);
+    assert_eq!(second.get(), vec![&4]);
+
+    // Counters add up concurrent increments and decrements
+    let mut first = PnCounter::new();
+    let mut second = PnCounter::new();
+    first.increment(3, "a");
+    second.increment(2, "b");
+    second.decrement(1, "b");
+    first.merge(&second);
+    first.merge(&second);
+    assert_eq!(first.get(), 4);
+
+    // Concurrent additions win over removals
+    let mut first = OrSet::new();
+    first.add("x", "a");
+    let mut second = first.clone();
+    second.remove(&"x");
+    first.add("x", "a");
+    first.add("y", "a");
+    second.merge(&first);
+    assert!(second.contains(&"x"));
+    assert_eq!(second.get(), vec![&"x", &"y"]);
+    first.remove(&"y");
+    second.merge(&first);
+    assert!(!second.contains(&"y"));
+
+    // Replicas holding the same additions are equal, regardless of their order
+    let mut first = OrSet::new();
+    first.add("x", "b");
+    first.add("y", "a");
+    let mut second = OrSet::new();
+    second.merge(&first);
+    assert_eq!(first, second);
+    assert_eq!(
+        format::to_vec(&first).unwrap(),
+        format::to_vec(&second).unwrap()
+    );
+}
+
+#[test]
+fn test_merge_crdt() {
+    let mut first = Projector::<Note>::new();
+    let mut second = Projector::<Note>::new();
+
+    // The first replica creates a note, which the second one receives
+    let mut note = Note {
+        uuid: Uuid::new_v4(),
+        title: LwwRegister::new(String::from("Draft"), "first"),
+        likes: PnCounter::new(),
+        tags: OrSet::new(),
+    };
+    let create = first.stamp(Event::create(Cow::Owned(note.clone())), "first");
+    let create_time = *create.get_time();
+    first.push(create.clone()).unwrap();
+    second.merge_crdt(vec![create], "second").unwrap();
+
+    // Both replicas update the note without knowing of each other
+    note.likes.increment(1, "first");
+    note.tags.add(String::from("rust"), "first");
+    let first_update = first.stamp(Event::update(Cow::Owned(note.clone())), "first");
+    first.push(first_update.clone()).unwrap();
+
+    let mut other = second.get_projection()[0].clone().into_owned();
+    other.title.set(String::from("Final"), "second");
+    other.likes.increment(2, "second");
+    other.tags.add(String::from("crdt"), "second");
+    let second_update = second.stamp(Event::update(Cow::Owned(other)), "second");
+    second.push(second_update.clone()).unwrap();
+
+    // Exchanging the updates merges the note field by field
+    let changes = first.merge_crdt(vec![second_update], "first").unwrap();
+    assert_eq!(changes.len(), 2);
+    let resolution = changes[1].get_event().clone();
+    second.merge_crdt(vec![first_update], "second").unwrap();
+    assert!(first.get_conflicts().is_empty());
+
+    let merged = &first.get_projection()[0];
+    assert_eq!(merged.title.get(), "Final");
+    assert_eq!(merged.likes.get(), 3);
+    assert_eq!(merged.tags.get().len(), 2);
+
+    // Both replicas converge, and their resolutions don't need to be resolved again
+    let changes = second.merge_crdt(vec![resolution], "second").unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(second.get_conflicts().is_empty());
+    assert_eq!(
+        format::to_vec(&second.get_projection()[0]).unwrap(),
+        format::to_vec(merged).unwrap()
+    );
+    assert_eq!(second.get_events_from(&create_time).len(), 5);
+}
+
+#[test]
+fn test_resolve_automatically() {
+    let mut first = Projector::<Note>::new();
+    let mut second = Projector::<Note>::new();
+    first.resolve_automatically("first");
+    second.resolve_automatically("second");
+
+    let mut note = Note {
+        uuid: Uuid::new_v4(),
+        title: LwwRegister::new(String::from("Draft"), "first"),
+        likes: PnCounter::new(),
+        tags: OrSet::new(),
+    };
+    let create = first.stamp(Event::create(Cow::Owned(note.clone())), "first");
+    first.push(create.clone()).unwrap();
+    second.merge(vec![create]).unwrap();
+
+    // Both replicas update the note concurrently
+    note.likes.increment(1, "first");
+    let first_update = first.stamp(Event::update(Cow::Owned(note.clone())), "first");
+    first.push(first_update.clone()).unwrap();
+    let mut other = second.get_projection()[0].clone().into_owned();
+    other.likes.increment(2, "second");
+    let second_update = second.stamp(Event::update(Cow::Owned(other)), "second");
+    second.push(second_update.clone()).unwrap();
+
+    // Merging the updates resolves the conflicts right away
+    let changes = first.merge(vec![second_update]).unwrap();
+    assert_eq!(changes.len(), 2);
+    second.merge(vec![first_update]).unwrap();
+    assert!(first.get_conflicts().is_empty());
+    assert!(second.get_conflicts().is_empty());
+    assert_eq!(first.get_projection()[0].likes.get(), 3);
+    assert_eq!(second.get_projection()[0].likes.get(), 3);
+
+    // Exchanging the identical resolutions doesn't conflict again
+    let first_resolution = changes[1].get_event().clone();
+    let changes = second.merge(vec![first_resolution]).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(second.get_conflicts().is_empty());
+}
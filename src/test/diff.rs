@@ -0,0 +1,70 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projector};
+use chrono::Utc;
+use std::{borrow::Cow, thread, time};
+
+#[test]
+fn test_diff() {
+    let mut books = Projector::<book::Book>::new();
+    let mut first = new_book(1);
+    let second = new_book(2);
+    let third = new_book(3);
+    books
+        .push(Event::create(Cow::Owned(first.clone())))
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(second.clone())))
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(third.clone())))
+        .unwrap();
+    let monday = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+
+    // Update, delete, create, and create and delete books
+    first.some_number = 10;
+    books
+        .push(Event::update(Cow::Owned(first.clone())))
+        .unwrap();
+    books
+        .push(Event::delete(Cow::Owned(second.clone())))
+        .unwrap();
+    let tuesday = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    books.make_snapshot();
+    let fourth = new_book(4);
+    let temporary = new_book(5);
+    books
+        .push(Event::create(Cow::Owned(fourth.clone())))
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(temporary.clone())))
+        .unwrap();
+    books.push(Event::delete(Cow::Owned(temporary))).unwrap();
+    for number in 11..14 {
+        first.some_number = number;
+        books
+            .push(Event::update(Cow::Owned(first.clone())))
+            .unwrap();
+    }
+    let today = Utc::now();
+
+    // Within a single segment
+    let diff = books.diff(&monday, &tuesday).unwrap();
+    assert!(diff.get_created().is_empty());
+    assert_eq!(diff.get_updated().len(), 1);
+    assert_eq!(diff.get_updated()[0].0.some_number, 1);
+    assert_eq!(diff.get_updated()[0].1.some_number, 10);
+    assert_eq!(diff.get_deleted(), &vec![Cow::Owned(second)]);
+
+    // Spanning several segments
+    let diff = books.diff(&monday, &today).unwrap();
+    assert_eq!(diff.get_created(), &vec![Cow::Owned(fourth)]);
+    assert_eq!(diff.get_updated().len(), 1);
+    assert_eq!(diff.get_updated()[0].1.some_number, 13);
+    assert_eq!(diff.get_deleted().len(), 1);
+
+    // Nothing changed, or invalid ranges
+    assert!(books.diff(&today, &Utc::now()).unwrap().is_empty());
+    assert!(books.diff(&today, &monday).is_none());
+}
@@ -0,0 +1,122 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projector};
+use chrono::Utc;
+use std::{borrow::Cow, sync::Arc, thread, time};
+
+/// Waits for the clock to advance, so events get distinct timestamps
+fn tick() {
+    thread::sleep(time::Duration::from_millis(1));
+}
+
+#[test]
+fn test_fork_and_merge_branch() {
+    let mut my_book = new_book(42);
+    let mut other_book = new_book(7);
+    let mut books = Projector::<book::Book>::new();
+
+    // Create two books and make a snapshot
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(other_book.clone())))
+        .unwrap();
+    books.make_snapshot();
+    tick();
+    my_book.some_number = 43;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    tick();
+    let fork_time = Utc::now();
+    tick();
+
+    // Edits after the fork point aren't part of the fork
+    my_book.some_number = 44;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    let mut draft = books.fork_at(&fork_time);
+    assert_eq!(draft.get_forked_at(), Some(&fork_time));
+    assert_eq!(draft.get_projection()[0].some_number, 43);
+    assert_eq!(draft[0].get_events(), books[0].get_events());
+    assert!(books.get_forked_at().is_none());
+
+    // The sealed segment is shared rather than copied
+    assert!(Arc::ptr_eq(&draft[0], &books[0]));
+
+    // Edit the draft independently
+    tick();
+    my_book.some_number = 100;
+    draft
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    other_book.some_number = 8;
+    draft
+        .push(Event::update(Cow::Owned(other_book.clone())))
+        .unwrap();
+    assert_eq!(books.get_projection()[0].some_number, 44);
+    assert!(Arc::ptr_eq(&draft[0], &books[0]));
+
+    // Merging the draft back reports the book edited on both lines
+    let (changes, conflicts) = books.merge_branch(&draft).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(conflicts.len(), 1);
+    let numbers: Vec<_> = conflicts[0]
+        .get_events()
+        .iter()
+        .map(|e| e.some_number)
+        .collect();
+    assert_eq!(numbers, vec![44, 100]);
+    assert_eq!(books.get_projection()[0].some_number, 100);
+    assert_eq!(books.get_projection()[1].some_number, 8);
+
+    // Merging again doesn't change anything
+    let (changes, _) = books.merge_branch(&draft).unwrap();
+    assert!(changes.is_empty());
+
+    // Only forks can be merged as branches
+    assert!(draft.merge_branch(&books).is_err());
+}
+
+#[test]
+fn test_merge_branch_with_deletions() {
+    let mut deleted_here = new_book(1);
+    let mut deleted_there = new_book(2);
+    let mut books = Projector::<book::Book>::new();
+    books
+        .push(Event::create(Cow::Owned(deleted_here.clone())))
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(deleted_there.clone())))
+        .unwrap();
+    tick();
+    let fork_time = Utc::now();
+    let mut draft = books.fork_at(&fork_time);
+    tick();
+
+    // Each line deletes a book, which the other one updates afterwards
+    books
+        .push(Event::delete(Cow::Owned(deleted_here.clone())))
+        .unwrap();
+    draft
+        .push(Event::delete(Cow::Owned(deleted_there.clone())))
+        .unwrap();
+    tick();
+    deleted_there.some_number = 20;
+    books
+        .push(Event::update(Cow::Owned(deleted_there.clone())))
+        .unwrap();
+    deleted_here.some_number = 10;
+    draft
+        .push(Event::update(Cow::Owned(deleted_here.clone())))
+        .unwrap();
+
+    // Both books are reported as conflicts, keeping the version of this line
+    let (changes, conflicts) = books.merge_branch(&draft).unwrap();
+    assert!(changes.is_empty());
+    assert_eq!(conflicts.len(), 2);
+    assert!(conflicts.iter().all(|c| c.get_events().len() == 2));
+    assert_eq!(books.get_projection().len(), 1);
+    assert_eq!(books.get_projection()[0].some_number, 20);
+}
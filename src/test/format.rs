@@ -0,0 +1,134 @@
+use super::{book, person};
+use crate::{events::Event, format};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Line(i32, i32),
+    Rectangle { width: u8, height: Option<u16> },
+}
+
+#[test]
+fn test_format_round_trip() {
+    let event = Event::<book::Book>::create(Cow::Owned(book::Book {
+        uuid: Uuid::new_v4(),
+        some_number: 42,
+        author: person::Person {
+            uuid: Uuid::new_v4(),
+            first_name: String::from("Alex"),
+            last_name: String::from("Example"),
+        },
+    }));
+
+    // Events survive a round trip, and encoding them is deterministic
+    let bytes = format::to_vec(&event).unwrap();
+    let decoded: Event<book::Book> = format::from_slice(&bytes).unwrap();
+    assert_eq!(decoded, event);
+    assert_eq!(format::to_vec(&decoded).unwrap(), bytes);
+
+    // All kinds of enum variants are supported
+    let shapes = vec![
+        Shape::Empty,
+        Shape::Circle(-1.5),
+        Shape::Line(-300, i32::MAX),
+        Shape::Rectangle {
+            width: 3,
+            height: None,
+        },
+        Shape::Rectangle {
+            width: 0,
+            height: Some(7),
+        },
+    ];
+    let bytes = format::to_vec(&shapes).unwrap();
+    assert_eq!(format::from_slice::<Vec<Shape>>(&bytes).unwrap(), shapes);
+
+    // Borrowed strings and extreme integers
+    assert_eq!(
+        format::from_slice::<&str>(&format::to_vec("hi").unwrap()).unwrap(),
+        "hi"
+    );
+    for value in &[i64::MIN, -1, 0, 1, i64::MAX] {
+        let bytes = format::to_vec(value).unwrap();
+        assert_eq!(format::from_slice::<i64>(&bytes).unwrap(), *value);
+    }
+    let bytes = format::to_vec(&u64::MAX).unwrap();
+    assert_eq!(format::from_slice::<u64>(&bytes).unwrap(), u64::MAX);
+}
+
+#[test]
+fn test_format_canonical() {
+    // The integer type doesn't matter
+    assert_eq!(
+        format::to_vec(&5u8).unwrap(),
+        format::to_vec(&5i64).unwrap()
+    );
+    assert_eq!(format::to_vec(&5u8).unwrap(), vec![0x10, 5]);
+    assert_eq!(format::to_vec(&-1i8).unwrap(), vec![0x11, 0]);
+    assert_eq!(format::to_vec(&300u16).unwrap(), vec![0x10, 0xac, 0x02]);
+
+    // Neither does the order of map entries
+    let mut hashed = HashMap::new();
+    let mut sorted = BTreeMap::new();
+    for i in 0..100 {
+        hashed.insert(format!("key {}", i), i);
+        sorted.insert(format!("key {}", i), i);
+    }
+    let bytes = format::to_vec(&hashed).unwrap();
+    assert_eq!(bytes, format::to_vec(&sorted).unwrap());
+    assert_eq!(
+        format::from_slice::<HashMap<String, i32>>(&bytes).unwrap(),
+        hashed
+    );
+
+    // Non-canonical input is rejected
+    assert!(format::from_slice::<u8>(&[0x10, 0x85, 0x00]).is_err());
+    assert!(format::from_slice::<u8>(&[0x10, 5, 0]).is_err());
+    assert!(format::from_slice::<u8>(&[0x10]).is_err());
+    let unsorted = [0x31, 2, 0x20, 1, b'b', 0x10, 1, 0x20, 1, b'a', 0x10, 2];
+    assert!(format::from_slice::<BTreeMap<String, u8>>(&unsorted).is_err());
+    let duplicate = [0x31, 2, 0x20, 1, b'a', 0x10, 1, 0x20, 1, b'a', 0x10, 2];
+    assert!(format::from_slice::<BTreeMap<String, u8>>(&duplicate).is_err());
+    let sorted = [0x31, 2, 0x20, 1, b'a', 0x10, 2, 0x20, 1, b'b', 0x10, 1];
+    assert_eq!(
+        format::from_slice::<BTreeMap<String, u8>>(&sorted)
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // Re-encoding values of the encoded types yields the original bytes
+    let event = Event::<book::Book>::create(Cow::Owned(book::new_book(42)));
+    let bytes = format::to_vec(&event).unwrap();
+    let decoded: Event<book::Book> = format::from_slice(&bytes).unwrap();
+    assert_eq!(format::to_vec(&decoded).unwrap(), bytes);
+    let bytes = format::to_vec(&sorted).unwrap();
+    let decoded: Vec<u8> = format::from_slice(&bytes).unwrap();
+    assert_eq!(format::to_vec(&decoded).unwrap(), bytes);
+}
+
+#[test]
+fn test_format_nesting_depth() {
+    // Deeply nested input is rejected instead of overflowing the stack
+    let mut nested = [0x30, 1].repeat(200_000);
+    nested.extend([0x30, 0]);
+    assert!(format::from_slice::<serde_json::Value>(&nested).is_err());
+    let mut nested = [0x03].repeat(200_000);
+    nested.push(0x00);
+    assert!(format::from_slice::<serde_json::Value>(&nested).is_err());
+
+    // Values up to the maximum depth are accepted
+    let mut nested = [0x30, 1].repeat(127);
+    nested.extend([0x30, 0]);
+    assert!(format::from_slice::<serde_json::Value>(&nested).is_ok());
+    let mut nested = [0x30, 1].repeat(128);
+    nested.extend([0x30, 0]);
+    assert!(format::from_slice::<serde_json::Value>(&nested).is_err());
+}
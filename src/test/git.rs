@@ -0,0 +1,82 @@
+use super::book::{self, new_book};
+use crate::events::{Event, GitStorage, Projector};
+use std::{borrow::Cow, thread, time};
+use uuid::Uuid;
+
+#[test]
+fn test_git_storage() {
+    let mut my_book = new_book(42);
+    let directory = std::env::temp_dir().join(format!("libocc-{}", Uuid::new_v4()));
+    let storage = GitStorage::<book::Book>::init(directory.join("first.git")).unwrap();
+
+    // Create a book, make a snapshot and modify it twice
+    let mut books = Projector::<book::Book>::new();
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    books.make_snapshot();
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 123;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    let timestamp = chrono::Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 7;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+
+    // Only the sealed segment is saved
+    assert_eq!(storage.save(&books).unwrap(), 1);
+    assert_eq!(storage.save(&books).unwrap(), 0);
+    books.make_snapshot();
+    assert_eq!(storage.save(&books).unwrap(), 1);
+
+    // Loading yields the same history
+    let loaded = storage.load_projector().unwrap();
+    assert_eq!(loaded.len(), 3);
+    assert_eq!(loaded.get_projection().first().unwrap().some_number, 7);
+    assert_eq!(loaded[1].get_time(), books[1].get_time());
+    assert_eq!(loaded[1].get_events(), books[1].get_events());
+    assert_eq!(loaded[1].get_ids(), books[1].get_ids());
+
+    // Projections map onto the commit history
+    let projection = storage.project_at(&timestamp).unwrap().unwrap();
+    assert_eq!(projection.first().unwrap().some_number, 123);
+    assert!(storage
+        .project_at(&(*books[0].get_time() - chrono::Duration::seconds(1)))
+        .unwrap()
+        .is_none());
+
+    // Another replica fetches the segments
+    let other = GitStorage::<book::Book>::init(directory.join("second.git")).unwrap();
+    other
+        .fetch(directory.join("first.git").to_str().unwrap())
+        .unwrap();
+    assert_eq!(other.load().unwrap().len(), 2);
+
+    // Diverging histories are rejected
+    let mut diverged = Projector::<book::Book>::new();
+    diverged
+        .push(Event::create(Cow::Owned(new_book(1))))
+        .unwrap();
+    diverged.make_snapshot();
+    let third = GitStorage::<book::Book>::init(directory.join("third.git")).unwrap();
+    third.save(&diverged).unwrap();
+    assert!(third
+        .fetch(directory.join("first.git").to_str().unwrap())
+        .is_err());
+    assert!(third.save(&books).is_err());
+
+    // Segments with the same timestamps but different content are rejected as well
+    let mut tampered = serde_json::to_value(&books).unwrap();
+    tampered["segments"][1]["events"][0]["Update"]["data"]["some_number"] = 100.into();
+    let tampered: Projector<book::Book> = serde_json::from_value(tampered).unwrap();
+    assert_eq!(tampered[1].get_time(), books[1].get_time());
+    assert_eq!(tampered[1].get_events()[0].some_number, 100);
+    assert!(storage.save(&tampered).is_err());
+    assert_eq!(storage.save(&books).unwrap(), 0);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,161 @@
+use super::book::{self, new_book};
+use crate::events::{Event, History, Projector};
+use std::{borrow::Cow, thread, time};
+
+#[test]
+fn test_history() {
+    let mut my_book = new_book(42);
+    let other_book = new_book(7);
+
+    // Create a book on a shared history
+    let mut first = History::<book::Book>::new();
+    let root = first
+        .append(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    assert_eq!(first.get_heads(), vec![root.clone()]);
+
+    // Another replica receives it
+    let mut second =
+        History::<book::Book>::from_events(first.get_events().into_iter().cloned().collect())
+            .unwrap();
+
+    // Both replicas edit offline
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 123;
+    let update = first
+        .append(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(1));
+    let create = second
+        .append(Event::create(Cow::Owned(other_book.clone())))
+        .unwrap();
+
+    // Exchanging the events yields two heads
+    first
+        .insert(second.get_event(&create).unwrap().clone())
+        .unwrap();
+    assert_eq!(first.get_heads(), vec![update.clone(), create.clone()]);
+    assert!(!first.is_ancestor(&update, &create).unwrap());
+    assert_eq!(
+        first.lowest_common_ancestor(&update, &create).unwrap(),
+        Some(root.clone())
+    );
+
+    // A merge event joins the heads
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 7;
+    let merge = first
+        .append(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    assert_eq!(first.get_heads(), vec![merge.clone()]);
+    assert_eq!(first.get_event(&merge).unwrap().get_parents().len(), 2);
+    assert_eq!(
+        first.get_ancestors(&merge).unwrap(),
+        vec![root.clone(), update.clone(), create.clone()]
+    );
+    assert!(first.is_ancestor(&root, &merge).unwrap());
+    assert_eq!(
+        first.lowest_common_ancestor(&merge, &create).unwrap(),
+        Some(create.clone())
+    );
+
+    // The replay is deterministic, regardless of the insertion order
+    let projection = first.replay();
+    assert_eq!(projection.len(), 2);
+    assert_eq!(projection[0].some_number, 7);
+    let mut events: Vec<_> = first.get_events().into_iter().cloned().collect();
+    events.reverse();
+    let reordered = History::from_events(events).unwrap();
+    assert_eq!(reordered.get_heads(), first.get_heads());
+    assert_eq!(reordered.get_events(), first.get_events());
+
+    // Projections can be computed as seen by any event
+    let projection = first.project(&update).unwrap();
+    assert_eq!(projection.len(), 1);
+    assert_eq!(projection[0].some_number, 123);
+
+    // Events with unknown parents are rejected
+    let orphan =
+        Event::<book::Book>::update(Cow::Owned(my_book)).with_parents(vec![
+            Event::<book::Book>::create(Cow::Owned(new_book(1)))
+                .id()
+                .unwrap(),
+        ]);
+    assert!(second.insert(orphan).is_err());
+}
+
+#[test]
+fn test_history_from_projector() {
+    let mut my_book = new_book(42);
+    let mut books = Projector::<book::Book>::new();
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    books.make_snapshot();
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 123;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+
+    // The events of a projector form a linear history
+    let history = History::from_projector(&books).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.get_heads().len(), 1);
+    assert_eq!(history.replay()[0].some_number, 123);
+
+    // Histories are persisted as their events
+    let json = serde_json::to_string(&history).unwrap();
+    let restored: History<book::Book> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.get_heads(), history.get_heads());
+    assert_eq!(restored.get_events(), history.get_events());
+}
+
+#[test]
+fn test_history_conflicts() {
+    let mut my_book = new_book(42);
+
+    // Create a book on a shared history
+    let mut first = History::<book::Book>::new();
+    first
+        .append(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    let mut second =
+        History::<book::Book>::from_events(first.get_events().into_iter().cloned().collect())
+            .unwrap();
+
+    // One replica deletes the book, while the other one updates it
+    thread::sleep(time::Duration::from_millis(1));
+    let delete = first
+        .append(Event::delete(Cow::Owned(my_book.clone())))
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 123;
+    let update = second
+        .append(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+
+    // Exchanging the events skips the update, as the deletion is replayed first
+    first
+        .insert(second.get_event(&update).unwrap().clone())
+        .unwrap();
+    second
+        .insert(first.get_event(&delete).unwrap().clone())
+        .unwrap();
+    for replica in &[&first, &second] {
+        assert!(replica.replay().is_empty());
+        assert_eq!(replica.get_skipped(), vec![update.clone()]);
+    }
+
+    // Each branch can still be projected on its own
+    assert_eq!(first.project(&update).unwrap()[0].some_number, 123);
+    assert!(first.project(&delete).unwrap().is_empty());
+
+    // The history can be continued after the conflict
+    my_book.some_number = 7;
+    first
+        .append(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    assert_eq!(first.replay()[0].some_number, 7);
+    assert_eq!(first.get_skipped(), vec![update]);
+}
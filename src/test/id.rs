@@ -0,0 +1,62 @@
+use super::book::{self, new_book};
+use crate::events::{ContentId, Event, Projector};
+use std::borrow::Cow;
+
+#[test]
+fn test_content_ids() {
+    let mut books = Projector::<book::Book>::new();
+    let mut my_book = new_book(42);
+    let create = Event::<book::Book>::create(Cow::Owned(my_book.clone()));
+    books.push(create.clone()).unwrap();
+    books.make_snapshot();
+    my_book.some_number = 123;
+    let update = Event::<book::Book>::update(Cow::Owned(my_book.clone()));
+
+    // Identical events have identical ids, different ones don't
+    let id = create.id().unwrap();
+    assert_eq!(id, create.clone().id().unwrap());
+    assert_ne!(id, update.id().unwrap());
+    assert_eq!(id.get_codec(), ContentId::LIBOCC_FORMAT);
+    assert_eq!(id.get_hash().code(), 0x12);
+
+    // Ids are self-describing strings
+    let string = id.to_string();
+    assert!(string.starts_with("f018080c0011220"));
+    assert_eq!(string.parse::<ContentId>().unwrap(), id);
+    assert_eq!(ContentId::from_bytes(&id.to_bytes()).unwrap(), id);
+    assert_eq!(
+        serde_json::from_value::<ContentId>(serde_json::to_value(&id).unwrap()).unwrap(),
+        id
+    );
+    assert!("z123".parse::<ContentId>().is_err());
+    assert!("f01".parse::<ContentId>().is_err());
+
+    // Over-long varints aren't valid
+    let mut overlong = vec![0x81u8, 0x00];
+    overlong.extend(&id.to_bytes()[1..]);
+    assert!(ContentId::from_bytes(&overlong).is_err());
+
+    // Events can be looked up by their ids
+    books.push(update.clone()).unwrap();
+    assert_eq!(books.get_event(&id).unwrap(), Some(&create));
+    assert_eq!(
+        books.get_event(&update.id().unwrap()).unwrap(),
+        Some(&update)
+    );
+    let unknown = Event::<book::Book>::create(Cow::Owned(new_book(1)));
+    assert_eq!(books.get_event(&unknown.id().unwrap()).unwrap(), None);
+
+    // Events added after a lookup (e.g. merged out of order) can be looked up as well
+    let earlier = Event::<book::Book>::create_at(Cow::Owned(new_book(1)), *books[0].get_time());
+    books.insert(earlier.clone()).unwrap();
+    assert_eq!(
+        books.get_event(&earlier.id().unwrap()).unwrap(),
+        Some(&earlier)
+    );
+
+    // Sealed segments have stable ids
+    let sealed = books[0].id().unwrap();
+    books.push(Event::create(Cow::Owned(new_book(7)))).unwrap();
+    assert_eq!(books[0].id().unwrap(), sealed);
+    assert_ne!(books[1].id().unwrap(), sealed);
+}
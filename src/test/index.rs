@@ -0,0 +1,174 @@
+use super::{
+    book::{self, new_book},
+    numbers,
+};
+use crate::events::{Event, Projector};
+use chrono::{Duration, Utc};
+use std::{borrow::Cow, thread, time};
+
+fn new_book_by(some_number: usize, last_name: &str) -> book::Book {
+    let mut book = new_book(some_number);
+    book.author.last_name = String::from(last_name);
+    book
+}
+
+#[test]
+fn test_indexes() {
+    let mut books = Projector::<book::Book>::new();
+    let mut first = new_book_by(1, "Example");
+    let second = new_book_by(2, "Sample");
+    let third = new_book_by(3, "Example");
+    books
+        .push(Event::create(Cow::Owned(first.clone())))
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(second.clone())))
+        .unwrap();
+
+    // Indexes cover existing and new entities
+    books.add_index("author", |b| b.author.last_name.clone());
+    books
+        .push(Event::create(Cow::Owned(third.clone())))
+        .unwrap();
+    assert_eq!(
+        numbers(books.lookup("author", "Example").unwrap()),
+        vec![1, 3]
+    );
+    assert!(books.lookup("title", "Example").is_err());
+    let timestamp = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    books.make_snapshot();
+
+    // Updates and deletions are reflected as well
+    first.author.last_name = String::from("Sample");
+    books
+        .push(Event::update(Cow::Owned(first.clone())))
+        .unwrap();
+    books.push(Event::delete(Cow::Owned(second))).unwrap();
+    assert_eq!(numbers(books.lookup("author", "Example").unwrap()), vec![3]);
+    assert_eq!(numbers(books.lookup("author", "Sample").unwrap()), vec![1]);
+
+    // Entities keep their identifiers when others are deleted
+    let index = books[1].get_index("author").unwrap();
+    assert_eq!(index.get("Example"), &[2]);
+    assert_eq!(books[1].get_entity(2).unwrap().some_number, 3);
+    assert!(books[1].get_entity(1).is_none());
+    let later = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    books.push(Event::delete(Cow::Owned(third))).unwrap();
+    assert!(books.lookup("author", "Example").unwrap().is_empty());
+
+    // Previous states can be looked up
+    let previous = books.lookup_at("author", "Example", &timestamp).unwrap();
+    assert_eq!(previous.unwrap().len(), 2);
+    let previous = books.lookup_at("author", "Sample", &later).unwrap();
+    assert_eq!(previous.unwrap()[0].some_number, 1);
+    assert!(books
+        .lookup_at("author", "Sample", &(timestamp - chrono::Duration::days(1)))
+        .unwrap()
+        .is_none());
+
+    // Indexes are persisted, but aren't part of the content identifiers of segments
+    let sealed = books[0].id().unwrap();
+    books.add_index("number", |b| b.some_number.to_string());
+    assert_eq!(books[0].id().unwrap(), sealed);
+    let json = serde_json::to_string(&books).unwrap();
+    let mut restored: Projector<book::Book> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored[0].id().unwrap(), sealed);
+    assert_eq!(
+        restored[0].get_index("author"),
+        books[0].get_index("author")
+    );
+    assert_eq!(
+        numbers(restored.lookup("author", "Sample").unwrap()),
+        vec![1]
+    );
+
+    // Their key functions aren't, so they're marked as stale when further changes can't be indexed
+    assert!(restored.lookup_at("author", "Sample", &later).is_err());
+    restored.push(Event::delete(Cow::Owned(first))).unwrap();
+    assert!(restored.last().unwrap().is_stale("author"));
+    assert!(restored.last().unwrap().get_index("author").is_some());
+    assert!(restored.lookup("author", "Sample").is_err());
+
+    // Adding the index again rebuilds the stale ones
+    restored.add_index("author", |b| b.author.last_name.clone());
+    assert_eq!(
+        restored[0].get_index("author"),
+        books[0].get_index("author")
+    );
+    assert!(restored.lookup("author", "Sample").unwrap().is_empty());
+    assert_eq!(
+        restored
+            .lookup_at("author", "Sample", &later)
+            .unwrap()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_entity_ids() {
+    let mut books = Projector::<book::Book>::new();
+    let start = Utc::now();
+    books.add_index("author", |b| b.author.last_name.clone());
+    for number in 0..3 {
+        books
+            .push(Event::create(Cow::Owned(new_book_by(number, "Example"))))
+            .unwrap();
+        if number == 1 {
+            books.make_snapshot();
+        }
+    }
+    let timestamp = *books.last().unwrap().get_time();
+
+    // Entities created by events merged out of order don't reuse identifiers
+    books
+        .merge(vec![Event::create_at(
+            Cow::Owned(new_book_by(3, "Example")),
+            start - Duration::seconds(1),
+        )])
+        .unwrap();
+    let segment = books.last().unwrap();
+    assert_eq!(segment.get_entity(0).unwrap().some_number, 0);
+    assert_eq!(segment.get_entity(2).unwrap().some_number, 2);
+    assert_eq!(segment.get_entity(3).unwrap().some_number, 3);
+    assert_eq!(
+        segment.get_index("author").unwrap().get("Example"),
+        &[0, 1, 2, 3]
+    );
+
+    // Forks and restored projectors keep the identifiers
+    let fork = books.fork_at(&timestamp);
+    assert_eq!(fork.last().unwrap().get_ids(), &vec![3, 0, 1]);
+    let json = serde_json::to_string(&books).unwrap();
+    let restored: Projector<book::Book> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.last().unwrap().get_ids(), segment.get_ids());
+}
+
+/// A projector persisted by version 0.5, which didn't identify the entities of segments
+const PROJECTOR_0_5: &str = include_str!("fixtures/projector-0.5.json");
+
+#[test]
+fn test_entity_ids_of_older_projectors() {
+    let mut books: Projector<book::Book> = serde_json::from_str(PROJECTOR_0_5).unwrap();
+
+    // The entities are identified in order
+    assert_eq!(books.last().unwrap().get_ids(), &vec![0, 1]);
+    books.add_index("number", |b| b.some_number.to_string());
+
+    // Updating and deleting them works as usual
+    let mut second = books.get_projection()[1].clone().into_owned();
+    second.some_number = 8;
+    books
+        .push(Event::update(Cow::Owned(second.clone())))
+        .unwrap();
+    assert_eq!(numbers(books.lookup("number", "8").unwrap()), vec![8]);
+    books.push(Event::delete(Cow::Owned(second))).unwrap();
+    assert_eq!(books.last().unwrap().get_ids(), &vec![0]);
+    books
+        .push(Event::create(Cow::Owned(new_book_by(3, "Example"))))
+        .unwrap();
+    assert_eq!(books.last().unwrap().get_ids(), &vec![0, 2]);
+}
@@ -0,0 +1,80 @@
+use super::book::{self, new_book};
+use crate::{
+    events::{Event, Projector},
+    interop::{Operation, TsEvent, TsProjector},
+};
+use std::borrow::Cow;
+
+/// A projector in the shape exported by libocc-ts
+///
+/// It's written by hand following the `Projector` of libocc-ts (rather than
+/// exported by it), using the entities of the tests as they're serialized by
+/// Serde (e.g. `some_number`), as libocc-ts stores entities without renaming them.
+const FIXTURE: &str = include_str!("fixtures/ts-projector.json");
+
+#[test]
+fn test_ts_fixture_round_trip() {
+    let fixture: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+
+    // Read the projector of libocc-ts
+    let exported: TsProjector<book::Book> = serde_json::from_str(FIXTURE).unwrap();
+    assert_eq!(exported.get_event_log().len(), 4);
+    assert_eq!(
+        exported.get_event_log()[3].get_operation(),
+        Operation::Delete
+    );
+
+    // Replay it, which yields the same projection
+    let projector = exported.into_projector().unwrap();
+    assert_eq!(projector.get_projection().len(), 1);
+    assert_eq!(projector.get_projection()[0].some_number, 123);
+
+    // Writing it back yields the same JSON
+    let imported = TsProjector::from_projector(&projector);
+    assert_eq!(serde_json::to_value(&imported).unwrap(), fixture);
+
+    // A mismatching projection is rejected, even if it only differs in fields
+    // not compared by `PartialEq`
+    let mut tampered = fixture.clone();
+    tampered["projection"] = serde_json::json!([]);
+    let tampered: TsProjector<book::Book> = serde_json::from_value(tampered).unwrap();
+    assert!(tampered.into_projector().is_err());
+    let mut tampered = fixture;
+    tampered["projection"][0]["some_number"] = serde_json::json!(42);
+    let tampered: TsProjector<book::Book> = serde_json::from_value(tampered).unwrap();
+    assert!(tampered.into_projector().is_err());
+}
+
+#[test]
+fn test_ts_event_conversion() {
+    let my_book = new_book(42);
+
+    // Events are written like libocc-ts does, with milliseconds only
+    let event = Event::<book::Book>::update(Cow::Owned(my_book.clone()));
+    let exported = TsEvent::from(event.clone());
+    let json = serde_json::to_value(&exported).unwrap();
+    assert_eq!(json["operation"], "update");
+    assert_eq!(json["date"].as_str().unwrap().len(), 24);
+    assert_eq!(
+        exported.get_date().timestamp_millis(),
+        event.get_time().timestamp_millis()
+    );
+
+    // Reading them back yields the same event (apart from the precision)
+    let imported: Event<book::Book> = serde_json::from_value::<TsEvent<book::Book>>(json)
+        .unwrap()
+        .into();
+    assert!(matches!(imported, Event::Update(_)));
+    assert_eq!(imported.get_time(), exported.get_date());
+
+    // Exporting a projector flattens its segments
+    let mut books = Projector::<book::Book>::new();
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    books.make_snapshot();
+    books.push(Event::update(Cow::Owned(my_book))).unwrap();
+    let exported = TsProjector::from_projector(&books);
+    assert_eq!(exported.get_event_log().len(), 2);
+    assert_eq!(exported.get_projection().len(), 1);
+}
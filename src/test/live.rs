@@ -0,0 +1,167 @@
+use super::{
+    book::{self, new_book},
+    person,
+};
+use crate::{
+    events::{Event, Projector},
+    sync::LiveClient,
+};
+use std::{
+    borrow::Cow,
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Waits for a client to connect to the server
+fn wait_for_connection(client: &LiveClient<book::Book>) {
+    for _ in 0..500 {
+        if client.is_connected() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Cannot connect to the server");
+}
+
+/// Merges events received by a client until a projector satisfies a condition
+fn wait_for<F>(client: &mut LiveClient<book::Book>, projector: &mut Projector<book::Book>, f: F)
+where
+    F: Fn(&Projector<book::Book>) -> bool,
+{
+    for _ in 0..50 {
+        client.apply_timeout(projector, TIMEOUT / 50).unwrap();
+        if f(projector) {
+            return;
+        }
+    }
+    panic!("Cannot receive the expected events");
+}
+
+#[test]
+fn test_live_replication() {
+    let url = format!("http://{}", super::server::start_server());
+    let mut my_book = book::Book {
+        uuid: Uuid::new_v4(),
+        some_number: 42,
+        author: person::Person {
+            uuid: Uuid::new_v4(),
+            first_name: String::from("Alex"),
+            last_name: String::from("Example"),
+        },
+    };
+
+    // Connect two replicas to the server
+    let mut first = Projector::<book::Book>::new();
+    let mut second = Projector::<book::Book>::new();
+    let mut first_client = LiveClient::connect(&url, "books", 0);
+    let mut second_client = LiveClient::connect(&url, "books", 0);
+    wait_for_connection(&first_client);
+    wait_for_connection(&second_client);
+
+    // The first replica creates a book, which is streamed to the second one
+    let changes = first.subscribe();
+    first
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    first_client.forward(&changes).unwrap();
+    wait_for(&mut second_client, &mut second, |p| {
+        p.get_projection().len() == 1
+    });
+    assert_eq!(second_client.get_acknowledged(), 1);
+
+    // The second replica modifies the book, which is streamed back to the first one
+    thread::sleep(Duration::from_millis(1));
+    my_book.some_number = 123;
+    second
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    second_client
+        .send(vec![Event::update(Cow::Owned(my_book.clone()))])
+        .unwrap();
+    wait_for(&mut first_client, &mut first, |p| {
+        p.get_projection().first().unwrap().some_number == 123
+    });
+
+    // Clients resuming from a position only receive the later events,
+    // so the update can't be merged without the creation of the book
+    let mut third = Projector::<book::Book>::new();
+    let mut third_client = LiveClient::connect(&url, "books", 1);
+    wait_for_connection(&third_client);
+    assert!(third_client.apply_timeout(&mut third, TIMEOUT).is_err());
+    assert!(third.get_projection().is_empty());
+
+    // The update is kept, and merged once the creation of the book is known
+    third.merge(vec![first[0].get_events()[0].clone()]).unwrap();
+    third_client.apply(&mut third).unwrap();
+    assert_eq!(third.get_projection().first().unwrap().some_number, 123);
+
+    // Errors are reported by the server
+    let mut unknown_book = my_book.clone();
+    unknown_book.uuid = Uuid::new_v4();
+    first_client
+        .send(vec![Event::update(Cow::Owned(unknown_book))])
+        .unwrap();
+    for _ in 0..50 {
+        first_client
+            .apply_timeout(&mut first, TIMEOUT / 50)
+            .unwrap();
+        if !first_client.take_errors().is_empty() {
+            return;
+        }
+    }
+    panic!("Cannot receive the expected error");
+}
+
+#[test]
+fn test_live_resume_offline_events() {
+    let url = format!("http://{}", super::server::start_server());
+    // A book created offline (before the one created online)
+    let offline_event = Event::create(Cow::Owned(new_book(42)));
+    thread::sleep(Duration::from_millis(1));
+
+    // The first replica receives a book created online, and disconnects
+    let mut first = Projector::<book::Book>::new();
+    let mut first_client = LiveClient::connect(&url, "books", 0);
+    wait_for_connection(&first_client);
+    first_client
+        .send(vec![Event::create(Cow::Owned(new_book(42)))])
+        .unwrap();
+    wait_for(&mut first_client, &mut first, |p| {
+        p.get_projection().len() == 1
+    });
+    let acknowledged = first_client.get_acknowledged();
+    drop(first_client);
+
+    // Another replica pushes the book created offline
+    let second_client = LiveClient::<book::Book>::connect(&url, "books", 0);
+    wait_for_connection(&second_client);
+    second_client.send(vec![offline_event]).unwrap();
+
+    // Resuming receives the book created offline, despite its earlier timestamp
+    let mut first_client = LiveClient::connect(&url, "books", acknowledged);
+    wait_for(&mut first_client, &mut first, |p| {
+        p.get_projection().len() == 2
+    });
+    assert_eq!(first_client.get_acknowledged(), 2);
+}
+
+#[test]
+fn test_live_client_drop() {
+    // Find a port nobody is listening on
+    let address = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client = LiveClient::<book::Book>::connect(&format!("http://{}", address), "books", 0);
+
+    // Dropping the client doesn't wait for the next attempt to reconnect
+    thread::sleep(Duration::from_millis(400));
+    assert!(!client.is_connected());
+    let start = Instant::now();
+    drop(client);
+    assert!(start.elapsed() < Duration::from_millis(100));
+}
@@ -0,0 +1,98 @@
+use super::book::{self, new_book};
+use crate::events::{merge3, Event, MergeConflict, Projector};
+use std::borrow::Cow;
+
+/// Returns a copy of a book with a different number
+fn with_number(book: &book::Book, some_number: usize) -> Cow<'static, book::Book> {
+    let mut book = book.clone();
+    book.some_number = some_number;
+    Cow::Owned(book)
+}
+
+#[test]
+fn test_merge3_clean() {
+    let (unchanged, ours_only, theirs_only, both_same, deleted) = (
+        new_book(1),
+        new_book(2),
+        new_book(3),
+        new_book(4),
+        new_book(5),
+    );
+    let (created_by_us, created_by_them) = (new_book(6), new_book(7));
+    let base: Vec<Cow<book::Book>> = vec![
+        Cow::Borrowed(&unchanged),
+        Cow::Borrowed(&ours_only),
+        Cow::Borrowed(&theirs_only),
+        Cow::Borrowed(&both_same),
+        Cow::Borrowed(&deleted),
+    ];
+    let ours = vec![
+        Cow::Borrowed(&unchanged),
+        with_number(&ours_only, 20),
+        Cow::Borrowed(&theirs_only),
+        with_number(&both_same, 40),
+        Cow::Borrowed(&created_by_us),
+    ];
+    let theirs = vec![
+        Cow::Borrowed(&unchanged),
+        Cow::Borrowed(&ours_only),
+        with_number(&theirs_only, 30),
+        with_number(&both_same, 40),
+        Cow::Borrowed(&deleted),
+        Cow::Borrowed(&created_by_them),
+    ];
+
+    let merge = merge3(&base, &ours, &theirs).unwrap();
+    assert!(merge.is_clean());
+    let numbers: Vec<_> = merge.get_merged().iter().map(|b| b.some_number).collect();
+    assert_eq!(numbers, vec![1, 20, 30, 40, 6, 7]);
+}
+
+#[test]
+fn test_merge3_conflicts() {
+    let (updated, updated_deleted, deleted_updated) = (new_book(1), new_book(2), new_book(3));
+    let created = new_book(4);
+
+    // Use the snapshot of a segment as the base
+    let mut books = Projector::<book::Book>::new();
+    for book in &[&updated, &updated_deleted, &deleted_updated] {
+        books
+            .push(Event::create(Cow::Owned(book::Book::clone(book))))
+            .unwrap();
+    }
+    books.make_snapshot();
+
+    let ours = vec![
+        with_number(&updated, 10),
+        with_number(&updated_deleted, 20),
+        with_number(&created, 40),
+    ];
+    let theirs = vec![
+        with_number(&updated, 11),
+        with_number(&deleted_updated, 31),
+        with_number(&created, 41),
+    ];
+
+    let (merged, conflicts) = books[0].merge3(&ours, &theirs).unwrap().take();
+    let numbers: Vec<_> = merged.iter().map(|b| b.some_number).collect();
+    assert_eq!(numbers, vec![10, 20, 31, 40]);
+    assert_eq!(conflicts.len(), 4);
+    assert!(matches!(
+        &conflicts[0],
+        MergeConflict::BothUpdated { base, ours, theirs }
+            if base.some_number == 1 && ours.some_number == 10 && theirs.some_number == 11
+    ));
+    assert!(matches!(
+        &conflicts[1],
+        MergeConflict::UpdatedDeleted { ours, .. } if ours.some_number == 20
+    ));
+    assert!(matches!(
+        &conflicts[2],
+        MergeConflict::DeletedUpdated { theirs, .. } if theirs.some_number == 31
+    ));
+    assert!(matches!(
+        &conflicts[3],
+        MergeConflict::DuplicateCreate { ours, theirs }
+            if ours.some_number == 40 && theirs.some_number == 41
+    ));
+}
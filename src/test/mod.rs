@@ -1,9 +1,52 @@
+// The baseline tests index projections with `get(0)`
+#![allow(clippy::get_first)]
+
+mod aggregate;
 mod book;
+mod change;
+mod crdt;
+mod diff;
+mod fork;
+mod format;
+#[cfg(feature = "git")]
+mod git;
+mod history;
+mod id;
+mod index;
+mod interop;
+#[cfg(all(feature = "server", feature = "client"))]
+mod live;
+mod merge3;
+mod pending;
 mod person;
+mod projection;
+mod query;
+#[cfg(all(feature = "server", feature = "client"))]
+mod server;
+mod shared;
+#[cfg(feature = "async")]
+mod storage;
+mod store;
+mod sync;
+mod text;
+mod transaction;
+mod tree;
+mod version;
 use chrono::Utc;
-use std::{borrow::Cow, thread, time};
+use std::{
+    borrow::{Borrow, Cow},
+    thread, time,
+};
 use uuid::Uuid;
 
+/// Returns the numbers of the given books, in order
+fn numbers<'b, B>(books: impl IntoIterator<Item = B>) -> Vec<usize>
+where
+    B: Borrow<Cow<'b, book::Book>>,
+{
+    books.into_iter().map(|b| b.borrow().some_number).collect()
+}
+
 #[test]
 fn test_projector() {
     // Create a new book
@@ -327,3 +370,46 @@ fn test_many_change_after_snapshots() {
         123
     );
 }
+
+#[test]
+fn test_get_events_from() {
+    let mut my_book = book::new_book(42);
+    let before: crate::events::Timestamp = Utc::now();
+    let mut books = crate::events::Projector::<book::Book>::new();
+
+    // Create the book, then update it twice after making a snapshot
+    books
+        .push(crate::events::Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(1));
+    let between: crate::events::Timestamp = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    books.make_snapshot();
+    for some_number in [43, 44] {
+        thread::sleep(time::Duration::from_millis(1));
+        my_book.some_number = some_number;
+        books
+            .push(crate::events::Event::update(Cow::Owned(my_book.clone())))
+            .unwrap();
+    }
+    let updated = *books[1].get_events()[0].get_time();
+
+    // Events occurring at the given timestamp are included
+    let numbers = |from| -> Vec<usize> {
+        books
+            .get_events_from(from)
+            .iter()
+            .map(|e| e.some_number)
+            .collect()
+    };
+    assert_eq!(numbers(&updated), vec![43, 44]);
+
+    // Timestamps between events or segments start at the following event
+    assert_eq!(numbers(&between), vec![43, 44]);
+
+    // Timestamps predating all segments include all events
+    assert_eq!(numbers(&before), vec![42, 43, 44]);
+
+    // Timestamps following all events include none
+    assert!(numbers(&Utc::now()).is_empty());
+}
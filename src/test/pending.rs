@@ -0,0 +1,69 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projector};
+use chrono::Utc;
+use std::borrow::Cow;
+
+#[test]
+fn test_pending_unversioned() {
+    let mut books = Projector::<book::Book>::new();
+    let mut my_book = new_book(42);
+    let create = Event::create(Cow::Owned(my_book.clone()));
+    my_book.some_number = 123;
+    let update = Event::<book::Book>::update(Cow::Owned(my_book.clone()));
+
+    // The update arrives first, so it's held back
+    assert!(books.deliver(vec![update.clone()]).unwrap().is_empty());
+    assert_eq!(books.get_pending().len(), 1);
+    assert_eq!(books.get_pending()[0].get_event(), &update);
+    assert!(books.get_projection().is_empty());
+
+    // Once the create arrives, both are applied (at once)
+    let changes = books.deliver(vec![create]).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].get_old().is_none());
+    assert!(books.get_pending().is_empty());
+    assert_eq!(books.get_projection()[0].some_number, 123);
+}
+
+#[test]
+fn test_pending_versioned() {
+    let mut origin = Projector::<book::Book>::new();
+    let mut replica = Projector::<book::Book>::new();
+    let mut my_book = new_book(42);
+    let other_book = new_book(7);
+
+    // The origin creates two books and updates the first one
+    let first = origin.stamp(Event::create(Cow::Owned(my_book.clone())), "origin");
+    origin.push(first.clone()).unwrap();
+    let second = origin.stamp(Event::create(Cow::Owned(other_book)), "origin");
+    origin.push(second.clone()).unwrap();
+    my_book.some_number = 123;
+    let third = origin.stamp(Event::update(Cow::Owned(my_book)), "origin");
+    origin.push(third.clone()).unwrap();
+
+    // The update could be applied, but it causally depends on the second create
+    replica.deliver(vec![first, third]).unwrap();
+    assert_eq!(replica.get_pending().len(), 1);
+    assert_eq!(replica.get_projection()[0].some_number, 42);
+
+    // Filling the gap applies the held back update
+    replica.deliver(vec![second]).unwrap();
+    assert!(replica.get_pending().is_empty());
+    assert_eq!(replica.get_projection().len(), 2);
+    assert_eq!(replica.get_projection()[0].some_number, 123);
+    assert_eq!(replica.get_frontier(), origin.get_frontier());
+}
+
+#[test]
+fn test_pending_expiry() {
+    let mut books = Projector::<book::Book>::new();
+    let stuck = Event::<book::Book>::update(Cow::Owned(new_book(1)));
+    books.deliver(vec![stuck.clone(), stuck.clone()]).unwrap();
+    assert_eq!(books.get_pending().len(), 1);
+
+    // Only events received before the given time expire
+    let received = *books.get_pending()[0].get_received();
+    assert!(books.expire_pending(&received).is_empty());
+    assert_eq!(books.expire_pending(&Utc::now()), vec![stuck]);
+    assert!(books.get_pending().is_empty());
+}
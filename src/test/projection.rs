@@ -0,0 +1,100 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projection, Projector};
+use chrono::Utc;
+use std::{borrow::Cow, collections::HashMap, thread, time};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Statistics {
+    count: usize,
+    updates: usize,
+}
+
+impl Projection<book::Book> for Statistics {
+    fn apply(&mut self, event: &Event<book::Book>) {
+        match event {
+            Event::Create(_) => self.count += 1,
+            Event::Update(_) => self.updates += 1,
+            Event::Delete(_) => self.count -= 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Numbers(HashMap<Uuid, usize>);
+
+impl Projection<book::Book> for Numbers {
+    fn apply(&mut self, event: &Event<book::Book>) {
+        match event {
+            Event::Delete(_) => self.0.remove(&event.uuid),
+            _ => self.0.insert(event.uuid, event.some_number),
+        };
+    }
+}
+
+#[test]
+fn test_custom_projections() {
+    let mut books = Projector::<book::Book>::new();
+    let mut first = new_book(1);
+    let second = new_book(2);
+    books
+        .push(Event::create(Cow::Owned(first.clone())))
+        .unwrap();
+    let timestamp = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    books.make_snapshot();
+    books
+        .push(Event::create(Cow::Owned(second.clone())))
+        .unwrap();
+
+    // Projections added later cover the entire history
+    books.add_projection("statistics", Statistics::default());
+    books.add_projection("numbers", Numbers::default());
+    let statistics: &Statistics = books.get_custom_projection("statistics").unwrap();
+    assert_eq!(statistics.count, 2);
+
+    // Projections are updated incrementally
+    first.some_number = 10;
+    books
+        .push(Event::update(Cow::Owned(first.clone())))
+        .unwrap();
+    books.push(Event::delete(Cow::Owned(second))).unwrap();
+    let statistics: &Statistics = books.get_custom_projection("statistics").unwrap();
+    assert_eq!(
+        statistics,
+        &Statistics {
+            count: 1,
+            updates: 1
+        }
+    );
+    let numbers: &Numbers = books.get_custom_projection("numbers").unwrap();
+    assert_eq!(numbers.0.get(&first.uuid), Some(&10));
+    assert_eq!(numbers.0.len(), 1);
+
+    // Previous states can be projected
+    let previous: Statistics = books
+        .project_custom_at("statistics", &timestamp)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        previous,
+        Statistics {
+            count: 1,
+            updates: 0
+        }
+    );
+
+    // Merging out-of-order events updates the projections as well
+    let late = Event::create_at(Cow::Owned(new_book(3)), timestamp);
+    books.merge(vec![late]).unwrap();
+    let statistics: &Statistics = books.get_custom_projection("statistics").unwrap();
+    assert_eq!(statistics.count, 2);
+
+    // Unknown projections and mismatching types are rejected
+    assert!(books
+        .get_custom_projection::<Statistics>("unknown")
+        .is_err());
+    assert!(books
+        .get_custom_projection::<Numbers>("statistics")
+        .is_err());
+}
@@ -0,0 +1,64 @@
+use super::{
+    book::{self, new_book},
+    numbers,
+};
+use crate::events::{Event, Projector};
+use chrono::Utc;
+use std::{borrow::Cow, thread, time};
+
+#[test]
+fn test_query() {
+    let mut books = Projector::<book::Book>::new();
+    for some_number in [5, 3, 8, 1, 9, 4] {
+        books
+            .push(Event::create(Cow::Owned(new_book(some_number))))
+            .unwrap();
+    }
+    let timestamp = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    books.make_snapshot();
+    books.push(Event::create(Cow::Owned(new_book(7)))).unwrap();
+
+    // Without any conditions, all entities are returned in order
+    assert_eq!(
+        numbers(books.query().execute().unwrap()),
+        vec![5, 3, 8, 1, 9, 4, 7]
+    );
+
+    // Filter, sort and paginate the current projection
+    let page = books
+        .query()
+        .filter(|b| b.some_number > 2)
+        .sort_by(|a, b| a.some_number.cmp(&b.some_number))
+        .skip(1)
+        .take(3)
+        .execute()
+        .unwrap();
+    assert_eq!(numbers(page), vec![4, 5, 7]);
+
+    // Later comparisons break ties
+    let sorted = books
+        .query()
+        .sort_by(|a, b| (a.some_number % 2).cmp(&(b.some_number % 2)))
+        .sort_by(|a, b| b.some_number.cmp(&a.some_number))
+        .execute()
+        .unwrap();
+    assert_eq!(numbers(sorted), vec![8, 4, 9, 7, 5, 3, 1]);
+
+    // Query a historical projection
+    let page = books
+        .query()
+        .filter(|b| b.some_number > 2)
+        .sort_by(|a, b| a.some_number.cmp(&b.some_number))
+        .skip(1)
+        .take(3)
+        .at(&timestamp)
+        .execute()
+        .unwrap();
+    assert_eq!(numbers(page), vec![4, 5, 8]);
+    assert!(books
+        .query()
+        .at(&(timestamp - chrono::Duration::days(1)))
+        .execute()
+        .is_none());
+}
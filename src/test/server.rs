@@ -0,0 +1,137 @@
+use super::{book, person};
+use crate::{
+    events::{Event, Timestamp},
+    server::{Document, Server, SnapshotInfo},
+};
+use chrono::{SecondsFormat, Utc};
+use std::{borrow::Cow, net::SocketAddr, sync::Arc, thread};
+use uuid::Uuid;
+
+/// Starts a new sync server on a random local port, returning its address
+pub(super) fn start_server() -> SocketAddr {
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            sender.send(listener.local_addr().unwrap()).unwrap();
+            Arc::new(Server::new())
+                .serve(listener, futures::future::pending())
+                .await
+                .unwrap();
+        });
+    });
+
+    receiver.recv().unwrap()
+}
+
+/// Formats a timestamp for use in a query
+fn format(timestamp: &Timestamp) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+#[test]
+fn test_server() {
+    let url = format!("http://{}", start_server());
+    let mut my_book = book::Book {
+        uuid: Uuid::new_v4(),
+        some_number: 42,
+        author: person::Person {
+            uuid: Uuid::new_v4(),
+            first_name: String::from("Alex"),
+            last_name: String::from("Example"),
+        },
+    };
+
+    // Unknown collections can't be read
+    let error = ureq::get(&format!("{}/collections/books/events", url))
+        .call()
+        .unwrap_err();
+    assert!(matches!(error, ureq::Error::Status(404, _)));
+
+    // Push a new book
+    let create = Event::<book::Book>::create(Cow::Owned(my_book.clone()));
+    ureq::post(&format!("{}/collections/books/events", url))
+        .send_json(vec![&create])
+        .unwrap();
+    let timestamp = Utc::now();
+
+    // Make a snapshot and push an update
+    ureq::post(&format!("{}/collections/books/snapshots", url))
+        .call()
+        .unwrap();
+    my_book.some_number = 123;
+    let update = Event::<book::Book>::update(Cow::Owned(my_book.clone()));
+    ureq::post(&format!("{}/collections/books/events", url))
+        .send_json(vec![&update])
+        .unwrap();
+
+    // Invalid events are rejected
+    let mut unknown_book = my_book.clone();
+    unknown_book.uuid = Uuid::new_v4();
+    let error = ureq::post(&format!("{}/collections/books/events", url))
+        .send_json(vec![Event::<book::Book>::update(Cow::Owned(unknown_book))])
+        .map(|_| ())
+        .unwrap_err();
+    assert!(matches!(error, ureq::Error::Status(422, _)));
+
+    // Documents without keys are rejected
+    let keyless =
+        Event::<Document>::create(Cow::Owned(Document::from(serde_json::json!({ "a": 1 }))));
+    let error = ureq::post(&format!("{}/collections/books/events", url))
+        .send_json(vec![keyless])
+        .map(|_| ())
+        .unwrap_err();
+    assert!(matches!(error, ureq::Error::Status(422, _)));
+
+    // The collection is listed
+    let collections: Vec<String> = ureq::get(&format!("{}/collections", url))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(collections, vec![String::from("books")]);
+
+    // Fetch the events
+    let events: Vec<Event<book::Book>> = ureq::get(&format!("{}/collections/books/events", url))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(events, vec![create, update.clone()]);
+    let events: Vec<Event<book::Book>> = ureq::get(&format!("{}/collections/books/events", url))
+        .query("from", &format(&timestamp))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(events, vec![update]);
+
+    // Fetch the current and a previous projection
+    let projection: Vec<book::Book> = ureq::get(&format!("{}/collections/books/projection", url))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(projection.first().unwrap().some_number, 123);
+    let projection: Vec<book::Book> = ureq::get(&format!("{}/collections/books/projection", url))
+        .query("at", &format(&timestamp))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(projection.first().unwrap().some_number, 42);
+
+    // Fetch the snapshots
+    let snapshots: Vec<SnapshotInfo> = ureq::get(&format!("{}/collections/books/snapshots", url))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(
+        snapshots[1].snapshot,
+        vec![Document::from(serde_json::to_value(&my_book).unwrap())]
+    );
+}
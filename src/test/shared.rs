@@ -0,0 +1,112 @@
+use super::book::{self, new_book};
+use crate::events::{Event, SharedProjector};
+use chrono::Utc;
+use std::{borrow::Cow, sync::Arc, thread};
+use uuid::Uuid;
+
+#[test]
+fn test_shared_projector() {
+    // Create a new book
+    let mut my_book = new_book(0);
+
+    // Create a new shared projector of type `Book` containing the book
+    let books = Arc::new(SharedProjector::<book::Book>::new());
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    books.make_snapshot();
+
+    // Take a view before modifying the book
+    let view = books.view();
+    let timestamp = Utc::now();
+
+    // Modify the book concurrently to several readers
+    let writer = {
+        let books = books.clone();
+        let mut my_book = my_book.clone();
+        thread::spawn(move || {
+            for some_number in 1..=100 {
+                my_book.some_number = some_number;
+                books
+                    .push(Event::update(Cow::Owned(my_book.clone())))
+                    .unwrap();
+            }
+        })
+    };
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let books = books.clone();
+            thread::spawn(move || {
+                let mut last_number = 0;
+                for _ in 0..100 {
+                    // Every view is consistent, and views never go back in time
+                    let view = books.view();
+                    let some_number = view.get_projection().first().unwrap().some_number;
+                    assert!(some_number >= last_number);
+                    last_number = some_number;
+                }
+            })
+        })
+        .collect();
+    writer.join().unwrap();
+    readers.into_iter().for_each(|r| r.join().unwrap());
+
+    // The old view is unaffected by the modifications
+    assert_eq!(view.get_projection().first().unwrap().some_number, 0);
+
+    // The current view contains all modifications, and the history is available
+    let current = books.view();
+    assert_eq!(current.get_projection().first().unwrap().some_number, 100);
+    assert_eq!(
+        current
+            .project_at(&timestamp)
+            .unwrap()
+            .first()
+            .unwrap()
+            .some_number,
+        0
+    );
+    assert_eq!(current.get_events_from(&timestamp).len(), 100);
+
+    // Sealed segments are shared between the views
+    assert!(Arc::ptr_eq(
+        &view.get_segments()[0],
+        &current.get_segments()[0]
+    ));
+    assert!(!Arc::ptr_eq(
+        &view.get_segments()[1],
+        &current.get_segments()[1]
+    ));
+
+    // Invalid events are rejected
+    my_book.uuid = Uuid::new_v4();
+    assert!(books.push(Event::update(Cow::Owned(my_book))).is_err());
+    assert_eq!(current.to_projector().get_projection().len(), 1);
+}
+
+#[test]
+fn test_shared_projector_copy_on_write() {
+    let mut my_book = new_book(0);
+    let books = SharedProjector::<book::Book>::new();
+    let latest = Arc::as_ptr(books.view().get_segments().last().unwrap());
+
+    // The latest segment isn't copied if no view uses it
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    let view = books.view();
+    assert_eq!(Arc::as_ptr(view.get_segments().last().unwrap()), latest);
+
+    // Otherwise it's copied, leaving the view unaffected
+    my_book.some_number = 1;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    assert_eq!(Arc::as_ptr(view.get_segments().last().unwrap()), latest);
+    assert_eq!(view.get_projection()[0].some_number, 0);
+    assert_eq!(books.view().get_segments()[0].get_events().len(), 2);
+    assert_ne!(
+        Arc::as_ptr(books.view().get_segments().last().unwrap()),
+        latest
+    );
+}
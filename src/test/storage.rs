@@ -0,0 +1,248 @@
+use super::book::{self, new_book};
+use crate::events::{AsyncProjector, AsyncStorage, Event, FileStorage, MemoryStorage, Segment};
+use anyhow::{bail, Result};
+use futures::{future::BoxFuture, FutureExt, StreamExt};
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_memory_storage() {
+    let mut my_book = new_book(42);
+
+    // Load a new projector from an empty storage
+    let mut books = AsyncProjector::load(MemoryStorage::<book::Book>::new())
+        .await
+        .unwrap();
+    assert_eq!(books.get_projection().len(), 0);
+    let start = *books.first().unwrap().get_time();
+
+    // Create and modify a book, making a snapshot in between
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .await
+        .unwrap();
+    books.make_snapshot().await.unwrap();
+    my_book.some_number = 123;
+    books
+        .transaction(|tx| {
+            tx.update(Cow::Owned(my_book.clone()));
+        })
+        .await
+        .unwrap();
+
+    // Invalid events are neither applied nor persisted
+    assert!(books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .await
+        .is_err());
+
+    // The events can be streamed
+    let events: Vec<_> = books.stream_events_from(&start).collect().await;
+    assert_eq!(events.len(), 2);
+
+    // Reloading the storage yields the same projector
+    let (projector, storage) = books.take();
+    let books = AsyncProjector::load(storage).await.unwrap();
+    assert_eq!(books.len(), projector.len());
+    assert_eq!(books.get_projection().first().unwrap().some_number, 123);
+}
+
+#[tokio::test]
+async fn test_file_storage() {
+    let mut my_book = new_book(42);
+    let directory = std::env::temp_dir().join(format!("libocc-{}", Uuid::new_v4()));
+
+    // Load a new projector from an empty directory
+    let mut books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+
+    // Create a book, make a snapshot and modify it
+    books
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .await
+        .unwrap();
+    books.make_snapshot().await.unwrap();
+    my_book.some_number = 123;
+    books
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .await
+        .unwrap();
+
+    // Reloading the directory yields the same projector
+    let books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+    assert_eq!(books.len(), 2);
+    assert_eq!(books.get_projection().first().unwrap().some_number, 123);
+
+    // Storing the segments again without clearing the appended events (as if
+    // interrupted) doesn't replay them twice
+    let appended = std::fs::read(directory.join("events.jsonl")).unwrap();
+    let mut books = books;
+    books.make_snapshot().await.unwrap();
+    std::fs::write(directory.join("events.jsonl"), appended).unwrap();
+    let books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+    assert_eq!(books.len(), 3);
+    assert_eq!(books.get_events_from(books[0].get_time()).len(), 2);
+
+    // Modify the book twice atomically
+    let mut books = books;
+    books
+        .transaction(|tx| {
+            my_book.some_number = 1;
+            tx.update(Cow::Owned(my_book.clone()));
+            my_book.some_number = 2;
+            tx.update(Cow::Owned(my_book.clone()));
+        })
+        .await
+        .unwrap();
+
+    // Reloading the directory keeps both events of the transaction
+    let books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+    assert_eq!(books.last().unwrap().get_events().len(), 2);
+    assert_eq!(books.get_projection().first().unwrap().some_number, 2);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+async fn test_interrupted_append() {
+    let directory = std::env::temp_dir().join(format!("libocc-{}", Uuid::new_v4()));
+    let events = directory.join("events.jsonl");
+    let mut books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+    books
+        .push(Event::create(Cow::Owned(new_book(42))))
+        .await
+        .unwrap();
+    let appended = std::fs::read(&events).unwrap();
+
+    // Interrupt appending the events of a transaction halfway through
+    books
+        .transaction(|tx| {
+            tx.create(Cow::Owned(new_book(1)));
+            tx.create(Cow::Owned(new_book(2)));
+        })
+        .await
+        .unwrap();
+    let written = std::fs::read(&events).unwrap();
+    let torn = appended.len() + (written.len() - appended.len()) / 2;
+    std::fs::write(&events, &written[..torn]).unwrap();
+
+    // Reloading discards all events of the transaction
+    let mut books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+    assert_eq!(books.get_projection().len(), 1);
+
+    // Further events are appended as usual
+    books
+        .push(Event::create(Cow::Owned(new_book(7))))
+        .await
+        .unwrap();
+    let books = AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+        .await
+        .unwrap();
+    assert_eq!(books.get_projection().len(), 2);
+
+    // Unreadable lines before the final one can't be discarded
+    let mut corrupt = b"{\n".to_vec();
+    corrupt.extend(std::fs::read(&events).unwrap());
+    std::fs::write(&events, corrupt).unwrap();
+    assert!(
+        AsyncProjector::<book::Book, _>::load(FileStorage::new(&directory))
+            .await
+            .is_err()
+    );
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+/// A storage failing to persist anything once it's broken
+struct BrokenStorage {
+    storage: MemoryStorage<book::Book>,
+    broken: AtomicBool,
+}
+
+impl AsyncStorage<book::Book> for BrokenStorage {
+    fn load(&self) -> BoxFuture<'_, Result<Vec<Segment<'static, book::Book>>>> {
+        self.storage.load()
+    }
+
+    fn append_all<'b>(
+        &'b self,
+        events: &'b [Event<'static, book::Book>],
+    ) -> BoxFuture<'b, Result<()>> {
+        if self.broken.load(Ordering::SeqCst) {
+            return async { bail!("Cannot append to a broken storage") }.boxed();
+        }
+        self.storage.append_all(events)
+    }
+
+    fn store<'b>(
+        &'b self,
+        segments: &'b [Arc<Segment<'static, book::Book>>],
+    ) -> BoxFuture<'b, Result<()>> {
+        if self.broken.load(Ordering::SeqCst) {
+            return async { bail!("Cannot store to a broken storage") }.boxed();
+        }
+        self.storage.store(segments)
+    }
+}
+
+#[tokio::test]
+async fn test_failed_persistence() {
+    let storage = BrokenStorage {
+        storage: MemoryStorage::new(),
+        broken: AtomicBool::new(false),
+    };
+    let mut books = AsyncProjector::load(storage).await.unwrap();
+    books.add_index("number", |b: &book::Book| b.some_number.to_string());
+    let changes = books.subscribe();
+    books
+        .push(Event::create(Cow::Owned(new_book(42))))
+        .await
+        .unwrap();
+
+    assert_eq!(changes.try_iter().count(), 1);
+
+    // Failing to persist rolls the operations back, without notifying the subscribers
+    books.get_storage().broken.store(true, Ordering::SeqCst);
+    assert!(books
+        .push(Event::create(Cow::Owned(new_book(7))))
+        .await
+        .is_err());
+    assert!(books
+        .transaction(|tx| {
+            tx.create(Cow::Owned(new_book(8)));
+        })
+        .await
+        .is_err());
+    assert!(books.make_snapshot().await.is_err());
+    assert_eq!(books.len(), 1);
+    assert_eq!(books.get_projection().len(), 1);
+    assert_eq!(changes.try_iter().count(), 0);
+
+    // The indexes and subscribers are kept
+    books.get_storage().broken.store(false, Ordering::SeqCst);
+    books
+        .push(Event::create(Cow::Owned(new_book(7))))
+        .await
+        .unwrap();
+    assert_eq!(books.lookup("number", "7").unwrap().len(), 1);
+    let received: Vec<_> = changes.try_iter().collect();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].get_new().unwrap().some_number, 7);
+}
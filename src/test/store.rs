@@ -0,0 +1,147 @@
+use super::{
+    book::{self, new_book},
+    person,
+};
+use crate::events::{Event, Store};
+use std::borrow::Cow;
+use uuid::Uuid;
+
+#[test]
+fn test_store() {
+    // Create a new author and a new book written by them
+    let author = person::Person {
+        uuid: Uuid::new_v4(),
+        first_name: String::from("Alex"),
+        last_name: String::from("Example"),
+    };
+    let mut my_book = book::Book {
+        uuid: Uuid::new_v4(),
+        some_number: 42,
+        author: author.clone(),
+    };
+
+    // Create a new store hosting both entity types
+    let mut store = Store::new();
+    store.register::<person::Person>();
+
+    // So far, the store doesn't contain any books
+    assert!(store.collection::<book::Book>().is_none());
+    assert_eq!(store.collection::<person::Person>().unwrap().len(), 1);
+
+    // Create the author and their book together
+    assert_eq!(store.create(author).unwrap(), 0);
+    assert_eq!(store.create(my_book.clone()).unwrap(), 1);
+
+    // Modify the book, subscribing to the changes and indexing the books first
+    let changes = store.subscribe::<book::Book>();
+    store.add_index::<book::Book>("number", |b| b.some_number.to_string());
+    my_book.some_number = 123;
+    assert_eq!(store.update(my_book).unwrap(), 2);
+    assert_eq!(changes.try_iter().count(), 1);
+    let books = store.collection::<book::Book>().unwrap();
+    assert_eq!(books.lookup("number", "123").unwrap().len(), 1);
+
+    // Both collections contain their entities
+    let people = store.collection::<person::Person>().unwrap();
+    let books = store.collection::<book::Book>().unwrap();
+    assert_eq!(people.get_projection().len(), 1);
+    assert_eq!(books.get_projection().first().unwrap().some_number, 123);
+
+    // The global sequence orders the events of both collections
+    let log = store.get_log();
+    assert_eq!(log.len(), 3);
+    assert!(log[0].is::<person::Person>());
+    assert!(log[1].is::<book::Book>());
+    assert!(log[2].is::<book::Book>());
+    assert!(log.windows(2).all(|w| w[0].get_time() < w[1].get_time()));
+
+    // Events predating the latest event of the store are rejected
+    let timestamp = *log[0].get_time();
+    let late_author = person::Person {
+        uuid: Uuid::new_v4(),
+        first_name: String::from("Late"),
+        last_name: String::from("Example"),
+    };
+    assert!(store
+        .push(Event::create_at(
+            Cow::<person::Person>::Owned(late_author),
+            timestamp
+        ))
+        .is_err());
+    assert_eq!(store.get_log().len(), 3);
+
+    // Rejected events don't register their collections
+    assert!(store.update(String::from("unknown")).is_err());
+    assert!(store.collection::<String>().is_none());
+}
+
+#[test]
+fn test_store_transaction() {
+    let author = person::Person {
+        uuid: Uuid::new_v4(),
+        first_name: String::from("Alex"),
+        last_name: String::from("Example"),
+    };
+    let mut my_book = new_book(42);
+    let mut store = Store::new();
+
+    // Create the author and their book atomically
+    let sequences = store
+        .transaction(|t| {
+            t.create(author.clone()).create(my_book.clone());
+        })
+        .unwrap();
+    assert_eq!(sequences, vec![0, 1]);
+    assert_eq!(store.collection::<person::Person>().unwrap().len(), 1);
+    assert_eq!(store.collection::<book::Book>().unwrap().len(), 1);
+    assert_eq!(store.get_log()[0].get_time(), store.get_log()[1].get_time());
+
+    // If the events of any collection fail, none of them are applied
+    my_book.some_number = 123;
+    assert!(store
+        .transaction(|t| {
+            t.update(my_book.clone()).update(new_book(7));
+        })
+        .is_err());
+    let books = store.collection::<book::Book>().unwrap();
+    assert_eq!(books.get_projection()[0].some_number, 42);
+    assert_eq!(store.get_log().len(), 2);
+
+    // This includes collections which weren't registered before
+    assert!(store
+        .transaction(|t| {
+            t.update(my_book.clone()).delete(42_u32);
+        })
+        .is_err());
+    assert!(store.collection::<u32>().is_none());
+    assert_eq!(store.get_log().len(), 2);
+
+    // The events of all collections are committed along with their transaction ids
+    let sequences = store
+        .transaction(|t| {
+            t.update(my_book.clone()).delete(author.clone());
+        })
+        .unwrap();
+    assert_eq!(sequences, vec![2, 3]);
+    let books = store.collection::<book::Book>().unwrap();
+    assert_eq!(books.get_projection()[0].some_number, 123);
+    assert!(store
+        .collection::<person::Person>()
+        .unwrap()
+        .get_projection()
+        .is_empty());
+    assert_eq!(books[0].get_events()[1].get_transaction(), Some(1));
+
+    // The transaction id is shared by the events of all collections
+    let people = store.collection::<person::Person>().unwrap();
+    assert_eq!(people[0].get_events()[1].get_transaction(), Some(1));
+    let log = store.get_log();
+    assert_eq!(log[0].get_transaction(), Some(0));
+    assert_eq!(log[1].get_transaction(), Some(0));
+    assert_eq!(log[2].get_transaction(), Some(1));
+    assert_eq!(log[3].get_transaction(), Some(1));
+
+    // Events outside of transactions aren't part of any
+    let sequence = store.create(new_book(7)).unwrap();
+    assert_eq!(store.get_log()[sequence as usize].get_transaction(), None);
+}
@@ -0,0 +1,139 @@
+use super::book::{self, new_book};
+use crate::{
+    events::{Event, Projector},
+    sync::{LoopbackTransport, SyncClient, Transport},
+};
+use std::{borrow::Cow, thread, time};
+
+/// Lets two replicas edit a collection offline and sync it using given transports
+fn sync_replicas<X>(first_transport: X, second_transport: X)
+where
+    X: Transport<book::Book> + Clone,
+{
+    let mut my_book = new_book(42);
+
+    // Create two replicas with their own clients
+    let mut first = Projector::<book::Book>::new();
+    let mut second = Projector::<book::Book>::new();
+    let mut first_client = SyncClient::new(first_transport);
+    let mut second_client = SyncClient::new(second_transport);
+
+    // The first replica creates a book and syncs it to the second one
+    first
+        .push(Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+    assert!(first_client.sync(&mut first).unwrap().is_empty());
+    let changes = second_client.sync(&mut second).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(second.get_projection().first().unwrap().some_number, 42);
+
+    // Both replicas make changes while being offline
+    thread::sleep(time::Duration::from_millis(1));
+    my_book.some_number = 123;
+    second
+        .push(Event::update(Cow::Owned(my_book.clone())))
+        .unwrap();
+    first.push(Event::create(Cow::Owned(new_book(7)))).unwrap();
+
+    // Syncing both of them lets them converge
+    second_client.sync(&mut second).unwrap();
+    first_client.sync(&mut first).unwrap();
+    second_client.sync(&mut second).unwrap();
+    assert_eq!(first.get_projection().len(), 2);
+    assert_eq!(
+        first.get_projection().first().unwrap().some_number,
+        second.get_projection().first().unwrap().some_number
+    );
+    assert_eq!(first.get_projection().first().unwrap().some_number, 123);
+    assert_eq!(
+        first.get_projection().get(1).unwrap().uuid,
+        second.get_projection().get(1).unwrap().uuid
+    );
+    assert_eq!(
+        first_client.get_frontier().pulled,
+        second_client.get_frontier().pulled
+    );
+
+    // Events created offline are pulled, even though they predate the latest events pulled
+    let mut offline = Projector::<book::Book>::new();
+    offline
+        .push(Event::create(Cow::Owned(new_book(1))))
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(1));
+    first.push(Event::create(Cow::Owned(new_book(2)))).unwrap();
+    first_client.sync(&mut first).unwrap();
+    second_client.sync(&mut second).unwrap();
+    SyncClient::new(first_client.get_transport().clone())
+        .push(&offline)
+        .unwrap();
+    second_client.sync(&mut second).unwrap();
+    assert_eq!(second.get_projection().len(), 4);
+
+    // Pulling everything again doesn't change anything
+    assert!(second_client.pull_all(&mut second).unwrap().is_empty());
+    assert_eq!(
+        first_client.get_frontier().pulled + 1,
+        second_client.get_frontier().pulled
+    );
+
+    // Local events inserted before the latest pushed event are pushed as well
+    let mut other = Projector::<book::Book>::new();
+    other.push(Event::create(Cow::Owned(new_book(3)))).unwrap();
+    thread::sleep(time::Duration::from_millis(1));
+    first.push(Event::create(Cow::Owned(new_book(4)))).unwrap();
+    first_client.sync(&mut first).unwrap();
+    first
+        .merge(
+            other
+                .get_events_from(other[0].get_time())
+                .into_iter()
+                .cloned()
+                .collect(),
+        )
+        .unwrap();
+    first_client.sync(&mut first).unwrap();
+    second_client.sync(&mut second).unwrap();
+    assert_eq!(second.get_projection().len(), 6);
+}
+
+#[test]
+fn test_loopback_sync() {
+    let transport = LoopbackTransport::new();
+    sync_replicas(transport.clone(), transport.clone());
+
+    // The remote replica contains all events
+    assert_eq!(
+        transport
+            .get_remote()
+            .lock()
+            .unwrap()
+            .get_projection()
+            .len(),
+        6
+    );
+}
+
+#[cfg(all(feature = "server", feature = "client"))]
+#[test]
+fn test_http_sync() {
+    use crate::sync::HttpTransport;
+
+    let url = format!("http://{}", super::server::start_server());
+    sync_replicas(
+        HttpTransport::new(&url, "books"),
+        HttpTransport::new(&url, "books"),
+    );
+
+    // Collection names are encoded, so they may contain any characters
+    let name = "my books/2024?draft#1";
+    sync_replicas(
+        HttpTransport::new(&url, name),
+        HttpTransport::new(&url, name),
+    );
+    let names: Vec<String> = ureq::get(&format!("{}/collections", url))
+        .call()
+        .unwrap()
+        .into_json()
+        .unwrap();
+    assert_eq!(names, vec![String::from("books"), String::from(name)]);
+}
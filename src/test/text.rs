@@ -0,0 +1,220 @@
+use crate::{
+    crdt::{Crdt, Text, TextEdit, TextProjection},
+    events::{Event, Projector},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, thread, time};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Document {
+    uuid: Uuid,
+    body: Text,
+}
+
+impl PartialEq for Document {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+impl Crdt for Document {
+    fn merge(&mut self, other: &Self) {
+        self.body.merge(&other.body);
+    }
+}
+
+#[test]
+fn test_text_operations() {
+    let mut first = Text::new();
+    first.insert(0, "Hello", "first").unwrap();
+    let mut second = first.clone();
+    assert!(first.insert(6, "!", "first").is_err());
+
+    // Both replicas edit the text concurrently
+    let mut first_ops = first.insert(5, " world", "first").unwrap();
+    first_ops.extend(first.delete(0, 1).unwrap());
+    first_ops.extend(first.insert(0, "J", "first").unwrap());
+    let second_ops = second.insert(5, "!", "second").unwrap();
+    assert_eq!(first.to_string(), "Jello world");
+    assert_eq!(second.to_string(), "Hello!");
+
+    // Applying the operations of the other replica converges (even twice)
+    for op in &second_ops {
+        first.apply(op).unwrap();
+    }
+    for op in first_ops.iter().chain(first_ops.iter()) {
+        second.apply(op).unwrap();
+    }
+    assert_eq!(first.to_string(), second.to_string());
+    assert_eq!(first.len(), 12);
+
+    // Operations referring to unknown characters fail
+    let mut empty = Text::new();
+    assert!(empty.apply(&second_ops[0]).is_err());
+    assert!(empty.is_empty());
+
+    // Applying several operations at once yields the same text as applying them one by one
+    let mut third = first.clone();
+    let mut fourth = first.clone();
+    let mut ops = first.insert(3, "pasted text", "first").unwrap();
+    ops.extend(first.delete(2, 4).unwrap());
+    ops.extend(second.insert(3, "concurrently pasted", "second").unwrap());
+    assert!(third.apply_all(&ops).is_empty());
+    assert!(third.apply_all(&ops).is_empty());
+    for op in &ops {
+        fourth.apply(op).unwrap();
+    }
+    assert_eq!(third.to_string(), fourth.to_string());
+    assert_eq!(Text::new().apply_all(&ops), ops);
+}
+
+#[test]
+fn test_text_events() {
+    let mut first = Projector::<Document>::new();
+    let mut second = Projector::<Document>::new();
+
+    // The first replica creates a document, which the second one receives
+    let mut document = Document {
+        uuid: Uuid::new_v4(),
+        body: Text::new(),
+    };
+    document.body.insert(0, "Hello", "first").unwrap();
+    let create = first.stamp(Event::create(Cow::Owned(document.clone())), "first");
+    first.push(create.clone()).unwrap();
+    second.merge_crdt(vec![create], "second").unwrap();
+    let timestamp = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    first.make_snapshot();
+    second.make_snapshot();
+
+    // Both replicas edit the text concurrently
+    document.body.insert(5, " world", "first").unwrap();
+    let first_update = first.stamp(Event::update(Cow::Owned(document)), "first");
+    first.push(first_update.clone()).unwrap();
+
+    let mut other = second.get_projection()[0].clone().into_owned();
+    other.body.delete(0, 1).unwrap();
+    other.body.insert(0, "J", "second").unwrap();
+    let second_update = second.stamp(Event::update(Cow::Owned(other)), "second");
+    second.push(second_update.clone()).unwrap();
+
+    // Exchanging the updates merges the edits instead of overwriting them
+    first.merge_crdt(vec![second_update], "first").unwrap();
+    second.merge_crdt(vec![first_update], "second").unwrap();
+    assert_eq!(first.get_projection()[0].body.to_string(), "Jello world");
+    assert_eq!(second.get_projection()[0].body.to_string(), "Jello world");
+
+    // Previous versions of the text are still available
+    assert_eq!(
+        first.project_at(&timestamp).unwrap()[0].body.to_string(),
+        "Hello"
+    );
+}
+
+#[test]
+fn test_text_edit_events() {
+    let mut first = Projector::<TextEdit>::new();
+    let mut second = Projector::<TextEdit>::new();
+    first.add_projection("texts", TextProjection::default());
+    second.add_projection("texts", TextProjection::default());
+    let text = |projector: &Projector<TextEdit>| -> Text {
+        let texts: &TextProjection = projector.get_custom_projection("texts").unwrap();
+        texts.get("notes").cloned().unwrap_or_default()
+    };
+
+    // The first replica creates a text, which the second one receives
+    let mut body = Text::new();
+    let operations = body.insert(0, "Hello", "first").unwrap();
+    let create = first.stamp(
+        Event::create(Cow::Owned(TextEdit::new("notes", operations))),
+        "first",
+    );
+    first.push(create.clone()).unwrap();
+    second.merge(vec![create]).unwrap();
+    assert_eq!(text(&second).to_string(), "Hello");
+    let timestamp = Utc::now();
+    thread::sleep(time::Duration::from_millis(1));
+    first.make_snapshot();
+    second.make_snapshot();
+
+    // Both replicas edit the text concurrently, pushing only their operations
+    let mut first_updates = vec![];
+    for (index, value) in " world".chars().enumerate() {
+        let mut body = text(&first);
+        let operations = body.insert(5 + index, &value.to_string(), "first").unwrap();
+        let update = first.stamp(
+            Event::update(Cow::Owned(TextEdit::new("notes", operations))),
+            "first",
+        );
+        assert_eq!(update.get_operations().len(), 1);
+        first.push(update.clone()).unwrap();
+        first_updates.push(update);
+    }
+    let mut body = text(&second);
+    let mut operations = body.delete(0, 1).unwrap();
+    operations.extend(body.insert(0, "J", "second").unwrap());
+    let second_update = second.stamp(
+        Event::update(Cow::Owned(TextEdit::new("notes", operations))),
+        "second",
+    );
+    second.push(second_update.clone()).unwrap();
+
+    // Exchanging the edits merges them instead of overwriting them
+    first.merge(vec![second_update]).unwrap();
+    second.merge(first_updates).unwrap();
+    assert_eq!(text(&first).to_string(), "Jello world");
+    assert_eq!(text(&second).to_string(), "Jello world");
+
+    // Previous versions of the text are still available
+    let texts: TextProjection = first
+        .project_custom_at("texts", &timestamp)
+        .unwrap()
+        .unwrap();
+    assert_eq!(texts.get("notes").unwrap().to_string(), "Hello");
+}
+
+#[test]
+fn test_text_offline_creation() {
+    let mut first = Projector::<TextEdit>::new();
+    let mut second = Projector::<TextEdit>::new();
+    first.add_projection("texts", TextProjection::default());
+    second.add_projection("texts", TextProjection::default());
+    let text = |projector: &Projector<TextEdit>| -> String {
+        let texts: &TextProjection = projector.get_custom_projection("texts").unwrap();
+        texts.get("notes").unwrap().to_string()
+    };
+    let events = |projector: &Projector<'static, TextEdit>| -> Vec<Event<'static, TextEdit>> {
+        projector
+            .iter()
+            .flat_map(|s| s.get_events())
+            .cloned()
+            .collect()
+    };
+
+    // Both replicas create the same text and edit it while being offline
+    first.insert(TextEdit::create_event("notes")).unwrap();
+    let operations = Text::new().insert(0, "a", "first").unwrap();
+    let update = first.stamp(
+        Event::update(Cow::Owned(TextEdit::new("notes", operations))),
+        "first",
+    );
+    first.push(update).unwrap();
+    second.insert(TextEdit::create_event("notes")).unwrap();
+    let operations = Text::new().insert(0, "b", "second").unwrap();
+    let update = second.stamp(
+        Event::update(Cow::Owned(TextEdit::new("notes", operations))),
+        "second",
+    );
+    second.push(update).unwrap();
+
+    // Exchanging all events merges both creations into one
+    let (first_events, second_events) = (events(&first), events(&second));
+    first.merge(second_events).unwrap();
+    second.merge(first_events).unwrap();
+    assert_eq!(first.get_projection().len(), 1);
+    assert_eq!(events(&first).len(), 3);
+    assert_eq!(text(&first), text(&second));
+    assert_eq!(text(&first).len(), 2);
+}
@@ -0,0 +1,104 @@
+use super::book::{self, new_book};
+use crate::events::Projector;
+use std::borrow::Cow;
+
+#[test]
+fn test_transaction_commit() {
+    let mut first_book = new_book(1);
+    let second_book = new_book(2);
+
+    // Create a new projector of type `Book`
+    let mut books = Projector::<book::Book>::new();
+
+    // Create two books and modify one of them atomically
+    let id = books
+        .transaction(|tx| {
+            tx.create(Cow::Owned(first_book.clone()))
+                .create(Cow::Owned(second_book.clone()));
+            first_book.some_number = 42;
+            tx.update(Cow::Owned(first_book.clone()));
+        })
+        .unwrap();
+
+    // All events were applied
+    assert_eq!(books.get_projection().len(), 2);
+    assert_eq!(books.get_projection().first().unwrap().some_number, 42);
+
+    // All events share the transaction id and the timestamp
+    let events = books.last().unwrap().get_events();
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|e| e.get_transaction() == Some(id)));
+    assert!(events.iter().all(|e| e.get_time() == events[0].get_time()));
+
+    // The next transaction gets a new id
+    let next_id = books
+        .transaction(|tx| {
+            tx.delete(Cow::Owned(second_book.clone()));
+        })
+        .unwrap();
+    assert_ne!(id, next_id);
+    assert_eq!(books.get_projection().len(), 1);
+}
+
+#[test]
+fn test_transaction_rollback() {
+    let my_book = new_book(1);
+    let other_book = new_book(2);
+
+    // Create a new projector of type `Book`
+    let mut books = Projector::<book::Book>::new();
+    books
+        .push(crate::events::Event::create(Cow::Owned(my_book.clone())))
+        .unwrap();
+
+    // The third event fails, as the book doesn't exist
+    assert!(books
+        .transaction(|tx| {
+            tx.delete(Cow::Owned(my_book.clone()))
+                .create(Cow::Owned(other_book.clone()))
+                .update(Cow::Owned(new_book(3)));
+        })
+        .is_err());
+
+    // None of the events were applied
+    assert_eq!(books.get_projection().len(), 1);
+    assert_eq!(books.get_projection().first().unwrap().uuid, my_book.uuid);
+    assert_eq!(books.last().unwrap().get_events().len(), 1);
+}
+
+#[test]
+fn test_transaction_merge() {
+    let mut my_book = new_book(1);
+
+    // Create a new projector of type `Book`
+    let mut books = Projector::<book::Book>::new();
+
+    // Create a book and modify it twice atomically
+    books
+        .transaction(|tx| {
+            tx.create(Cow::Owned(my_book.clone()));
+            my_book.some_number = 2;
+            tx.update(Cow::Owned(my_book.clone()));
+            my_book.some_number = 3;
+            tx.update(Cow::Owned(my_book.clone()));
+        })
+        .unwrap();
+
+    // The events are told apart by their positions within the transaction
+    let events = books.last().unwrap().get_events().clone();
+    let positions: Vec<_> = events
+        .iter()
+        .map(|e| e.get_transaction_position())
+        .collect();
+    assert_eq!(positions, vec![Some(0), Some(1), Some(2)]);
+
+    // A fresh replica merges all events of the transaction
+    let mut replica = Projector::<book::Book>::new();
+    replica.merge(events.clone()).unwrap();
+    assert_eq!(replica.last().unwrap().get_events().len(), 3);
+    assert_eq!(replica.get_projection()[0].some_number, 3);
+
+    // Merging them again doesn't change anything
+    assert!(replica.merge(events).unwrap().is_empty());
+    assert_eq!(replica.last().unwrap().get_events().len(), 3);
+}
@@ -0,0 +1,49 @@
+use crate::tree::{MoveOperation, Tree};
+
+#[test]
+fn test_tree_moves() {
+    let mut tree = Tree::<&str, String>::new();
+    tree.move_node("root", String::from("a"), "a", "first");
+    tree.move_node("root", String::from("b"), "b", "first");
+    tree.move_node("a", String::from("c"), "c", "first");
+    assert_eq!(tree.get_children(&"root"), vec![&"a", &"b"]);
+    assert_eq!(tree.get_parent(&"c"), Some(&"a"));
+    assert_eq!(tree.get_metadata(&"c").unwrap(), "c");
+    assert!(tree.is_ancestor(&"root", &"c"));
+    assert_eq!(tree.get_parent(&"root"), None);
+
+    // Moving a node below one of its descendants is skipped
+    tree.move_node("c", String::from("a"), "a", "first");
+    assert_eq!(tree.get_parent(&"a"), Some(&"root"));
+    assert_eq!(tree.get_operations().len(), 4);
+}
+
+#[test]
+fn test_concurrent_tree_moves() {
+    let mut first = Tree::<&str, ()>::new();
+    first.move_node("root", (), "a", "first");
+    first.move_node("root", (), "b", "first");
+    let mut second = Tree::new();
+    second.apply_all(first.get_operations().into_iter().cloned().collect());
+
+    // Both replicas move the nodes below each other
+    let first_move = first.move_node("b", (), "a", "first");
+    let second_move = second.move_node("a", (), "b", "second");
+    first.apply(second_move.clone());
+    second.apply(first_move.clone());
+
+    // Both converge to the same acyclic tree (the later move is skipped)
+    assert_eq!(first, second);
+    assert!(!(first.is_ancestor(&"a", &"b") && first.is_ancestor(&"b", &"a")));
+    assert_eq!(first.get_parent(&"a"), Some(&"b"));
+    assert_eq!(first.get_parent(&"b"), Some(&"root"));
+
+    // The order of arrival doesn't matter, and duplicates are ignored
+    let mut operations: Vec<MoveOperation<&str, ()>> =
+        first.get_operations().into_iter().cloned().collect();
+    operations.reverse();
+    operations.push(second_move);
+    let mut third = Tree::new();
+    third.apply_all(operations);
+    assert_eq!(third, first);
+}
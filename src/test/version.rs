@@ -0,0 +1,134 @@
+use super::book::{self, new_book};
+use crate::events::{Event, Projector, VersionVector};
+use chrono::Duration;
+use std::{borrow::Cow, cmp::Ordering};
+
+#[test]
+fn test_version_vectors() {
+    let mut first = VersionVector::new();
+    let mut second = VersionVector::new();
+    assert_eq!(first.partial_cmp(&second), Some(Ordering::Equal));
+
+    // Counting events of one replica makes its version greater
+    assert_eq!(first.increment("a"), 1);
+    assert!(second < first);
+
+    // Counting events of different replicas makes them concurrent
+    second.increment("b");
+    assert!(first.is_concurrent_with(&second));
+
+    // Merging yields a version following both
+    first.merge(&second);
+    assert!(second < first);
+    assert_eq!(first.get("a"), 1);
+    assert_eq!(first.get("b"), 1);
+    assert_eq!(first.get("c"), 0);
+
+    // Version vectors are written as maps
+    let json = serde_json::to_value(&first).unwrap();
+    assert_eq!(json, serde_json::json!({ "a": 1, "b": 1 }));
+    assert_eq!(
+        serde_json::from_value::<VersionVector>(json).unwrap(),
+        first
+    );
+
+    // Replicas without any events are omitted, so equal versions are equal
+    let json = serde_json::json!({ "a": 1, "b": 1, "c": 0 });
+    let version = serde_json::from_value::<VersionVector>(json).unwrap();
+    assert_eq!(version.partial_cmp(&first), Some(Ordering::Equal));
+    assert_eq!(version, first);
+    assert_eq!(version.get_counters().len(), 2);
+}
+
+#[test]
+fn test_concurrent_events() {
+    let mut my_book = new_book(42);
+    let mut first = Projector::<book::Book>::new();
+    let mut second = Projector::<book::Book>::new();
+
+    // The first replica creates a book, which the second one receives
+    let create = first.stamp(Event::create(Cow::Owned(my_book.clone())), "first");
+    first.push(create.clone()).unwrap();
+    second.merge(vec![create.clone()]).unwrap();
+    assert_eq!(second.get_frontier().get("first"), 1);
+
+    // Both replicas update the book without knowing of each other
+    my_book.some_number = 1;
+    let first_update = first.stamp(Event::update(Cow::Owned(my_book.clone())), "first");
+    first.push(first_update.clone()).unwrap();
+    my_book.some_number = 2;
+    let second_update = second.stamp(Event::update(Cow::Owned(my_book.clone())), "second");
+    second.push(second_update.clone()).unwrap();
+    assert!(create.happened_before(&first_update));
+    assert!(first_update.is_concurrent_with(&second_update));
+    assert!(first.get_conflicts().is_empty());
+
+    // Exchanging the events reveals the conflict on both replicas
+    first.merge(vec![second_update.clone()]).unwrap();
+    second.merge(vec![first_update.clone()]).unwrap();
+    for replica in &[&first, &second] {
+        let conflicts = replica.get_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].get_events(),
+            &vec![first_update.clone(), second_update.clone()]
+        );
+    }
+
+    // Resolving the conflict on one replica resolves it on both
+    my_book.some_number = 3;
+    let resolution = first.stamp(Event::update(Cow::Owned(my_book.clone())), "first");
+    assert_eq!(resolution.get_replica().unwrap(), "first");
+    assert_eq!(resolution.get_version().unwrap().get("second"), 1);
+    first.push(resolution.clone()).unwrap();
+    second.merge(vec![resolution]).unwrap();
+    assert!(first.get_conflicts().is_empty());
+    assert!(second.get_conflicts().is_empty());
+    assert_eq!(second.get_projection()[0].some_number, 3);
+    assert_eq!(first.get_frontier(), second.get_frontier());
+
+    // The frontier is computed again after deserializing
+    let json = serde_json::to_string(&first).unwrap();
+    let restored: Projector<book::Book> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.get_frontier(), first.get_frontier());
+
+    // Events without versions are never concurrent
+    let unversioned = Event::<book::Book>::update(Cow::Owned(my_book));
+    assert!(!unversioned.is_concurrent_with(&first_update));
+    assert!(!unversioned.happened_before(&first_update));
+}
+
+#[test]
+fn test_clock_skew() {
+    let mut my_book = new_book(42);
+    let mut first = Projector::<book::Book>::new();
+    let mut second = Projector::<book::Book>::new();
+
+    // The first replica creates a book, which the second one receives
+    let create = first.stamp(Event::create(Cow::Owned(my_book.clone())), "first");
+    first.push(create.clone()).unwrap();
+    second.merge(vec![create.clone()]).unwrap();
+
+    // The clock of the second replica lags behind, but its update still follows the creation
+    let past = *create.get_time() - Duration::hours(1);
+    my_book.some_number = 1;
+    let update = second.stamp(
+        Event::update_at(Cow::Owned(my_book.clone()), past),
+        "second",
+    );
+    assert!(update.get_time() > create.get_time());
+    second.push(update.clone()).unwrap();
+    first.merge(vec![update.clone()]).unwrap();
+    assert_eq!(first.get_projection()[0].some_number, 1);
+
+    // Events predating the events they causally follow are rejected
+    my_book.some_number = 2;
+    let skewed = Event::<book::Book>::update_at(Cow::Owned(my_book.clone()), past)
+        .with_version("second", update.get_version().unwrap().clone());
+    assert!(create.happened_before(&skewed));
+    assert!(first.merge(vec![skewed.clone()]).is_err());
+    assert!(Projector::<book::Book>::new()
+        .merge(vec![create, skewed])
+        .is_err());
+    assert_eq!(first.get_projection()[0].some_number, 1);
+}
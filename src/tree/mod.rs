@@ -3,8 +3,16 @@ This is the newer implementation of the `libocc` versioning system.
 
 Its tree data structure is generally preferred to the initial implementation
 using projectors and event logs.
+
+Nodes are restructured by [`MoveOperation`]s, which every replica applies in
+the same order, so concurrent moves never create cycles (see [`Tree`]).
+
+[`MoveOperation`]: struct.MoveOperation.html
+[`Tree`]: struct.Tree.html
 */
 
+mod moves;
 mod node;
 
+pub use moves::{MoveOperation, Tree};
 pub use node::Node;
@@ -0,0 +1,264 @@
+use crate::events::{ReplicaId, Timestamp};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/**
+An operation moving a node of a [`Tree`] below a new parent.
+
+Moving a node which isn't part of the tree yet inserts it. Operations are
+ordered by their timestamps (and by the identifiers of their replicas, if
+their timestamps are equal).
+
+[`Tree`]: struct.Tree.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MoveOperation<N, M> {
+    /// The moment in time the node was moved
+    timestamp: Timestamp,
+
+    /// The replica which moved the node
+    replica: ReplicaId,
+
+    /// The new parent of the node
+    parent: N,
+
+    /// The metadata of the node at its new position (e.g. its name)
+    metadata: M,
+
+    /// The moved node
+    child: N,
+}
+
+impl<N, M> MoveOperation<N, M> {
+    /// Creates a new operation moving a node on behalf of a replica
+    pub fn new(parent: N, metadata: M, child: N, replica: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            replica: replica.to_owned(),
+            parent,
+            metadata,
+            child,
+        }
+    }
+
+    /// Returns a reference to the moment in time the node was moved
+    pub fn get_time(&self) -> &Timestamp {
+        &self.timestamp
+    }
+
+    /// Returns a reference to the replica which moved the node
+    pub fn get_replica(&self) -> &ReplicaId {
+        &self.replica
+    }
+
+    /// Returns a reference to the new parent of the node
+    pub fn get_parent(&self) -> &N {
+        &self.parent
+    }
+
+    /// Returns a reference to the metadata of the node at its new position
+    pub fn get_metadata(&self) -> &M {
+        &self.metadata
+    }
+
+    /// Returns a reference to the moved node
+    pub fn get_child(&self) -> &N {
+        &self.child
+    }
+
+    /// Returns the key the operations are ordered by
+    fn key(&self) -> (&Timestamp, &ReplicaId) {
+        (&self.timestamp, &self.replica)
+    }
+}
+
+/// An applied operation, along with the position of the node before it was applied
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct LogEntry<N, M> {
+    operation: MoveOperation<N, M>,
+    old_position: Option<(N, M)>,
+}
+
+/**
+A tree which can be restructured concurrently by moving its nodes.
+
+Replicas moving nodes concurrently (e.g. while offline) could create cycles by
+moving two nodes below each other. To prevent this, all operations are applied
+in the order of their timestamps: Receiving an operation undoes all later ones,
+applies the received one and then redoes the later ones again. Operations which
+would move a node below itself (or one of its descendants) are skipped. So every
+replica converges to the same acyclic hierarchy, regardless of the order in
+which it receives the operations.
+
+Nodes without a parent are roots. Nodes can be deleted by moving them below a
+dedicated trash node.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tree<N, M>
+where
+    N: Ord,
+{
+    /// All applied operations, ordered by their timestamps
+    log: Vec<LogEntry<N, M>>,
+
+    /// The parent and metadata of every node (except for roots)
+    positions: BTreeMap<N, (N, M)>,
+}
+
+impl<N, M> Tree<N, M>
+where
+    N: Clone + Ord,
+    M: Clone + PartialEq,
+{
+    /// Creates a new, empty tree
+    pub fn new() -> Self {
+        Self {
+            log: vec![],
+            positions: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a reference to the parent of a node (if any)
+    pub fn get_parent(&self, node: &N) -> Option<&N> {
+        self.positions.get(node).map(|(parent, _)| parent)
+    }
+
+    /// Returns a reference to the metadata of a node (if it isn't a root)
+    pub fn get_metadata(&self, node: &N) -> Option<&M> {
+        self.positions.get(node).map(|(_, metadata)| metadata)
+    }
+
+    /// Returns references to the children of a node, in ascending order
+    pub fn get_children(&self, node: &N) -> Vec<&N> {
+        self.positions
+            .iter()
+            .filter(|(_, (parent, _))| parent == node)
+            .map(|(child, _)| child)
+            .collect()
+    }
+
+    /// Checks if a node is an ancestor of another one
+    pub fn is_ancestor(&self, ancestor: &N, node: &N) -> bool {
+        let mut current = node;
+        while let Some(parent) = self.get_parent(current) {
+            if parent == ancestor {
+                return true;
+            }
+            current = parent;
+        }
+
+        false
+    }
+
+    /// Returns references to all applied operations, ordered by their timestamps
+    pub fn get_operations(&self) -> Vec<&MoveOperation<N, M>> {
+        self.log.iter().map(|entry| &entry.operation).collect()
+    }
+
+    /// Moves a node below a new parent on behalf of a replica, returning the operation
+    ///
+    /// The operation is timestamped after all operations applied so far, so it
+    /// always takes effect locally (unless it would create a cycle).
+    pub fn move_node(
+        &mut self,
+        parent: N,
+        metadata: M,
+        child: N,
+        replica: &str,
+    ) -> MoveOperation<N, M> {
+        let mut operation = MoveOperation::new(parent, metadata, child, replica);
+
+        // Never move back in time
+        if let Some(last) = self.log.last() {
+            if operation.timestamp <= last.operation.timestamp {
+                operation.timestamp = last.operation.timestamp + chrono::Duration::nanoseconds(1);
+            }
+        }
+
+        self.apply(operation.clone());
+        operation
+    }
+
+    /// Applies an operation (e.g. received from another replica)
+    ///
+    /// Applying a known operation again doesn't change anything.
+    pub fn apply(&mut self, operation: MoveOperation<N, M>) {
+        if self.log.iter().any(|entry| entry.operation == operation) {
+            return;
+        }
+
+        // Undo all later operations
+        let mut undone = vec![];
+        while let Some(last) = self.log.last() {
+            if last.operation.key() < operation.key() {
+                break;
+            }
+            // Unwraps safely because there's a last entry
+            let entry = self.log.pop().unwrap();
+            self.undo(&entry);
+            undone.push(entry);
+        }
+
+        // Apply the operation and redo the later ones
+        self.do_operation(operation);
+        self.redo(undone);
+    }
+
+    /// Applies several operations in any order
+    pub fn apply_all(&mut self, operations: Vec<MoveOperation<N, M>>) {
+        for operation in operations {
+            self.apply(operation);
+        }
+    }
+
+    /// Applies an operation after all others, logging the previous position of the node
+    fn do_operation(&mut self, operation: MoveOperation<N, M>) {
+        let old_position = self.positions.get(&operation.child).cloned();
+
+        // Skip operations creating cycles
+        if operation.child != operation.parent
+            && !self.is_ancestor(&operation.child, &operation.parent)
+        {
+            self.positions.insert(
+                operation.child.clone(),
+                (operation.parent.clone(), operation.metadata.clone()),
+            );
+        }
+
+        self.log.push(LogEntry {
+            operation,
+            old_position,
+        });
+    }
+
+    /// Reverts the effect of the last logged operation
+    fn undo(&mut self, entry: &LogEntry<N, M>) {
+        match &entry.old_position {
+            Some(position) => {
+                self.positions
+                    .insert(entry.operation.child.clone(), position.clone());
+            }
+            None => {
+                self.positions.remove(&entry.operation.child);
+            }
+        }
+    }
+
+    /// Applies undone operations again (given in reverse order)
+    fn redo(&mut self, undone: Vec<LogEntry<N, M>>) {
+        for entry in undone.into_iter().rev() {
+            self.do_operation(entry.operation);
+        }
+    }
+}
+
+impl<N, M> Default for Tree<N, M>
+where
+    N: Clone + Ord,
+    M: Clone + PartialEq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}